@@ -1,9 +1,11 @@
 //! Parts for "combiners".  Combiners are custom user-defined macros for our
 //! notation/format/language.
 
+use core::{fmt, ops::Deref};
+
 use crate::{
     Datum, Error, TextBase,
-    parser::DatumAllocator,
+    parser::{DatumAllocator, Scope},
 };
 
 
@@ -13,10 +15,11 @@ use crate::{
 /// types used to refer to the functions.
 ///
 /// While these parameters as defined here can allow possibly inconsistent
-/// types, further bounds on these are required by a `Parser`'s
-/// [`OperatorBindings`](trait.OperatorBindings.html) which ensures that only
-/// consistent ones can be used with it, which is the only intended use of this
-/// type.
+/// types, further bounds on these, [`Operate`](trait.Operate.html) and
+/// [`Apply`](trait.Apply.html), are required by a `Parser`'s
+/// [`OperatorBindings`](../parser/trait.OperatorBindings.html) which ensures
+/// that only consistent ones can be used with it, which is the only intended
+/// use of this type.
 #[derive(Debug)]
 pub enum Combiner<OperativeRef, ApplicativeRef> {
     /// An "operative" combiner, which is given unparsed operands text.
@@ -43,6 +46,30 @@ pub type OpFn<DA, CE> = dyn Fn(DADatum<DA>, <DA as DatumAllocator>::TT, &mut DA)
 /// value.
 pub type ApFn<DA, CE> = dyn Fn(DADatum<DA>, DADatum<DA>, &mut DA) -> Result<DA, CE>;
 
+/// The type of "operative" functions that are also given the [`Context`] of
+/// the form.  Like [`OpFn`] except that the third argument is the `Context`,
+/// which also provides the `Parser`'s `DatumAllocator` and the user context of
+/// the `UC` type.  Use these via [`WithContext`].
+///
+/// [`Context`]: struct.Context.html
+/// [`OpFn`]: type.OpFn.html
+/// [`WithContext`]: struct.WithContext.html
+pub type OpCtxFn<DA, CE, UC = ()> = dyn Fn(DADatum<DA>, <DA as DatumAllocator>::TT,
+                                           &mut Context<'_, DA, CE, UC>)
+                                           -> Result<DA, CE>;
+
+/// The type of "applicative" functions that are also given the [`Context`] of
+/// the form.  Like [`ApFn`] except that the third argument is the `Context`,
+/// which also provides the `Parser`'s `DatumAllocator` and the user context of
+/// the `UC` type.  Use these via [`WithContext`].
+///
+/// [`Context`]: struct.Context.html
+/// [`ApFn`]: type.ApFn.html
+/// [`WithContext`]: struct.WithContext.html
+pub type ApCtxFn<DA, CE, UC = ()> = dyn Fn(DADatum<DA>, DADatum<DA>,
+                                           &mut Context<'_, DA, CE, UC>)
+                                           -> Result<DA, CE>;
+
 /// The type returned by "operative" and "applicative" functions.  For a
 /// successful `Some` return, the returned `Datum` is substituted for the
 /// original form by the parser in the AST it yields.  For a successful `None`
@@ -56,3 +83,247 @@ pub type Result<DA, CE>
 type DADatum<DA> = Datum<<DA as DatumAllocator>::TT,
                          <DA as DatumAllocator>::ET,
                          <DA as DatumAllocator>::DR>;
+
+
+/// The ability of the references in [`Combiner::Operative`] to be called by a
+/// `Parser`.
+///
+/// This is implemented for all references to [`OpFn`]s, which are given only
+/// the `DatumAllocator` of the `context` and so are used when there is no user
+/// context, and for [`WithContext`] references to [`OpCtxFn`]s, which are given
+/// the entire `context`.
+///
+/// [`Combiner::Operative`]: enum.Combiner.html#variant.Operative
+/// [`OpFn`]: type.OpFn.html
+/// [`WithContext`]: struct.WithContext.html
+/// [`OpCtxFn`]: type.OpCtxFn.html
+pub trait Operate<DA, CE, UC = ()>
+    where DA: DatumAllocator,
+{
+    /// Call the referenced operative function.  See [`OpFn`](type.OpFn.html)
+    /// for the description of the arguments and return value.
+    ///
+    /// # Errors
+    ///
+    /// Whatever `Error` the operative function returns.
+    fn operate(&self, operator: DADatum<DA>, operands: DA::TT,
+               context: &mut Context<'_, DA, CE, UC>)
+               -> Result<DA, CE>;
+}
+
+/// The ability of the references in [`Combiner::Applicative`] to be called by
+/// a `Parser`.
+///
+/// This is implemented for all references to [`ApFn`]s, which are given only
+/// the `DatumAllocator` of the `context` and so are used when there is no user
+/// context, and for [`WithContext`] references to [`ApCtxFn`]s, which are given
+/// the entire `context`.
+///
+/// [`Combiner::Applicative`]: enum.Combiner.html#variant.Applicative
+/// [`ApFn`]: type.ApFn.html
+/// [`WithContext`]: struct.WithContext.html
+/// [`ApCtxFn`]: type.ApCtxFn.html
+pub trait Apply<DA, CE, UC = ()>
+    where DA: DatumAllocator,
+{
+    /// Call the referenced applicative function.  See [`ApFn`](type.ApFn.html)
+    /// for the description of the arguments and return value.
+    ///
+    /// # Errors
+    ///
+    /// Whatever `Error` the applicative function returns.
+    fn apply(&self, operator: DADatum<DA>, operands: DADatum<DA>,
+             context: &mut Context<'_, DA, CE, UC>)
+             -> Result<DA, CE>;
+}
+
+/// This keeps all references to [`OpFn`]s, as were required before `Operate`
+/// existed, usable as the `OR` type of `OperatorBindings` without a user
+/// context.  (It is not generic over the user context type, because then the
+/// type of that could not be inferred when such bindings are used directly.)
+///
+/// [`OpFn`]: type.OpFn.html
+impl<R, DA, CE> Operate<DA, CE> for R
+    where R: Deref<Target = OpFn<DA, CE>>,
+          DA: DatumAllocator,
+{
+    #[inline]
+    fn operate(&self, operator: DADatum<DA>, operands: DA::TT,
+               context: &mut Context<'_, DA, CE>)
+               -> Result<DA, CE>
+    {
+        (**self)(operator, operands, context.allocator)
+    }
+}
+
+/// This keeps all references to [`ApFn`]s, as were required before `Apply`
+/// existed, usable as the `AR` type of `OperatorBindings` without a user
+/// context.
+///
+/// [`ApFn`]: type.ApFn.html
+impl<R, DA, CE> Apply<DA, CE> for R
+    where R: Deref<Target = ApFn<DA, CE>>,
+          DA: DatumAllocator,
+{
+    #[inline]
+    fn apply(&self, operator: DADatum<DA>, operands: DADatum<DA>,
+             context: &mut Context<'_, DA, CE>)
+             -> Result<DA, CE>
+    {
+        (**self)(operator, operands, context.allocator)
+    }
+}
+
+
+/// Wraps a reference to an [`OpCtxFn`] or an [`ApCtxFn`], so that it can be
+/// used in a [`Combiner`] and be given the [`Context`] of the forms it is
+/// called for.
+///
+/// E.g. `Combiner::Operative(WithContext(Box::new(|operator, operands, ctx|
+/// ...)))` with `OperatorBindings` whose `OR` type is
+/// `WithContext<Box<OpCtxFn<DA, CE>>>`.
+///
+/// [`OpCtxFn`]: type.OpCtxFn.html
+/// [`ApCtxFn`]: type.ApCtxFn.html
+/// [`Combiner`]: enum.Combiner.html
+/// [`Context`]: struct.Context.html
+#[derive(Copy, Clone, Debug)]
+pub struct WithContext<R>(pub R);
+
+impl<R, DA, CE, UC> Operate<DA, CE, UC> for WithContext<R>
+    where R: Deref<Target = OpCtxFn<DA, CE, UC>>,
+          DA: DatumAllocator,
+{
+    #[inline]
+    fn operate(&self, operator: DADatum<DA>, operands: DA::TT,
+               context: &mut Context<'_, DA, CE, UC>)
+               -> Result<DA, CE>
+    {
+        (*self.0)(operator, operands, context)
+    }
+}
+
+impl<R, DA, CE, UC> Apply<DA, CE, UC> for WithContext<R>
+    where R: Deref<Target = ApCtxFn<DA, CE, UC>>,
+          DA: DatumAllocator,
+{
+    #[inline]
+    fn apply(&self, operator: DADatum<DA>, operands: DADatum<DA>,
+             context: &mut Context<'_, DA, CE, UC>)
+             -> Result<DA, CE>
+    {
+        (*self.0)(operator, operands, context)
+    }
+}
+
+
+/// Information about the nested form that a combiner is called for, and the
+/// `Parser`'s `DatumAllocator`, and the user context.  The `CE` type parameter
+/// is the combiner error type of the bindings, which the [`Scope`] chain of
+/// them involves.
+///
+/// The user context is the value given to
+/// [`Parser::parse_with_context`](../struct.Parser.html#method.parse_with_context),
+/// or `()` when parsing without one.  It enables combiners to accumulate
+/// results, e.g. symbol tables or counters, during a parse.
+///
+/// The combiner functions themselves are `Fn`, not `FnMut`, because the
+/// bindings that hold them are only borrowed immutably while parsing (e.g. by
+/// all the frames of a `Scope` chain at once), and because a combiner can be
+/// called again, by a sub-parse, while it is running.  So mutable state should
+/// be kept in the user context, which each call is lent mutably, instead of in
+/// the combiners.
+///
+/// [`Scope`]: ../parser/struct.Scope.html
+pub struct Context<'c, DA, CE, UC = ()>
+    where DA: DatumAllocator,
+{
+    /// The `Parser`'s `DatumAllocator`.
+    pub allocator: &'c mut DA,
+    /// The user context.
+    pub user: &'c mut UC,
+    /// The positions of the form's start and end delimiter characters.
+    pub span: Span<<DA::TT as TextBase>::Pos>,
+    /// The nesting depth of the form, where 1 is a top-level form.
+    pub depth: usize,
+    /// The innermost of the forms that enclose the form, if any.
+    pub enclosing: Option<&'c Enclosing<'c, DA>>,
+    /// The chain of scopes of bindings that applies to the operands of the
+    /// form, which includes the child scope of the operator, if any.  An
+    /// operative can parse its operands in it, with bindings of its own pushed
+    /// as a new frame, via
+    /// [`SubParser::parse_list_in`](../struct.SubParser.html#method.parse_list_in).
+    pub scope: &'c Scope<'c, DA, CE, UC>,
+}
+
+impl<DA, CE, UC> Context<'_, DA, CE, UC>
+    where DA: DatumAllocator,
+{
+    /// Iterate over the operator sub-forms of the forms that enclose the form,
+    /// starting with the innermost and proceeding outward.
+    #[inline]
+    pub fn enclosing_operators(&self) -> impl Iterator<Item = &DADatum<DA>> {
+        let mut next = self.enclosing;
+        core::iter::from_fn(move || {
+            let cur = next?;
+            next = cur.parent;
+            Some(cur.operator)
+        })
+    }
+}
+
+impl<DA, CE, UC> fmt::Debug for Context<'_, DA, CE, UC>
+    where DA: DatumAllocator + fmt::Debug,
+          UC: fmt::Debug,
+          <DA::TT as TextBase>::Pos: fmt::Debug,
+          DADatum<DA>: fmt::Debug,
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Context")
+         .field("allocator", &self.allocator)
+         .field("user", &self.user)
+         .field("span", &self.span)
+         .field("depth", &self.depth)
+         .field("enclosing", &self.enclosing)
+         .field("scope", &self.scope)
+         .finish()
+    }
+}
+
+
+/// The positions of the start and end delimiter characters of a nested form.
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+pub struct Span<SourcePosition> {
+    /// Position of the nest start character.
+    pub start: SourcePosition,
+    /// Position of the nest end character.
+    pub end: SourcePosition,
+}
+
+
+/// A frame of the chain of nested forms that enclose the operands being parsed,
+/// as given in a combiner's [`Context`](struct.Context.html).
+///
+/// Only forms that have an operator, i.e. that are not empty nests, and whose
+/// operands are recursively parsed, i.e. that are applicative or unbound, can
+/// enclose other forms.
+pub struct Enclosing<'e, DA>
+    where DA: DatumAllocator,
+{
+    /// The operator sub-form of the enclosing form.
+    pub operator: &'e DADatum<DA>,
+    /// The frame of the form that encloses this one, if any.
+    pub parent: Option<&'e Enclosing<'e, DA>>,
+}
+
+impl<DA> fmt::Debug for Enclosing<'_, DA>
+    where DA: DatumAllocator,
+          DADatum<DA>: fmt::Debug,
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Enclosing")
+         .field("operator", &self.operator)
+         .field("parent", &self.parent)
+         .finish()
+    }
+}
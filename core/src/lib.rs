@@ -72,9 +72,6 @@
 // both into a hybrid and where there are two complementary ways of processing
 // forms like in Kernel.
 
-// TODO: Impl `Text` (and so a SourceStream too) for:
-// - &[char] (which will also work for Vec<char> outside this crate with `std`)
-
 // FUTURE: When/if the `generic_associated_types` feature of Rust becomes
 // stable, use it so all the text chunk and char iterators can be generic and
 // defined by the implementors and have the needed access to the lifetimes of
@@ -121,7 +118,10 @@
 )]
 
 
-use parser::{CharClassifier, DatumAllocator, AllocError, OperatorBindings};
+use core::fmt;
+
+use parser::{CharClassifier, DatumAllocator, AllocError, OperatorBindings, Scope};
+use combiner::{Context, Span, Enclosing};
 
 
 mod error;
@@ -142,10 +142,13 @@ pub use combiner::Combiner;
 
 pub mod parser;
 
+pub mod scan;
+
 
 /// Implementations provided for ready use.
 pub mod premade {
     pub mod inmem;
+    pub mod inmem_chars;
 
     /// Useful when omitting the positional information is desired/required.
     impl super::SourcePosition for () {
@@ -248,7 +251,6 @@ impl<CC, DA, OB> Parser<CC, DA, OB>
     where CC: CharClassifier,
           DA: DatumAllocator,
           DA::TT: TextConcat<DA>,
-          OB: OperatorBindings<DA>,
 {
     /// The primary method.  Parse the given text source, according to the
     /// specific parameterization of our `Self`, and return an iterator that
@@ -256,8 +258,235 @@ impl<CC, DA, OB> Parser<CC, DA, OB>
     #[inline]
     pub fn parse<S>(&mut self, source: S) -> ParseIter<'_, Self, S>
         where S: SourceStream<DA>,
+              OB: OperatorBindings<DA>,
+    {
+        ParseIter::new(self, source, ())
+    }
+
+    /// Like [`parse`](#method.parse), but also give the given user context
+    /// value to the returned iterator, which lends it, as the `user` field of
+    /// the [`Context`](combiner/struct.Context.html), to each combiner it
+    /// calls.  This enables combiners to share and mutate state across all the
+    /// forms of a parse, e.g. to number footnotes or to collect definitions.
+    /// The value can be accessed, or taken back, via the iterator.
+    #[inline]
+    pub fn parse_with_context<S, UC>(&mut self, source: S, user: UC)
+                                     -> ParseIter<'_, Self, S, UC>
+        where S: SourceStream<DA>,
+              OB: OperatorBindings<DA, UC>,
     {
-        ParseIter::new(self, source)
+        ParseIter::new(self, source, user)
+    }
+
+    /// Make a [`SubParser`](struct.SubParser.html) that borrows our parts.
+    #[inline]
+    pub fn sub_parser(&mut self) -> SubParser<'_, CC, DA, OB> {
+        SubParser::new(&self.classifier, &mut self.allocator, &self.bindings)
+    }
+}
+
+
+/// A parser made of borrowed parts, which enables combiners to re-enter parsing
+/// on texts they are given, e.g. on their operands text, with a classifier and
+/// bindings of their choosing while sharing the `DatumAllocator` they were
+/// given.
+///
+/// This parses exactly like a [`Parser`](struct.Parser.html) that has the same
+/// parts.  Because `Text`s can be iterated as `SourceStream`s that yield the
+/// positions of their characters in the original source, a sub-parse of an
+/// operands text produces `Datum`s and `Error`s with positions that are correct
+/// relative to the original source, not to the operands text.  The operands text
+/// was delimited according to the classifier of the enclosing parser, and so
+/// only characters inside it are affected by a different classifier.
+///
+/// Escapes are processed again by a sub-parse.  An operands text given by the
+/// enclosing parser no longer has the escape characters that were in its
+/// source, and the characters that they escaped are in it as they are.  So a
+/// character that must be taken as text by a sub-parse of the operands must be
+/// escaped twice in the original source, i.e. the escape character must itself
+/// be escaped, e.g. `\\\{` with the default classifier, which the enclosing
+/// parse makes `\{` and the sub-parse makes `{`.  A combiner that sub-parses
+/// its operands should document this for its forms.
+///
+/// By default, the parsed forms are top-level forms, like those of a `Parser`.
+/// A combiner that sub-parses its operands should use [`within`] so that the
+/// [`Context`]s given to the combiners of the forms inside have the nesting
+/// depth and the enclosing forms that continue from its own.
+///
+/// [`within`]: #method.within
+/// [`Context`]: combiner/struct.Context.html
+pub struct SubParser<'a, CC, DA, OB>
+    where DA: DatumAllocator,
+{
+    /// The character classifier. Determines which `char`s are the format's
+    /// delimiters.
+    pub classifier: &'a CC,
+    /// The `Datum` allocator. Determines how and where returned AST nodes are
+    /// allocated.
+    pub allocator: &'a mut DA,
+    /// The operator bindings. Determines which, if any, operator forms are
+    /// bound to macros.
+    pub bindings: &'a OB,
+    /// The nesting depth of the form that the parsed text is inside of, or 0
+    /// if it is not inside any.
+    pub depth: usize,
+    /// The innermost of the forms that the parsed text is inside of, if any.
+    pub enclosing: Option<&'a Enclosing<'a, DA>>,
+}
+
+impl<'a, CC, DA, OB> SubParser<'a, CC, DA, OB>
+    where CC: CharClassifier,
+          DA: DatumAllocator,
+          DA::TT: TextConcat<DA>,
+{
+    /// Make a new instance of `Self` from the given parts, which parses
+    /// top-level forms.
+    #[inline]
+    pub fn new(classifier: &'a CC, allocator: &'a mut DA, bindings: &'a OB) -> Self {
+        Self { classifier, allocator, bindings, depth: 0, enclosing: None }
+    }
+
+    /// Make `self` parse forms as nested inside the form of the given nesting
+    /// depth which is the innermost of the given enclosing forms.
+    ///
+    /// A combiner that sub-parses its operands should give the `depth` of its
+    /// [`Context`] and an [`Enclosing`] frame for its own form, e.g.
+    /// `sub.within(ctx.depth, Some(&Enclosing { operator: &operator, parent:
+    /// ctx.enclosing }))`, like a `Parser` does when it parses the operands of
+    /// an applicative or unbound form.
+    ///
+    /// [`Context`]: combiner/struct.Context.html
+    /// [`Enclosing`]: combiner/struct.Enclosing.html
+    #[inline]
+    #[must_use]
+    pub fn within(self, depth: usize, enclosing: Option<&'a Enclosing<'a, DA>>) -> Self {
+        Self { depth, enclosing, ..self }
+    }
+
+    /// Parse the given text source, like
+    /// [`Parser::parse`](struct.Parser.html#method.parse), and return an
+    /// iterator that yields each top-level form as a `Datum` AST.
+    #[inline]
+    pub fn parse<S>(&mut self, source: S) -> ParseIter<'_, Self, S>
+        where S: SourceStream<DA>,
+              OB: OperatorBindings<DA>,
+    {
+        self.parse_with_context(source, ())
+    }
+
+    /// Like [`parse`](#method.parse), but with the given user context value,
+    /// like [`Parser::parse_with_context`](struct.Parser.html#method.parse_with_context).
+    #[inline]
+    pub fn parse_with_context<S, UC>(&mut self, source: S, user: UC)
+                                     -> ParseIter<'_, Self, S, UC>
+        where S: SourceStream<DA>,
+              OB: OperatorBindings<DA, UC>,
+    {
+        let nest_depth = NestDepth { base: self.depth, nested: 0 };
+        ParseIter {
+            parser: self,
+            src_strm: source,
+            nest_depth,
+            user,
+        }
+    }
+
+    /// Parse all of the given text source and return its top-level forms as a
+    /// `Datum::List`, or as a `Datum::EmptyList` if there are none, like the
+    /// list of arguments that applicative combiners are given.
+    ///
+    /// # Errors
+    ///
+    /// If parsing any of the forms fails, the first `Error` is returned.
+    #[inline]
+    pub fn parse_list<S>(&mut self, source: S) -> ParseIterItem<DA, OB>
+        where S: SourceStream<DA>,
+              OB: OperatorBindings<DA>,
+    {
+        self.parse_list_with_context(source, &mut ())
+    }
+
+    /// Like [`parse_list`](#method.parse_list), but lend the given user context
+    /// to the combiners called.  A combiner that does a sub-parse can give the
+    /// `user` field of the `Context` it was given, to continue sharing the
+    /// state of the enclosing parse.
+    ///
+    /// # Errors
+    ///
+    /// If parsing any of the forms fails, the first `Error` is returned.
+    #[inline]
+    pub fn parse_list_with_context<S, UC>(&mut self, mut source: S, user: &mut UC)
+                                          -> ParseIterItem<DA, OB, UC>
+        where S: SourceStream<DA>,
+              OB: OperatorBindings<DA, UC>,
+    {
+        ParseIter::<'_, Parser<CC, DA, OB>, S, UC>::parse_all(ParseTextMode::Base,
+                                                              &mut source,
+                                                              &mut NestDepth {
+                                                                  base: self.depth,
+                                                                  nested: 0,
+                                                              },
+                                                              self.allocator,
+                                                              user,
+                                                              self.classifier,
+                                                              &Scope::new(self.bindings),
+                                                              self.enclosing)
+    }
+
+    /// Like [`parse_list_with_context`](#method.parse_list_with_context), but
+    /// our bindings are a new frame on the given [`Scope`] chain, and so the
+    /// combiners bound in the chain also apply, unless our bindings shadow
+    /// them.  The frame applies only while the source is parsed.
+    ///
+    /// This enables an operative to install bindings of its own choosing, for
+    /// the forms in its operands, on the chain of its own form, which is the
+    /// `scope` of its [`Context`], e.g.
+    /// `sub.within(ctx.depth, Some(&here)).parse_list_in(operands.iter(),
+    /// ctx.scope, ctx.user)`.
+    ///
+    /// # Errors
+    ///
+    /// If parsing any of the forms fails, the first `Error` is returned.
+    ///
+    /// [`Scope`]: parser/struct.Scope.html
+    /// [`Context`]: combiner/struct.Context.html
+    #[inline]
+    pub fn parse_list_in<S, UC>(&mut self, mut source: S,
+                                scope: &Scope<'_, DA, OB::CE, UC>, user: &mut UC)
+                                -> ParseIterItem<DA, OB, UC>
+        where S: SourceStream<DA>,
+              OB: OperatorBindings<DA, UC>,
+    {
+        ParseIter::<'_, Parser<CC, DA, OB>, S, UC>::parse_all(ParseTextMode::Base,
+                                                              &mut source,
+                                                              &mut NestDepth {
+                                                                  base: self.depth,
+                                                                  nested: 0,
+                                                              },
+                                                              self.allocator,
+                                                              user,
+                                                              self.classifier,
+                                                              &scope.child(self.bindings),
+                                                              self.enclosing)
+    }
+}
+
+/// Must implement this manually because deriving would place unwanted bounds on
+/// the type parameters.
+impl<CC, DA, OB> fmt::Debug for SubParser<'_, CC, DA, OB>
+    where CC: fmt::Debug,
+          DA: DatumAllocator + fmt::Debug,
+          OB: fmt::Debug,
+          ParseDatum<DA>: fmt::Debug,
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("SubParser")
+         .field("classifier", &self.classifier)
+         .field("allocator", &self.allocator)
+         .field("bindings", &self.bindings)
+         .field("depth", &self.depth)
+         .field("enclosing", &self.enclosing)
+         .finish()
     }
 }
 
@@ -269,42 +498,111 @@ impl<CC, DA, OB> Parser<CC, DA, OB>
 /// [`Error`](enum.Error.html), according to the given
 /// [`Parser`](struct.Parser.html)'s parameterization.
 #[derive(Debug)]
-pub struct ParseIter<'p, Prsr, SrcStrm> {
+pub struct ParseIter<'p, Prsr, SrcStrm, UC = ()> {
     parser: &'p mut Prsr,
     src_strm: SrcStrm,
-    nest_depth: usize,
+    nest_depth: NestDepth,
+    user: UC,
+}
+
+impl<Prsr, SrcStrm, UC> ParseIter<'_, Prsr, SrcStrm, UC> {
+    /// The user context value that is lent to the combiners.
+    #[inline]
+    pub fn user_context(&self) -> &UC {
+        &self.user
+    }
+
+    /// Mutable access to the user context value, e.g. to change it inbetween
+    /// the forms yielded.
+    #[inline]
+    pub fn user_context_mut(&mut self) -> &mut UC {
+        &mut self.user
+    }
+
+    /// Consume `self` and return the user context value, e.g. to get the state
+    /// accumulated by combiners after iterating all forms.
+    #[inline]
+    pub fn into_user_context(self) -> UC {
+        self.user
+    }
 }
 
-impl<'p, CC, DA, OB, S>
+impl<'p, CC, DA, OB, S, UC>
     Iterator
-    for ParseIter<'p, Parser<CC, DA, OB>, S>
+    for ParseIter<'p, Parser<CC, DA, OB>, S, UC>
     where CC: CharClassifier,
           DA: DatumAllocator,
           DA::TT: TextConcat<DA>,
-          OB: OperatorBindings<DA>,
+          OB: OperatorBindings<DA, UC>,
           Parser<CC, DA, OB>: 'p,
           S: SourceStream<DA>,
 {
-    type Item = ParseIterItem<DA, OB>;
+    type Item = ParseIterItem<DA, OB, UC>;
 
     fn next(&mut self) -> Option<Self::Item> {
         self.do_next().transpose()
     }
 }
 
+impl<CC, DA, OB, S, UC>
+    Iterator
+    for ParseIter<'_, SubParser<'_, CC, DA, OB>, S, UC>
+    where CC: CharClassifier,
+          DA: DatumAllocator,
+          DA::TT: TextConcat<DA>,
+          OB: OperatorBindings<DA, UC>,
+          S: SourceStream<DA>,
+{
+    type Item = ParseIterItem<DA, OB, UC>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let SubParser{classifier, allocator, bindings, enclosing, ..} = self.parser;
+        ParseIter::<'_, Parser<CC, DA, OB>, S, UC>::parse_next(ParseTextMode::Base,
+                                                               &mut self.src_strm,
+                                                               &mut self.nest_depth,
+                                                               allocator,
+                                                               &mut self.user,
+                                                               classifier,
+                                                               &Scope::new(*bindings),
+                                                               *enclosing)
+            .transpose()
+    }
+}
+
 /// The type of values given by the parser iterator
-pub type ParseIterItem<DA, OB> = ParseResult<DA, OB>;
+pub type ParseIterItem<DA, OB, UC = ()> = ParseResult<DA, OB, UC>;
 
 type ParseDatum<DA> = Datum<<DA as DatumAllocator>::TT,
                             <DA as DatumAllocator>::ET,
                             <DA as DatumAllocator>::DR>;
 
-type ParseError<DA, OB> = Error<<<DA as DatumAllocator>::TT as TextBase>::Pos,
-                                <OB as OperatorBindings<DA>>::CE>;
+type ParseError<DA, OB, UC> = Error<<<DA as DatumAllocator>::TT as TextBase>::Pos,
+                                    <OB as OperatorBindings<DA, UC>>::CE>;
+
+type ParseResult<DA, OB, UC> = Result<ParseDatum<DA>, ParseError<DA, OB, UC>>;
 
-type ParseResult<DA, OB> = Result<ParseDatum<DA>, ParseError<DA, OB>>;
+type ParseResultOption<DA, OB, UC> = Result<Option<ParseDatum<DA>>,
+                                            ParseError<DA, OB, UC>>;
 
-type ParseResultOption<DA, OB> = Result<Option<ParseDatum<DA>>, ParseError<DA, OB>>;
+type ParseScope<'s, DA, OB, UC> = Scope<'s, DA, <OB as OperatorBindings<DA, UC>>::CE, UC>;
+
+/// The nesting depth of the forms being parsed, as the depth relative to where
+/// the parse started, which determines the validity of nest end characters,
+/// and the depth of where it started, e.g. inside the form whose operands a
+/// `SubParser` parses.
+#[derive(Copy, Clone, Debug)]
+struct NestDepth {
+    base: usize,
+    nested: usize,
+}
+
+impl NestDepth {
+    /// The depth of the current form among all the forms of the source.
+    #[inline]
+    fn absolute(self) -> usize {
+        self.base + self.nested
+    }
+}
 
 #[derive(Copy, Clone, PartialEq, Eq, Debug)]
 enum ParseTextMode {
@@ -313,43 +611,49 @@ enum ParseTextMode {
     Operands,
 }
 
-impl<'p, CC, DA, OB, S>
-    ParseIter<'p, Parser<CC, DA, OB>, S>
+impl<'p, CC, DA, OB, S, UC>
+    ParseIter<'p, Parser<CC, DA, OB>, S, UC>
     where CC: CharClassifier,
           DA: DatumAllocator,
           DA::TT: TextConcat<DA>,
-          OB: OperatorBindings<DA>,
+          OB: OperatorBindings<DA, UC>,
           Parser<CC, DA, OB>: 'p,
           S: SourceStream<DA>,
 {
     #[inline]
-    fn new(parser: &'p mut Parser<CC, DA, OB>, src_strm: S) -> Self {
+    fn new(parser: &'p mut Parser<CC, DA, OB>, src_strm: S, user: UC) -> Self {
         Self {
             parser,
             src_strm,
-            nest_depth: 0,
+            nest_depth: NestDepth { base: 0, nested: 0 },
+            user,
         }
     }
 
     #[inline]
-    fn do_next(&mut self) -> ParseResultOption<DA, OB> {
+    fn do_next(&mut self) -> ParseResultOption<DA, OB, UC> {
         Self::parse_next(ParseTextMode::Base,
                          &mut self.src_strm,
                          &mut self.nest_depth,
                          &mut self.parser.allocator,
+                         &mut self.user,
                          &self.parser.classifier,
-                         &self.parser.bindings)
+                         &Scope::new(&self.parser.bindings),
+                         None)
     }
 
+    #[allow(clippy::too_many_arguments)]
     fn parse_next(
         mode: ParseTextMode,
         srcstrm: &mut S,
-        ndepth: &mut usize,
+        ndepth: &mut NestDepth,
         dalloc: &mut DA,
+        user: &mut UC,
         chcls: &CC,
-        bindings: &OB,
+        scope: &ParseScope<'_, DA, OB, UC>,
+        outer: Option<&Enclosing<'_, DA>>,
     )
-        -> ParseResultOption<DA, OB>
+        -> ParseResultOption<DA, OB, UC>
     {
         loop {
             if mode == ParseTextMode::Operator {
@@ -360,7 +664,7 @@ impl<'p, CC, DA, OB, S>
             let ch = match srcstrm.peek() {
                 Some(&SourceIterItem{ch, ..}) => ch,
                 None =>
-                    return if *ndepth == 0 {
+                    return if ndepth.nested == 0 {
                         Ok(None)
                     } else {
                         Err(Error::MissingEndChar)
@@ -369,10 +673,10 @@ impl<'p, CC, DA, OB, S>
             // Start of a nest, either a combination or an empty nest. Parse it
             // to its end and return it if its combiner didn't remove it.
             if chcls.is_nest_start(ch) {
-                *ndepth += 1;
+                ndepth.nested += 1;
                 let result = Self::parse_nested(srcstrm, ndepth,
-                                                dalloc, chcls, bindings);
-                *ndepth -= 1;
+                                                dalloc, user, chcls, scope, outer);
+                ndepth.nested -= 1;
                 // If a combiner indicated to remove the nest form, continue our
                 // loop to parse the next form, effectively removing the current
                 // nest form.  Else, return the result whatever it is.
@@ -385,7 +689,7 @@ impl<'p, CC, DA, OB, S>
             // End of a nest, or error. Don't parse nor return an item, only
             // check validity.
             else if chcls.is_nest_end(ch) {
-                return Self::check_end_char(srcstrm, *ndepth, chcls).map(|_| None)
+                return Self::check_end_char(srcstrm, ndepth.nested, chcls).map(|_| None)
             }
             // Start of a text. Parse it to its end and return it.
             else {
@@ -399,11 +703,11 @@ impl<'p, CC, DA, OB, S>
     fn parse_text(
         mode: ParseTextMode,
         srcstrm: &mut S,
-        ndepth: &mut usize,
+        ndepth: &mut NestDepth,
         dalloc: &mut DA,
         chcls: &CC,
     )
-        -> Result<DA::TT, ParseError<DA, OB>>
+        -> Result<DA::TT, ParseError<DA, OB, UC>>
     {
         #[inline]
         fn is_end_char<CC>(ch: char, chclass: &CC, mode: ParseTextMode) -> bool
@@ -465,7 +769,7 @@ impl<'p, CC, DA, OB, S>
                     srcstrm.next_accum(dalloc)?;
                     nest_level -= 1;
                 } else {
-                    Self::check_end_char(srcstrm, *ndepth, chcls)?;
+                    Self::check_end_char(srcstrm, ndepth.nested, chcls)?;
                     break;
                 }
             }
@@ -483,34 +787,36 @@ impl<'p, CC, DA, OB, S>
         }
     }
 
-    #[allow(unused_results)]
+    #[allow(unused_results, clippy::too_many_arguments)]
     fn parse_nested(
         srcstrm: &mut S,
-        ndepth: &mut usize,
+        ndepth: &mut NestDepth,
         dalloc: &mut DA,
+        user: &mut UC,
         chcls: &CC,
-        bindings: &OB,
+        scope: &ParseScope<'_, DA, OB, UC>,
+        outer: Option<&Enclosing<'_, DA>>,
     )
-        -> ParseResultOption<DA, OB>
+        -> ParseResultOption<DA, OB, UC>
     {
         let end = |ss: &mut S| {
             // Consume our nest's end char. A missing end char is possible, but
             // an erroneous non-end char shouldn't be.
-            if let Some(SourceIterItem{ch, ..}) = ss.next() {
+            if let Some(SourceIterItem{ch, pos}) = ss.next() {
                 debug_assert!(chcls.is_nest_end(ch));
-                Ok(())
+                Ok(pos)
             } else {
                 Err(Error::MissingEndChar)
             }
         };
 
-        // Advance past nest start char.
-        let start = srcstrm.next();
-        debug_assert_eq!(start.map(|SourceIterItem{ch, ..}| chcls.is_nest_start(ch)),
-                         Some(true));
+        // Advance past nest start char. This `unwrap` will never fail because
+        // our caller already did `peek` and knows there is a next.
+        let SourceIterItem{ch, pos: start} = srcstrm.next().unwrap();
+        debug_assert!(chcls.is_nest_start(ch));
         // Parse form in operator position, or empty.
         let operator = Self::parse_next(ParseTextMode::Operator, srcstrm, ndepth,
-                                        dalloc, chcls, bindings)?;
+                                        dalloc, user, chcls, scope, outer)?;
         // If operator delimited by following whitespace, advance past first
         // whitespace char.
         if let Some(&SourceIterItem{ch, ..}) = srcstrm.peek() {
@@ -518,8 +824,19 @@ impl<'p, CC, DA, OB, S>
         }
         // Determine the result.
         Ok(if let Some(operator) = operator {
+            // If the operator has a child scope of bindings, they apply, in
+            // addition to the enclosing ones, while parsing our operands.
+            let child;
+            let inner = if let Some(bindings) = scope.lookup_scope(&operator) {
+                child = scope.child(bindings);
+                &child
+            } else {
+                scope
+            };
+            // Our form encloses any forms in our operands.
+            let here = Enclosing { operator: &operator, parent: outer };
             // Parse the operands according to the operator.
-            if let Some(combiner) = bindings.lookup(&operator) {
+            if let Some(combiner) = scope.lookup(&operator) {
                 // Operator is bound to a combiner macro which will process the
                 // operands and determine the return value.
                 match combiner {
@@ -528,17 +845,31 @@ impl<'p, CC, DA, OB, S>
                         // whatever they want with it.
                         let operands = Self::parse_text(ParseTextMode::Operands,
                                                         srcstrm, ndepth, dalloc, chcls)?;
-                        end(srcstrm)?;
-                        opr(operator, operands, dalloc)?
+                        let end = end(srcstrm)?;
+                        opr.operate(operator, operands, &mut Context {
+                            allocator: dalloc,
+                            span: Span { start, end },
+                            depth: ndepth.absolute(),
+                            enclosing: outer,
+                            scope: inner,
+                            user: &mut *user,
+                        })?
                     },
                     Combiner::Applicative(apl) => {
                         // Applicatives are given the recursive parse of the
                         // operands text as a list of "arguments".
                         let arguments = Self::parse_all(ParseTextMode::Base,
-                                                        srcstrm, ndepth,
-                                                        dalloc, chcls, bindings)?;
-                        end(srcstrm)?;
-                        apl(operator, arguments, dalloc)?
+                                                        srcstrm, ndepth, dalloc, user,
+                                                        chcls, inner, Some(&here))?;
+                        let end = end(srcstrm)?;
+                        apl.apply(operator, arguments, &mut Context {
+                            allocator: dalloc,
+                            span: Span { start, end },
+                            depth: ndepth.absolute(),
+                            enclosing: outer,
+                            scope: inner,
+                            user: &mut *user,
+                        })?
                     }
                 }
             } else {
@@ -546,7 +877,7 @@ impl<'p, CC, DA, OB, S>
                 // value representing the "combination" of operator and operands
                 // forms.
                 let operands = Self::parse_all(ParseTextMode::Base, srcstrm, ndepth,
-                                               dalloc, chcls, bindings)?;
+                                               dalloc, user, chcls, inner, Some(&here))?;
                 end(srcstrm)?;
                 Some(Datum::Combination {
                     operator: dalloc.new_datum(operator)?,
@@ -560,20 +891,24 @@ impl<'p, CC, DA, OB, S>
         })
     }
 
+    #[allow(clippy::too_many_arguments)]
     fn parse_all(
         mode: ParseTextMode,
         srcstrm: &mut S,
-        ndepth: &mut usize,
+        ndepth: &mut NestDepth,
         dalloc: &mut DA,
+        user: &mut UC,
         chcls: &CC,
-        bindings: &OB,
+        scope: &ParseScope<'_, DA, OB, UC>,
+        outer: Option<&Enclosing<'_, DA>>,
     )
-        -> ParseResult<DA, OB>
+        -> ParseResult<DA, OB, UC>
     {
         let mut head = Datum::EmptyList;
         let mut tail = &mut head;
         loop {
-            let it = Self::parse_next(mode, srcstrm, ndepth, dalloc, chcls, bindings)?;
+            let it = Self::parse_next(mode, srcstrm, ndepth, dalloc, user,
+                                      chcls, scope, outer)?;
             if let Some(next_it) = it {
                 *tail = Datum::List {
                     elem: dalloc.new_datum(next_it)?,
@@ -608,7 +943,7 @@ impl<'p, CC, DA, OB, S>
 
     #[inline]
     fn check_end_char(srcstrm: &mut S, ndepth: usize, chcls: &CC)
-                      -> Result<(), ParseError<DA, OB>>
+                      -> Result<(), ParseError<DA, OB, UC>>
     {
         {
             debug_assert_eq!(srcstrm.peek().map(|&SourceIterItem{ch, ..}|
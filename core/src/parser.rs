@@ -1,10 +1,10 @@
 //! Traits and types that provide the different aspects of `Parser`s'
 //! functionality.
 
-use core::ops::Deref;
+use core::fmt;
 
 use crate::{Datum, DerefTryMut, Combiner, Text, Error};
-use crate::combiner::{OpFn, ApFn};
+use crate::combiner::{Operate, Apply};
 
 
 /// Implementations provided for ready use.
@@ -21,6 +21,21 @@ pub mod premade
 
     mod pair_bindings;
     pub use pair_bindings::PairOperatorBindings;
+
+    mod str_pair_bindings;
+    pub use str_pair_bindings::StrPairOperatorBindings;
+
+    mod scoped_bindings;
+    pub use scoped_bindings::ScopedOperatorBindings;
+
+    mod layered_bindings;
+    pub use layered_bindings::LayeredOperatorBindings;
+
+    mod fallback_bindings;
+    pub use fallback_bindings::FallbackOperatorBindings;
+
+    mod disabled_bindings;
+    pub use disabled_bindings::DisabledOperatorBindings;
 }
 
 
@@ -65,6 +80,32 @@ pub trait CharClassifier {
     fn is_whitespace(&self, c: char) -> bool;
 }
 
+/// Allows using borrows of classifiers, e.g. to parse several inputs with the
+/// same one.
+impl<CC> CharClassifier for &CC
+    where CC: CharClassifier + ?Sized,
+{
+    #[inline]
+    fn is_nest_start(&self, c: char) -> bool {
+        (**self).is_nest_start(c)
+    }
+
+    #[inline]
+    fn is_nest_end(&self, c: char) -> bool {
+        (**self).is_nest_end(c)
+    }
+
+    #[inline]
+    fn is_nest_escape(&self, c: char) -> bool {
+        (**self).is_nest_escape(c)
+    }
+
+    #[inline]
+    fn is_whitespace(&self, c: char) -> bool {
+        (**self).is_whitespace(c)
+    }
+}
+
 
 /// Allocator of space for `Datum` values that a `Parser` constructs.
 ///
@@ -101,15 +142,24 @@ pub trait DatumAllocator {
 /// substitute forms in a `Parser`'s returned AST.
 ///
 /// Enables different applications to use different sets or none at all.
-pub trait OperatorBindings<DA>
+///
+/// The `UC` type parameter is the type of the user context that is given to
+/// the combiners, via their [`Context`](../combiner/struct.Context.html), when
+/// parsing with [`Parser::parse_with_context`].  It defaults to `()` which is
+/// what is given when parsing with [`Parser::parse`].  Implementations that are
+/// generic over their combiner types should also be generic over it.
+///
+/// [`Parser::parse_with_context`]: ../struct.Parser.html#method.parse_with_context
+/// [`Parser::parse`]: ../struct.Parser.html#method.parse
+pub trait OperatorBindings<DA, UC = ()>
     where DA: DatumAllocator,
 {
     /// The type of references to
     /// [`Operative`](enum.Combiner.html#variant.Operative) macro functions.
-    type OR: Deref<Target = OpFn<DA, Self::CE>>;
+    type OR: Operate<DA, Self::CE, UC>;
     /// The type of references to
     /// [`Applicative`](enum.Combiner.html#variant.Applicative) macro functions.
-    type AR: Deref<Target = ApFn<DA, Self::CE>>;
+    type AR: Apply<DA, Self::CE, UC>;
     /// The [combiner error extension](enum.Error.html#variant.FailedCombiner)
     /// type.
     type CE;
@@ -124,4 +174,226 @@ pub trait OperatorBindings<DA>
     /// operands should simply be recursively parsed.
     fn lookup(&self, operator: &Datum<DA::TT, DA::ET, DA::DR>)
               -> Option<&Combiner<Self::OR, Self::AR>>;
+
+    /// Look-up any child scope of bindings we might have associated with the
+    /// given datum, referenced by the `operator` argument, which was found in
+    /// operator position of a nested form.  If we do have one, the returned
+    /// bindings are consulted first, before falling back to the enclosing
+    /// scopes' bindings, while the operands of that form are recursively
+    /// parsed, and they no longer apply after the form ends.  This enables,
+    /// e.g., a `{table ...}` form to make `{row ...}` and `{cell ...}` forms
+    /// meaningful only inside itself.  See [`Scope`](struct.Scope.html).
+    ///
+    /// The child scope applies regardless of whether the operator is also bound
+    /// to a combiner by [`lookup`](#tymethod.lookup).  The operands of an
+    /// operative are not recursively parsed by the `Parser`, but the chain
+    /// given to the operative in its [`Context`] has the child scope, and so it
+    /// applies if the operative parses its operands in that chain.
+    ///
+    /// The returned bindings need not be of the same type as `Self`, nor have
+    /// the same combiner types, only the same combiner error type.
+    ///
+    /// The default implementation always returns `None`, i.e. no child scopes.
+    ///
+    /// [`Context`]: ../combiner/struct.Context.html
+    #[inline]
+    fn lookup_scope(&self, _operator: &Datum<DA::TT, DA::ET, DA::DR>)
+                    -> Option<&dyn ScopeBindings<DA, Self::CE, UC>>
+    {
+        None
+    }
+}
+
+/// Allows using borrows, including of trait objects, as bindings, e.g. as the
+/// child scopes of [`ScopedOperatorBindings`].
+///
+/// [`ScopedOperatorBindings`]: premade/struct.ScopedOperatorBindings.html
+impl<DA, UC, OB> OperatorBindings<DA, UC> for &OB
+    where DA: DatumAllocator,
+          OB: OperatorBindings<DA, UC> + ?Sized,
+{
+    type OR = OB::OR;
+    type AR = OB::AR;
+    type CE = OB::CE;
+
+    #[inline]
+    fn lookup(&self, operator: &Datum<DA::TT, DA::ET, DA::DR>)
+              -> Option<&Combiner<Self::OR, Self::AR>>
+    {
+        (**self).lookup(operator)
+    }
+
+    #[inline]
+    fn lookup_scope(&self, operator: &Datum<DA::TT, DA::ET, DA::DR>)
+                    -> Option<&dyn ScopeBindings<DA, Self::CE, UC>>
+    {
+        (**self).lookup_scope(operator)
+    }
+}
+
+
+/// The type of the combiners that [`ScopeBindings`] return, which refer to
+/// their operative and applicative functions as trait objects.
+///
+/// [`ScopeBindings`]: trait.ScopeBindings.html
+pub type DynCombiner<'c, DA, CE, UC = ()>
+    = Combiner<&'c dyn Operate<DA, CE, UC>, &'c dyn Apply<DA, CE, UC>>;
+
+/// [`OperatorBindings`] as the frames of [`Scope`] chains use them, i.e. with
+/// the types of the combiner references erased.
+///
+/// This is what enables frames of bindings of differing types to be in the
+/// same chain, and enables the chain to be given to combiners in their
+/// [`Context`], whose type could not otherwise involve the types of the
+/// combiner references, because those themselves involve the type of
+/// `Context`.  It is implemented for all `OperatorBindings`, and there should
+/// be no need to implement it otherwise.
+///
+/// [`OperatorBindings`]: trait.OperatorBindings.html
+/// [`Scope`]: struct.Scope.html
+/// [`Context`]: ../combiner/struct.Context.html
+pub trait ScopeBindings<DA, CE, UC = ()>
+    where DA: DatumAllocator,
+{
+    /// Like [`OperatorBindings::lookup`], but return the combiner with its
+    /// function reference as a trait object.
+    ///
+    /// [`OperatorBindings::lookup`]: trait.OperatorBindings.html#tymethod.lookup
+    fn lookup_dyn(&self, operator: &Datum<DA::TT, DA::ET, DA::DR>)
+                  -> Option<DynCombiner<'_, DA, CE, UC>>;
+
+    /// Like [`OperatorBindings::lookup_scope`].
+    ///
+    /// [`OperatorBindings::lookup_scope`]: trait.OperatorBindings.html#method.lookup_scope
+    fn lookup_scope_dyn(&self, operator: &Datum<DA::TT, DA::ET, DA::DR>)
+                        -> Option<&dyn ScopeBindings<DA, CE, UC>>;
+}
+
+impl<DA, UC, OB> ScopeBindings<DA, OB::CE, UC> for OB
+    where DA: DatumAllocator,
+          OB: OperatorBindings<DA, UC> + ?Sized,
+{
+    #[inline]
+    fn lookup_dyn(&self, operator: &Datum<DA::TT, DA::ET, DA::DR>)
+                  -> Option<DynCombiner<'_, DA, OB::CE, UC>>
+    {
+        Some(match self.lookup(operator)? {
+            Combiner::Operative(opr) => Combiner::Operative(opr),
+            Combiner::Applicative(apl) => Combiner::Applicative(apl),
+        })
+    }
+
+    #[inline]
+    fn lookup_scope_dyn(&self, operator: &Datum<DA::TT, DA::ET, DA::DR>)
+                        -> Option<&dyn ScopeBindings<DA, OB::CE, UC>>
+    {
+        self.lookup_scope(operator)
+    }
+}
+
+
+/// A frame of a chain of lexically-nested environments of
+/// [`OperatorBindings`], as used by a `Parser` while it parses.
+///
+/// The outermost frame has a `Parser`'s own bindings.  Each time a nested form
+/// has an operator for which the current chain returns a child scope from
+/// [`lookup_scope`], a new frame is pushed, whose parent is the current frame,
+/// for parsing that form's operands, and it is popped when the form ends.
+/// Looking-up tries the innermost frame first and then falls back through the
+/// parents, so inner bindings shadow outer ones.  This is like the
+/// environments of the Kernel language, but lexically extended by the forms of
+/// the parsed text.
+///
+/// Combiners can also push frames.  The chain that applies to the operands of
+/// a form is given to its combiner as the `scope` of its [`Context`], and an
+/// operative can parse its operands text with a [`SubParser`] via
+/// [`parse_list_in`], which pushes the sub-parser's bindings as a new frame on
+/// that chain while the operands are parsed and pops it after.  The frames
+/// refer to their bindings as [`ScopeBindings`] trait objects, so that the
+/// type of the chain does not involve the types of the combiner references.
+///
+/// [`OperatorBindings`]: trait.OperatorBindings.html
+/// [`lookup_scope`]: trait.OperatorBindings.html#method.lookup_scope
+/// [`Context`]: ../combiner/struct.Context.html
+/// [`SubParser`]: ../struct.SubParser.html
+/// [`parse_list_in`]: ../struct.SubParser.html#method.parse_list_in
+/// [`ScopeBindings`]: trait.ScopeBindings.html
+pub struct Scope<'s, DA, CE, UC = ()> {
+    bindings: &'s dyn ScopeBindings<DA, CE, UC>,
+    parent: Option<&'s Scope<'s, DA, CE, UC>>,
+}
+
+impl<'s, DA, CE, UC> Scope<'s, DA, CE, UC>
+    where DA: DatumAllocator,
+{
+    /// Make a new outermost frame, without a parent, that has the given
+    /// bindings.
+    #[inline]
+    pub fn new(bindings: &'s dyn ScopeBindings<DA, CE, UC>) -> Self {
+        Self { bindings, parent: None }
+    }
+
+    /// Make a new frame, whose parent is `self`, that has the given bindings.
+    #[inline]
+    pub fn child<'c>(&'c self, bindings: &'c dyn ScopeBindings<DA, CE, UC>)
+                     -> Scope<'c, DA, CE, UC>
+    {
+        Scope { bindings, parent: Some(self) }
+    }
+
+    /// The bindings of only this frame.
+    #[inline]
+    #[must_use]
+    pub fn bindings(&self) -> &'s dyn ScopeBindings<DA, CE, UC> {
+        self.bindings
+    }
+
+    /// The enclosing frame, if any.
+    #[inline]
+    #[must_use]
+    pub fn parent(&self) -> Option<&'s Scope<'s, DA, CE, UC>> {
+        self.parent
+    }
+
+    /// Iterate over the frames of the chain, starting with `self` and
+    /// proceeding outward.
+    #[inline]
+    pub fn frames(&self) -> impl Iterator<Item = &Scope<'s, DA, CE, UC>> {
+        let mut next = Some(self);
+        core::iter::from_fn(move || {
+            let cur = next?;
+            next = cur.parent;
+            Some(cur)
+        })
+    }
+
+    /// Look-up the combiner bound to the given operator by the innermost frame
+    /// that has a binding for it, if any.
+    #[inline]
+    pub fn lookup(&self, operator: &Datum<DA::TT, DA::ET, DA::DR>)
+                  -> Option<DynCombiner<'s, DA, CE, UC>>
+    {
+        self.frames().find_map(|frame| frame.bindings.lookup_dyn(operator))
+    }
+
+    /// Look-up the child scope associated with the given operator by the
+    /// innermost frame that has one for it, if any.
+    #[inline]
+    pub fn lookup_scope(&self, operator: &Datum<DA::TT, DA::ET, DA::DR>)
+                        -> Option<&'s dyn ScopeBindings<DA, CE, UC>>
+    {
+        self.frames().find_map(|frame| frame.bindings.lookup_scope_dyn(operator))
+    }
+}
+
+/// Must implement this manually because deriving would place unwanted bounds on
+/// the type parameters.
+impl<DA, CE, UC> fmt::Debug for Scope<'_, DA, CE, UC>
+    where DA: DatumAllocator,
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Scope")
+         .field("depth", &self.frames().count())
+         .finish()
+    }
 }
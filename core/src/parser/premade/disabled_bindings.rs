@@ -0,0 +1,149 @@
+use core::{borrow::Borrow, marker::PhantomData};
+
+use crate::{
+    Datum, Combiner, Text,
+    parser::{OperatorBindings, DatumAllocator, ScopeBindings},
+};
+
+
+/// An [`OperatorBindings`] that masks some operator names of some other
+/// bindings, using a slice of the names, where the names are any type that can
+/// be viewed as a `str`.
+///
+/// Looking-up an operator that is a `Datum::Text` whose text, compared by
+/// streaming its `char`s, matches one of the names returns `None`, as does
+/// looking-up a child scope for it, and so such operators are treated as
+/// unbound.  Otherwise, looking-up is delegated to the wrapped `bindings`.
+/// E.g. a document can be prevented from using some of a shared set of
+/// extensions.
+///
+/// The masking is limited.  Operators that are not `Datum::Text`s are never
+/// masked.  And only the look-ups made of `Self` itself are masked: while a
+/// `Parser` uses a [`Scope`] chain, returning `None` only means that a frame
+/// has no binding, and so a masked name is still found in any other frame
+/// that binds it, e.g. in a child scope returned by the wrapped bindings, or
+/// in the enclosing frames when `Self` is the bindings of a child scope.  To
+/// mask names everywhere, `Self` should wrap the outermost bindings and each
+/// child scope should also be wrapped in its own `DisabledOperatorBindings`.
+///
+/// [`OperatorBindings`]: ../trait.OperatorBindings.html
+/// [`Scope`]: ../struct.Scope.html
+#[derive(Debug)]
+pub struct DisabledOperatorBindings<OB, P, K>
+    where P: Borrow<[K]>,
+          K: AsRef<str>,
+{
+    /// The bindings that apply to the operators that are not masked.
+    pub bindings: OB,
+    /// The masked operator names.  You must initialize and manage this
+    /// yourself.
+    pub names: P,
+    /// We want `Self` to be parameterized over this type because this better
+    /// fits with the uses of it.
+    have_type_params: PhantomData<*const K>,
+}
+
+
+impl<OB, P, K>
+    DisabledOperatorBindings<OB, P, K>
+    where P: Borrow<[K]>,
+          K: AsRef<str>,
+{
+    /// Given some bindings and a value of a type that can borrow as a slice of
+    /// names, make a new instance of `Self` that masks the names.
+    #[inline]
+    pub fn new(bindings: OB, names: P) -> Self {
+        Self {
+            bindings,
+            names,
+            have_type_params: PhantomData,
+        }
+    }
+
+    fn is_disabled<DA>(&self, operator: &Datum<DA::TT, DA::ET, DA::DR>) -> bool
+        where DA: DatumAllocator,
+    {
+        if let Datum::Text(text) = operator {
+            self.names.borrow().iter().any(
+                |name| text.chars().eq(name.as_ref().chars()))
+        } else {
+            false
+        }
+    }
+}
+
+
+impl<OB, P, K, DA, UC>
+    OperatorBindings<DA, UC>
+    for DisabledOperatorBindings<OB, P, K>
+    where P: Borrow<[K]>,
+          K: AsRef<str>,
+          DA: DatumAllocator,
+          OB: OperatorBindings<DA, UC>,
+{
+    type OR = OB::OR;
+    type AR = OB::AR;
+    type CE = OB::CE;
+
+    #[inline]
+    fn lookup(&self, operator: &Datum<DA::TT, DA::ET, DA::DR>)
+              -> Option<&Combiner<Self::OR, Self::AR>>
+    {
+        if self.is_disabled::<DA>(operator) {
+            None
+        } else {
+            self.bindings.lookup(operator)
+        }
+    }
+
+    #[inline]
+    fn lookup_scope(&self, operator: &Datum<DA::TT, DA::ET, DA::DR>)
+                    -> Option<&dyn ScopeBindings<DA, Self::CE, UC>>
+    {
+        if self.is_disabled::<DA>(operator) {
+            None
+        } else {
+            self.bindings.lookup_scope(operator)
+        }
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        parser::premade::{SliceDatumAllocator, PairOperatorBindings,
+                          ScopedOperatorBindings},
+        text::{premade::TextDatumList, chunk::premade::PosStr},
+        combiner::{OpFn, ApFn},
+    };
+
+    type TT<'d> = TextDatumList<'d, PosStr<'static>, ()>;
+    type DA<'d> = SliceDatumAllocator<'d, TT<'d>, ()>;
+    type Op<'f, 'd> = &'f OpFn<DA<'d>, ()>;
+    type Ap<'f, 'd> = &'f ApFn<DA<'d>, ()>;
+
+    fn text(s: &'static str) -> Datum<TT<'static>, (), <DA<'static> as DatumAllocator>::DR> {
+        Datum::Text(TT::from_str(s))
+    }
+
+    #[test]
+    fn lookup() {
+        let op: Op<'_, '_> = &(|_, _, _| unreachable!());
+        let ap: Ap<'_, '_> = &(|_, _, _| unreachable!());
+        let bindings = PairOperatorBindings::new([(text("a"), Combiner::Operative(op)),
+                                                  (text("b"), Combiner::Applicative(ap)),
+                                                  (Datum::EmptyNest, Combiner::Operative(op))]);
+        let scoped = ScopedOperatorBindings::new(&bindings, [("a", &bindings),
+                                                             ("b", &bindings)]);
+        let dob = DisabledOperatorBindings::new(scoped, ["a", "c"]);
+
+        assert_eq!(dob.lookup(&text("a")).map(|_| true), None);
+        assert_eq!(dob.lookup(&text("b")).map(|_| true), Some(true));
+        assert_eq!(dob.lookup(&text("c")).map(|_| true), None);
+        assert_eq!(dob.lookup(&Datum::EmptyNest).map(|_| true), Some(true));
+        assert_eq!(dob.lookup_scope(&text("a")).map(|_| true), None);
+        assert_eq!(dob.lookup_scope(&text("b")).map(|_| true), Some(true));
+    }
+}
@@ -0,0 +1,102 @@
+use crate::{
+    Datum, Combiner,
+    parser::{OperatorBindings, DatumAllocator, ScopeBindings},
+    combiner::{Operate, Apply},
+};
+
+
+/// An [`OperatorBindings`] that binds every operator that some other bindings
+/// don't, to a single `fallback` combiner.
+///
+/// Looking-up an operator first tries the wrapped `bindings` and, if they don't
+/// bind it, returns the `fallback`.  So, when used by a `Parser`, the fallback
+/// is called for every form whose operator is otherwise unbound, including
+/// operators that are not `Datum::Text`s, instead of the parser returning a
+/// `Datum::Combination`.  The fallback is given the operator, and so it can,
+/// e.g., log or reject unknown operators, or return a `Datum::Combination` to
+/// have the same result as when unbound.  Looking-up a child scope is simply
+/// delegated to the wrapped `bindings`.
+///
+/// [`OperatorBindings`]: ../trait.OperatorBindings.html
+#[derive(Debug)]
+pub struct FallbackOperatorBindings<OB, OR, AR> {
+    /// The bindings that are tried first.
+    pub bindings: OB,
+    /// The combiner for operators that `bindings` doesn't bind.
+    pub fallback: Combiner<OR, AR>,
+}
+
+
+impl<OB, OR, AR> FallbackOperatorBindings<OB, OR, AR> {
+    /// Given some bindings and a combiner, make a new instance of `Self` that
+    /// uses the combiner for all operators that the bindings don't bind.
+    #[inline]
+    pub fn new(bindings: OB, fallback: Combiner<OR, AR>) -> Self {
+        Self { bindings, fallback }
+    }
+}
+
+
+impl<OB, OR, AR, DA, UC>
+    OperatorBindings<DA, UC>
+    for FallbackOperatorBindings<OB, OR, AR>
+    where DA: DatumAllocator,
+          OB: OperatorBindings<DA, UC, OR = OR, AR = AR>,
+          OR: Operate<DA, OB::CE, UC>,
+          AR: Apply<DA, OB::CE, UC>,
+{
+    type OR = OR;
+    type AR = AR;
+    type CE = OB::CE;
+
+    #[inline]
+    fn lookup(&self, operator: &Datum<DA::TT, DA::ET, DA::DR>)
+              -> Option<&Combiner<Self::OR, Self::AR>>
+    {
+        self.bindings.lookup(operator).or(Some(&self.fallback))
+    }
+
+    #[inline]
+    fn lookup_scope(&self, operator: &Datum<DA::TT, DA::ET, DA::DR>)
+                    -> Option<&dyn ScopeBindings<DA, Self::CE, UC>>
+    {
+        self.bindings.lookup_scope(operator)
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        parser::premade::{SliceDatumAllocator, PairOperatorBindings},
+        text::{premade::TextDatumList, chunk::premade::PosStr, Text},
+        combiner::{OpFn, ApFn},
+    };
+
+    type TT<'d> = TextDatumList<'d, PosStr<'static>, ()>;
+    type DA<'d> = SliceDatumAllocator<'d, TT<'d>, ()>;
+    type Op<'f, 'd> = &'f OpFn<DA<'d>, ()>;
+    type Ap<'f, 'd> = &'f ApFn<DA<'d>, ()>;
+
+    fn is_operative<OR, AR>(c: &Combiner<OR, AR>) -> bool {
+        matches!(c, Combiner::Operative(_))
+    }
+
+    fn text(s: &'static str) -> Datum<TT<'static>, (), <DA<'static> as DatumAllocator>::DR> {
+        Datum::Text(TT::from_str(s))
+    }
+
+    #[test]
+    fn lookup() {
+        let op: Op<'_, '_> = &(|_, _, _| unreachable!());
+        let ap: Ap<'_, '_> = &(|_, _, _| unreachable!());
+        let bindings = PairOperatorBindings::new([(text("a"), Combiner::Operative(op))]);
+        let fob = FallbackOperatorBindings::new(bindings, Combiner::Applicative(ap));
+
+        assert_eq!(fob.lookup(&text("a")).map(is_operative), Some(true));
+        assert_eq!(fob.lookup(&text("b")).map(is_operative), Some(false));
+        assert_eq!(fob.lookup(&Datum::EmptyNest).map(is_operative), Some(false));
+        assert_eq!(fob.lookup_scope(&text("b")).map(|_| true), None);
+    }
+}
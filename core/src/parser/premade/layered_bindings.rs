@@ -0,0 +1,112 @@
+use crate::{
+    Datum, Combiner,
+    parser::{OperatorBindings, DatumAllocator, ScopeBindings},
+};
+
+
+/// An [`OperatorBindings`] that combines two other bindings, where the `top`
+/// ones shadow the `bottom` ones.
+///
+/// Looking-up an operator first tries the `top` bindings and, only if they
+/// don't bind it, then tries the `bottom` bindings.  Looking-up a child scope
+/// is done the same way.  E.g. per-document overrides can be layered over a
+/// shared set of extensions, without modifying the shared set.  Since either
+/// may itself be a `LayeredOperatorBindings`, any number of layers can be
+/// stacked.
+///
+/// [`OperatorBindings`]: ../trait.OperatorBindings.html
+#[derive(Debug)]
+pub struct LayeredOperatorBindings<T, B> {
+    /// The bindings that shadow the `bottom` ones.
+    pub top: T,
+    /// The bindings that apply when the `top` ones don't bind an operator.
+    pub bottom: B,
+}
+
+
+impl<T, B> LayeredOperatorBindings<T, B> {
+    /// Given the bindings for the top and bottom layers, make a new instance
+    /// of `Self` that combines them.
+    #[inline]
+    pub fn new(top: T, bottom: B) -> Self {
+        Self { top, bottom }
+    }
+}
+
+
+impl<T, B, DA, UC>
+    OperatorBindings<DA, UC>
+    for LayeredOperatorBindings<T, B>
+    where DA: DatumAllocator,
+          T: OperatorBindings<DA, UC>,
+          B: OperatorBindings<DA, UC, OR = T::OR, AR = T::AR, CE = T::CE>,
+{
+    type OR = T::OR;
+    type AR = T::AR;
+    type CE = T::CE;
+
+    #[inline]
+    fn lookup(&self, operator: &Datum<DA::TT, DA::ET, DA::DR>)
+              -> Option<&Combiner<Self::OR, Self::AR>>
+    {
+        self.top.lookup(operator).or_else(|| self.bottom.lookup(operator))
+    }
+
+    #[inline]
+    fn lookup_scope(&self, operator: &Datum<DA::TT, DA::ET, DA::DR>)
+                    -> Option<&dyn ScopeBindings<DA, Self::CE, UC>>
+    {
+        self.top.lookup_scope(operator).or_else(|| self.bottom.lookup_scope(operator))
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        parser::premade::{SliceDatumAllocator, PairOperatorBindings,
+                          StrPairOperatorBindings, ScopedOperatorBindings},
+        text::{premade::TextDatumList, chunk::premade::PosStr, Text},
+        combiner::{OpFn, ApFn},
+    };
+
+    type TT<'d> = TextDatumList<'d, PosStr<'static>, ()>;
+    type DA<'d> = SliceDatumAllocator<'d, TT<'d>, ()>;
+    type Op<'f, 'd> = &'f OpFn<DA<'d>, ()>;
+    type Ap<'f, 'd> = &'f ApFn<DA<'d>, ()>;
+    type Names<'d, 'f, P> = StrPairOperatorBindings<P, &'static str, DA<'d>,
+                                                    Op<'f, 'd>, Ap<'f, 'd>, ()>;
+
+    fn is_operative<OR, AR>(c: &Combiner<OR, AR>) -> bool {
+        matches!(c, Combiner::Operative(_))
+    }
+
+    fn text(s: &'static str) -> Datum<TT<'static>, (), <DA<'static> as DatumAllocator>::DR> {
+        Datum::Text(TT::from_str(s))
+    }
+
+    #[test]
+    fn lookup() {
+        let op: Op<'_, '_> = &(|_, _, _| unreachable!());
+        let ap: Ap<'_, '_> = &(|_, _, _| unreachable!());
+        let shared = PairOperatorBindings::new([(text("a"), Combiner::Operative(op)),
+                                                (text("b"), Combiner::Operative(op))]);
+        let overrides = Names::new([("b", Combiner::Applicative(ap)),
+                                    ("c", Combiner::Applicative(ap))]);
+        let layered = LayeredOperatorBindings::new(overrides, &shared);
+
+        assert_eq!(layered.lookup(&text("a")).map(is_operative), Some(true));
+        assert_eq!(layered.lookup(&text("b")).map(is_operative), Some(false));
+        assert_eq!(layered.lookup(&text("c")).map(is_operative), Some(false));
+        assert_eq!(layered.lookup(&text("d")).map(|_| true), None);
+        assert_eq!(layered.lookup(&Datum::EmptyNest).map(|_| true), None);
+        assert_eq!(shared.lookup(&text("b")).map(is_operative), Some(true));
+
+        let scoped = ScopedOperatorBindings::new(Names::new([]), [("a", &shared)]);
+        let layered = LayeredOperatorBindings::new(&layered, scoped);
+        assert_eq!(layered.lookup(&text("b")).map(is_operative), Some(false));
+        assert_eq!(layered.lookup_scope(&text("a")).map(|_| true), Some(true));
+        assert_eq!(layered.lookup_scope(&text("b")).map(|_| true), None);
+    }
+}
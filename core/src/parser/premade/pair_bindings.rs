@@ -1,9 +1,9 @@
-use core::{ops::Deref, borrow::Borrow, marker::PhantomData};
+use core::{borrow::Borrow, marker::PhantomData};
 
 use crate::{
     Datum, Combiner,
     parser::{OperatorBindings, DatumAllocator},
-    combiner::{OpFn, ApFn},
+    combiner::{Operate, Apply},
 };
 
 
@@ -66,14 +66,14 @@ impl<P, DA, OR, AR, CE>
 }
 
 
-impl<P, DA, OR, AR, CE>
-    OperatorBindings<DA>
+impl<P, DA, OR, AR, CE, UC>
+    OperatorBindings<DA, UC>
     for PairOperatorBindings<P, DA, OR, AR, CE>
     where P: Borrow<[(Datum<DA::TT, DA::ET, DA::DR>, Combiner<OR, AR>)]>,
           DA: DatumAllocator,
           Datum<DA::TT, DA::ET, DA::DR>: PartialEq,
-          OR: Deref<Target = OpFn<DA, CE>>,
-          AR: Deref<Target = ApFn<DA, CE>>,
+          OR: Operate<DA, CE, UC>,
+          AR: Apply<DA, CE, UC>,
 {
     type OR = OR;
     type AR = AR;
@@ -98,6 +98,7 @@ impl<P, DA, OR, AR, CE>
 mod tests {
     use super::*;
     use crate::{
+        combiner::{OpFn, ApFn},
         parser::premade::SliceDatumAllocator,
         text::{premade::TextDatumList, chunk::premade::PosStr, Text},
         datum::premade::DatumMutRef,
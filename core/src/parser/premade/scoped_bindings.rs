@@ -0,0 +1,155 @@
+use core::{borrow::Borrow, marker::PhantomData};
+
+use crate::{
+    Datum, Combiner, Text,
+    parser::{OperatorBindings, DatumAllocator, ScopeBindings},
+};
+
+
+/// An [`OperatorBindings`] that adds child scopes to some other bindings, by
+/// associating operator names with the bindings of the scopes, using a slice of
+/// pairs (2-tuples) of them, where the names are any type that can be viewed as
+/// a `str`.
+///
+/// Looking-up combiners is simply delegated to the wrapped `bindings`.
+/// Looking-up a child scope, for an operator that is a `Datum::Text`, compares
+/// its text by streaming its `char`s against the names, and the first pair
+/// whose name matches supplies the scope.  While a `Parser` parses the operands
+/// of such a form, the scope's bindings shadow the enclosing ones, and so, e.g.,
+/// a `{table ...}` form can make `{row ...}` forms meaningful only inside
+/// itself.  A scope's bindings may themselves be `ScopedOperatorBindings`, to
+/// nest further, and they need not have the same combiner types as the wrapped
+/// `bindings`, only the same combiner error type.  Borrows of trait objects may
+/// be used for the scopes, to have scopes of differing types.
+///
+/// [`OperatorBindings`]: ../trait.OperatorBindings.html
+#[derive(Debug)]
+pub struct ScopedOperatorBindings<OB, P, K, SB>
+    where P: Borrow<[(K, SB)]>,
+          K: AsRef<str>,
+{
+    /// The bindings that apply where `Self` is used, i.e. outside the child
+    /// scopes.
+    pub bindings: OB,
+    /// The child scopes associated with operator names.  You must initialize
+    /// and manage this yourself.
+    pub scopes: P,
+    /// We want `Self` to be parameterized over these types because this better
+    /// fits with the `OperatorBindings` trait and the uses of it.
+    have_type_params: PhantomData<(*const K, *const SB)>,
+}
+
+
+impl<OB, P, K, SB>
+    ScopedOperatorBindings<OB, P, K, SB>
+    where P: Borrow<[(K, SB)]>,
+          K: AsRef<str>,
+{
+    /// Given some bindings and a value of a type that can borrow as a slice of
+    /// pairs (2-tuples) of names with the bindings of child scopes, make a new
+    /// instance of `Self` that uses them.
+    #[inline]
+    pub fn new(bindings: OB, scopes: P) -> Self {
+        Self {
+            bindings,
+            scopes,
+            have_type_params: PhantomData,
+        }
+    }
+}
+
+
+impl<OB, P, K, SB, DA, UC>
+    OperatorBindings<DA, UC>
+    for ScopedOperatorBindings<OB, P, K, SB>
+    where P: Borrow<[(K, SB)]>,
+          K: AsRef<str>,
+          DA: DatumAllocator,
+          OB: OperatorBindings<DA, UC>,
+          SB: OperatorBindings<DA, UC, CE = OB::CE>,
+{
+    type OR = OB::OR;
+    type AR = OB::AR;
+    type CE = OB::CE;
+
+    #[inline]
+    fn lookup(&self, operator: &Datum<DA::TT, DA::ET, DA::DR>)
+              -> Option<&Combiner<Self::OR, Self::AR>>
+    {
+        self.bindings.lookup(operator)
+    }
+
+    #[inline]
+    fn lookup_scope(&self, operator: &Datum<DA::TT, DA::ET, DA::DR>)
+                    -> Option<&dyn ScopeBindings<DA, Self::CE, UC>>
+    {
+        if let Datum::Text(text) = operator {
+            let (_, scope) = self.scopes.borrow().iter().find(
+                |(name, _)| text.chars().eq(name.as_ref().chars()))?;
+            Some(scope)
+        } else {
+            None
+        }
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        parser::{Scope, premade::{SliceDatumAllocator, StrPairOperatorBindings}},
+        text::{premade::TextDatumList, chunk::premade::PosStr},
+        combiner::{OpFn, ApFn},
+    };
+
+    type TT<'d> = TextDatumList<'d, PosStr<'static>, ()>;
+    type DA<'d> = SliceDatumAllocator<'d, TT<'d>, ()>;
+    type Op<'f, 'd> = &'f OpFn<DA<'d>, ()>;
+    type Ap<'f, 'd> = &'f ApFn<DA<'d>, ()>;
+    type Names<'d, 'f, P> = StrPairOperatorBindings<P, &'static str, DA<'d>,
+                                                    Op<'f, 'd>, Ap<'f, 'd>, ()>;
+
+    fn is_operative<OR, AR>(c: &Combiner<OR, AR>) -> bool {
+        matches!(c, Combiner::Operative(_))
+    }
+
+    fn text(s: &'static str) -> Datum<TT<'static>, (), <DA<'static> as DatumAllocator>::DR> {
+        Datum::Text(TT::from_str(s))
+    }
+
+    #[test]
+    fn lookup() {
+        let op: Op<'_, '_> = &(|_, _, _| unreachable!());
+        let ap: Ap<'_, '_> = &(|_, _, _| unreachable!());
+        let row = Names::new([("cell", Combiner::Applicative(ap))]);
+        let table = ScopedOperatorBindings::new(
+            Names::new([("row", Combiner::Operative(op))]),
+            [("row", row)]);
+        let root = ScopedOperatorBindings::new(
+            Names::new([("table", Combiner::Applicative(ap))]),
+            [("table", table)]);
+
+        assert_eq!(root.lookup(&text("table")).map(is_operative), Some(false));
+        assert_eq!(root.lookup(&text("row")).map(|_| true), None);
+        assert_eq!(root.lookup_scope(&text("row")).map(|_| true), None);
+        assert_eq!(root.lookup_scope(&Datum::EmptyNest).map(|_| true), None);
+
+        let scope = Scope::new(&root);
+        let table = root.lookup_scope(&text("table")).unwrap();
+        let scope = scope.child(table);
+        assert_eq!(scope.lookup(&text("row")).as_ref().map(is_operative), Some(true));
+        assert_eq!(scope.lookup(&text("table")).as_ref().map(is_operative), Some(false));
+        assert_eq!(scope.lookup(&text("cell")).map(|_| true), None);
+
+        let row = scope.lookup_scope(&text("row")).unwrap();
+        let scope = scope.child(row);
+        assert_eq!(scope.frames().count(), 3);
+        assert_eq!(scope.lookup(&text("cell")).as_ref().map(is_operative), Some(false));
+        assert_eq!(scope.lookup(&text("row")).as_ref().map(is_operative), Some(true));
+        assert_eq!(scope.lookup(&text("table")).as_ref().map(is_operative), Some(false));
+        assert_eq!(scope.lookup(&text("other")).map(|_| true), None);
+        assert_eq!(scope.parent().and_then(|p| p.lookup(&text("cell"))).map(|_| true),
+                   None);
+    }
+}
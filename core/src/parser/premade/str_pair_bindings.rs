@@ -0,0 +1,175 @@
+use core::{borrow::Borrow, marker::PhantomData};
+
+use crate::{
+    Datum, Combiner, Text,
+    parser::{OperatorBindings, DatumAllocator},
+    combiner::{Operate, Apply},
+};
+
+
+/// An [`OperatorBindings`] that associates operator names with generic
+/// `Combiner`s, using a slice of pairs (2-tuples) of them, where the names are
+/// any type that can be viewed as a `str`, e.g. `&'static str` or `String`.
+///
+/// Only operators that are `Datum::Text`s can be bound by this.  Looking-up an
+/// operator compares its text by streaming its `char`s against the names,
+/// without constructing any temporary strings or `Datum`s, and so this can be
+/// used in `no_std` environments with a fixed table, e.g. an array or a
+/// `'static` slice.  The first pair whose name matches is the one used.
+///
+/// You are responsible for initializing the slice with the desired bindings,
+/// and it may be dynamically mutated inbetween parser invocations, if desired
+/// and if you use a mutable type for it.
+///
+/// [`OperatorBindings`]: ../trait.OperatorBindings.html
+#[derive(Debug)]
+pub struct StrPairOperatorBindings<P, K, DA, OR, AR, CE>
+    where P: Borrow<[(K, Combiner<OR, AR>)]>,
+          K: AsRef<str>,
+          DA: DatumAllocator,
+{
+    /// The bindings of operator names to macros.  You must initialize and
+    /// manage this yourself.
+    pub pairs: P,
+    /// We want `Self` to be parameterized over these types because this better
+    /// fits with the `OperatorBindings` trait and the uses of it.
+    have_type_params: PhantomData<(*const K, *const DA, *const OR, *const AR,
+                                   *const CE)>,
+}
+
+
+impl<P, K, DA, OR, AR, CE>
+    StrPairOperatorBindings<P, K, DA, OR, AR, CE>
+    where P: Borrow<[(K, Combiner<OR, AR>)]>,
+          K: AsRef<str>,
+          DA: DatumAllocator,
+{
+    /// Given a value of a type that can borrow as a slice of pairs (2-tuples)
+    /// of names with `Combiner`s, make a new instance of `Self` that uses it
+    /// for its bindings.
+    #[inline]
+    pub fn new(pairs: P) -> Self {
+        Self {
+            pairs,
+            have_type_params: PhantomData,
+        }
+    }
+}
+
+
+impl<P, K, DA, OR, AR, CE>
+    Default
+    for StrPairOperatorBindings<P, K, DA, OR, AR, CE>
+    where P: Default + Borrow<[(K, Combiner<OR, AR>)]>,
+          K: AsRef<str>,
+          DA: DatumAllocator,
+{
+    /// Make a new instance of `Self` using the `Default::default()` of our
+    /// pairs' type.
+    #[inline]
+    fn default() -> Self {
+        Self::new(P::default())
+    }
+}
+
+
+impl<P, K, DA, OR, AR, CE, UC>
+    OperatorBindings<DA, UC>
+    for StrPairOperatorBindings<P, K, DA, OR, AR, CE>
+    where P: Borrow<[(K, Combiner<OR, AR>)]>,
+          K: AsRef<str>,
+          DA: DatumAllocator,
+          OR: Operate<DA, CE, UC>,
+          AR: Apply<DA, CE, UC>,
+{
+    type OR = OR;
+    type AR = AR;
+    type CE = CE;
+
+    #[inline]
+    fn lookup(&self, operator: &Datum<DA::TT, DA::ET, DA::DR>)
+              -> Option<&Combiner<OR, AR>>
+    {
+        if let Datum::Text(text) = operator {
+            self.pairs.borrow().iter().find_map(
+                |(name, combiner)|
+                if text.chars().eq(name.as_ref().chars()) {
+                    Some(combiner)
+                } else {
+                    None
+                })
+        } else {
+            None
+        }
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        combiner::{OpFn, ApFn},
+        parser::premade::SliceDatumAllocator,
+        text::{premade::TextDatumList, chunk::premade::PosStr, TextConcat},
+        datum::premade::DatumMutRef,
+    };
+
+    type TT<'d> = TextDatumList<'d, PosStr<'static>, ()>;
+    type DA<'d> = SliceDatumAllocator<'d, TT<'d>, ()>;
+    type Op<'f, 'd> = &'f OpFn<DA<'d>, ()>;
+    type Ap<'f, 'd> = &'f ApFn<DA<'d>, ()>;
+    type Bindings<'d, 'f, P> = StrPairOperatorBindings<P, &'static str, DA<'d>,
+                                                       Op<'f, 'd>, Ap<'f, 'd>, ()>;
+
+    fn is_operative<OR, AR>(c: &Combiner<OR, AR>) -> bool {
+        matches!(c, Combiner::Operative(_))
+    }
+
+    #[test]
+    fn empty_array() {
+        let spob = Bindings::new([]);
+        assert_eq!(spob.lookup(&Datum::EmptyNest).map(|_| true), None);
+        assert_eq!(spob.lookup(&Datum::Text(TT::from_str(""))).map(|_| true), None);
+    }
+
+    #[test]
+    fn full_array() {
+        let op: Op<'_, '_> = &(|_, _, _| unreachable!());
+        let ap: Ap<'_, '_> = &(|_, _, _| unreachable!());
+        let spob = Bindings::new([("foo", Combiner::Operative(op)),
+                              ("", Combiner::Applicative(ap)),
+                              ("foo", Combiner::Applicative(ap)),
+                              ("λ x", Combiner::Applicative(ap))]);
+
+        assert_eq!(spob.lookup(&Datum::EmptyNest).map(|_| true), None);
+        assert_eq!(spob.lookup(&Datum::Text(TT::from_str("foo"))).map(is_operative),
+                   Some(true));
+        assert_eq!(spob.lookup(&Datum::Text(TT::from_str(""))).map(is_operative),
+                   Some(false));
+        assert_eq!(spob.lookup(&Datum::Text(TT::from_str("λ x"))).map(is_operative),
+                   Some(false));
+        assert_eq!(spob.lookup(&Datum::Text(TT::from_str("fo"))).map(|_| true),
+                   None);
+        assert_eq!(spob.lookup(&Datum::Text(TT::from_str("fooo"))).map(|_| true),
+                   None);
+        let (mut d1, mut d2) = (Datum::Text(TT::from_str("foo")), Datum::EmptyList);
+        assert_eq!(spob.lookup(&Datum::Combination {
+                                   operator: DatumMutRef(&mut d1),
+                                   operands: DatumMutRef(&mut d2),
+                               })
+                       .map(|_| true),
+                   None);
+    }
+
+    #[test]
+    fn chunked_text() {
+        let ap: Ap<'_, '_> = &(|_, _, _| unreachable!());
+        let spob = Bindings::new([("foobar", Combiner::Applicative(ap))]);
+        let mut arr = [Datum::EmptyList];
+        let mut dalloc = DA::new(&mut arr[..]);
+        let text = TT::from_str("foo").concat(TT::from_str("bar"), &mut dalloc)
+                                      .unwrap();
+        assert_eq!(spob.lookup(&Datum::Text(text)).map(is_operative), Some(false));
+    }
+}
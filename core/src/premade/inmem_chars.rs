@@ -0,0 +1,253 @@
+//! A `Parser` for parsing inputs that are in-memory `char` slices, for no-heap
+//! constrained applications.
+//!
+//! This is like the [`inmem`](../inmem/index.html) module but for inputs that
+//! are already decoded to `char`s, e.g. from UTF-32 sources or terminal
+//! emulators, which are parsed zero-copy without re-encoding them as UTF-8.
+
+use core::iter::TakeWhile;
+
+use crate::{
+    Parser as ParserStruct, ParseIter, ParseIterItem, Combiner, Error, Text as _,
+    parser::premade::{SliceDatumAllocator, PairOperatorBindings},
+    text::{self, premade::TextDatumList, chunk::premade::{PosChars, CharsPos}},
+    datum::premade::{MutRefDatum, DatumMutRef},
+    combiner::{OpFn, ApFn},
+};
+
+
+#[doc(no_inline)]
+pub use crate::parser::premade::DefaultCharClassifier as CharClassifier;
+
+
+/// Chosen so that the referenced parts of an input slice are zero-copy, and so
+/// that `char` positions are tracked (relative to the input `&[char]`), and so
+/// that, when chunks are broken around escape characters, a `Datum` list is
+/// used to logically concatenate them.
+///
+/// This `Text` type is a `TextDatumList` of `PosChars` chunks.
+pub type Text<'input, 'alloc, Extra = ()>
+    = TextDatumList<'alloc, PosChars<'input>, Extra>;
+
+/// Like [`inmem::DatumAllocator`] but for our `Text` type.
+///
+/// [`inmem::DatumAllocator`]: ../inmem/type.DatumAllocator.html
+pub type DatumAllocator<'input, 'alloc, Extra = ()>
+    = SliceDatumAllocator<'alloc, Text<'input, 'alloc, Extra>, Extra>;
+
+/// Like [`inmem::DatumSlice`] but for our `Datum` type.
+///
+/// [`inmem::DatumSlice`]: ../inmem/type.DatumSlice.html
+pub type DatumSlice<'input, 'alloc, Extra = ()>
+    = &'alloc mut [DatumType<'input, 'alloc, Extra>];
+
+/// Like [`inmem::DatumType`] but for our `Text` type.
+///
+/// [`inmem::DatumType`]: ../inmem/type.DatumType.html
+pub type DatumType<'input, 'alloc, Extra = ()>
+    = MutRefDatum<'alloc, Text<'input, 'alloc, Extra>, Extra>;
+
+/// Like [`inmem::DatumRef`] but for our `Text` type.
+///
+/// [`inmem::DatumRef`]: ../inmem/type.DatumRef.html
+pub type DatumRef<'input, 'alloc, Extra = ()>
+    = DatumMutRef<'alloc, Text<'input, 'alloc, Extra>, Extra>;
+
+/// Like [`inmem::OperatorBindings`] but for our `Datum` type.
+///
+/// [`inmem::OperatorBindings`]: ../inmem/type.OperatorBindings.html
+pub type OperatorBindings<'input, 'alloc, 'funs, 'bind, Extra = (), CombinerError = ()>
+    = PairOperatorBindings<
+          BindingsSlice<'input, 'alloc, 'funs, 'bind, Extra, CombinerError>,
+          DatumAllocator<'input, 'alloc, Extra>,
+          OperativeRef<'input, 'alloc, 'funs, Extra, CombinerError>,
+          ApplicativeRef<'input, 'alloc, 'funs, Extra, CombinerError>,
+          CombinerError
+      >;
+
+/// Like [`inmem::BindingsSlice`] but for our `Datum` type.
+///
+/// [`inmem::BindingsSlice`]: ../inmem/type.BindingsSlice.html
+pub type BindingsSlice<'input, 'alloc, 'funs, 'bind, Extra = (), CombinerError = ()>
+    = &'bind [BindingsPair<'input, 'alloc, 'funs, Extra, CombinerError>];
+
+/// Like [`inmem::BindingsPair`] but for our `Datum` type.
+///
+/// [`inmem::BindingsPair`]: ../inmem/type.BindingsPair.html
+pub type BindingsPair<'input, 'alloc, 'funs, Extra = (), CombinerError = ()>
+    = (
+        // The operator sub-form
+        DatumType<'input, 'alloc, Extra>,
+        // The macro function bound to the operator
+        Combiner<OperativeRef<'input, 'alloc, 'funs, Extra, CombinerError>,
+                 ApplicativeRef<'input, 'alloc, 'funs, Extra, CombinerError>>
+    );
+
+/// Like [`inmem::OperativeRef`] but for our `DatumAllocator` type.
+///
+/// [`inmem::OperativeRef`]: ../inmem/type.OperativeRef.html
+pub type OperativeRef<'input, 'alloc, 'funs, Extra = (), CombinerError = ()>
+    = &'funs OpFn<DatumAllocator<'input, 'alloc, Extra>,
+                  CombinerError>;
+
+/// Like [`inmem::ApplicativeRef`] but for our `DatumAllocator` type.
+///
+/// [`inmem::ApplicativeRef`]: ../inmem/type.ApplicativeRef.html
+pub type ApplicativeRef<'input, 'alloc, 'funs, Extra = (), CombinerError = ()>
+    = &'funs ApFn<DatumAllocator<'input, 'alloc, Extra>,
+                  CombinerError>;
+
+/// Like [`inmem::Parser`] but for inputs that are in-memory `char` slices, as
+/// `&[char]`s wrapped by this module's [`Text`] type.
+///
+/// [`inmem::Parser`]: ../inmem/type.Parser.html
+/// [`Text`]: type.Text.html
+pub type Parser<'input, 'alloc, 'funs, 'bind, Extra = (), CombinerError = ()>
+    = ParserStruct<
+          CharClassifier,
+          DatumAllocator<'input, 'alloc, Extra>,
+          OperatorBindings<'input, 'alloc, 'funs, 'bind, Extra, CombinerError>
+      >;
+
+/// Like [`inmem::TopFormResult`] but for our `Parser` type.
+///
+/// [`inmem::TopFormResult`]: ../inmem/type.TopFormResult.html
+pub type TopFormResult<'input, 'alloc, 'funs, 'bind, Extra = (), CombinerError = ()>
+    = ParseIterItem<
+          DatumAllocator<'input, 'alloc, Extra>,
+          OperatorBindings<'input, 'alloc, 'funs, 'bind, Extra, CombinerError>
+      >;
+
+/// Like [`inmem::parser`] but makes our `Parser` type.
+///
+/// [`inmem::parser`]: ../inmem/fn.parser.html
+#[inline]
+pub fn parser<'input, 'alloc, 'funs, 'bind, Extra, CombinerError>(
+    alloc_from: DatumSlice<'input, 'alloc, Extra>,
+    bind_pairs: BindingsSlice<'input, 'alloc, 'funs, 'bind, Extra, CombinerError>
+)
+    -> Parser<'input, 'alloc, 'funs, 'bind, Extra, CombinerError>
+where
+    Extra: Eq,
+{
+    ParserStruct {
+        classifier: CharClassifier,
+        allocator: DatumAllocator::new(alloc_from),
+        bindings: OperatorBindings::new(bind_pairs),
+    }
+}
+
+/// Like [`inmem::parser_no_bind`] but makes our `Parser` type.
+///
+/// [`inmem::parser_no_bind`]: ../inmem/fn.parser_no_bind.html
+#[inline]
+pub fn parser_no_bind<'input, 'alloc, 'funs, 'bind>(
+    alloc_from: DatumSlice<'input, 'alloc>,
+)
+    -> Parser<'input, 'alloc, 'funs, 'bind>
+{
+    parser(alloc_from, &[])
+}
+
+/// Like [`inmem::parse_text_with`] but for our `Text` and `Parser` types.
+///
+/// [`inmem::parse_text_with`]: ../inmem/fn.parse_text_with.html
+#[allow(clippy::type_complexity)]
+pub fn parse_text_with<'t, 'p, 'input, 'alloc, 'funs, 'bind, Extra, CombinerError>(
+    input: &'t Text<'input, 'alloc, Extra>,
+    parser: &'p mut Parser<'input, 'alloc, 'funs, 'bind, Extra, CombinerError>,
+)
+    -> ParseTextWith<'t, 'p, 'input, 'alloc, 'funs, 'bind, Extra, CombinerError,
+                     impl FnMut(&Result<DatumType<'input, 'alloc, Extra>,
+                                        Error<CharsPos<'input>, CombinerError>>)
+                                -> bool>
+where
+    Extra: Eq,
+{
+    let input_source_stream = input.iter();
+    let mut already_errored = false;
+    parser.parse(input_source_stream)
+          .take_while(move |r|
+                      if already_errored {
+                          false
+                      } else {
+                          if r.is_err() {
+                              already_errored = true;
+                          }
+                          true
+                      })
+}
+
+type ParseTextWith<'text, 'parser, 'input, 'alloc, 'funs, 'bind,
+                   Extra, CombinerError,
+                   Predicate>
+    = TakeWhile<ParseIter<'parser,
+                          Parser<'input, 'alloc, 'funs, 'bind,
+                                 Extra, CombinerError>,
+                          text::iter::Iter<'text, Text<'input, 'alloc, Extra>>>,
+                Predicate>;
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Datum, datum::premade::DatumMutRef};
+
+    #[test]
+    fn parser_no_bind() {
+        let input = ['{', 'b', ' ', 'c', '\\', '}', 'd', '}', ' ', 'λ', '}'];
+        let operands = ['c', '}', 'd'];
+        let mut dtb = Datum::Text(Text::from_chunkish(&['b'][..]));
+        let mut dto = Datum::Text(Text::from_chunkish(&operands[..]));
+        let mut del = Datum::EmptyList;
+        let mut dl = Datum::List { elem: DatumMutRef(&mut dto), next: DatumMutRef(&mut del) };
+
+        let a: DatumSlice<'_, '_>
+            = &mut [Datum::Extra(()), Datum::Extra(()), Datum::Extra(()),
+                    Datum::Extra(()), Datum::Extra(()), Datum::Extra(())];
+        let mut p = super::parser_no_bind(a);
+        let text = Text::from_chunkish(&input[..]);
+        let mut it = super::parse_text_with(&text, &mut p);
+
+        // The escaped operands text is broken into chunks, which are
+        // concatenated.
+        assert_eq!(it.next(), Some(Ok(Datum::Combination {
+                                          operator: DatumMutRef(&mut dtb),
+                                          operands: DatumMutRef(&mut dl),
+                                      })));
+        assert_eq!(it.next(), Some(Err(Error::UnbalancedEndChar(
+                                           CharsPos { src: &input, char_pos: 10 }))));
+        assert_eq!(it.next(), None);
+    }
+
+    #[test]
+    fn parse_text_with() {
+        let a: DatumSlice<'_, '_, i8> = &mut [Datum::Extra(0), Datum::Extra(0)];
+        let op = ['o', 'p'];
+        let b: BindingsSlice<'_, '_, '_, '_, i8, bool> = &[
+            (Datum::Text(Text::from_chunkish(&op[..])),
+             Combiner::Operative(&(|_, _, _| Ok(Some(Datum::Extra(1)))))),
+        ];
+        let mut p = parser(a, b);
+        let input = "{op ignored}a".chars().collect::<TextChars>();
+        let text = Text::from_chunkish(&input.0[.. input.1]);
+        let mut it = super::parse_text_with(&text, &mut p);
+        assert_eq!(it.next(), Some(Ok(Datum::Extra(1))));
+        assert_eq!(it.next(), Some(Ok(Datum::Text(Text::from_chunkish(&['a'][..])))));
+        assert_eq!(it.next(), None);
+    }
+
+    /// A fixed-capacity buffer of `char`s, because `Vec` isn't available.
+    struct TextChars([char; 32], usize);
+
+    impl core::iter::FromIterator<char> for TextChars {
+        fn from_iter<I: IntoIterator<Item = char>>(iter: I) -> Self {
+            let mut buf = TextChars([' '; 32], 0);
+            for ch in iter {
+                buf.0[buf.1] = ch;
+                buf.1 += 1;
+            }
+            buf
+        }
+    }
+}
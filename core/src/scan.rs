@@ -0,0 +1,568 @@
+//! A small parser-combinator toolkit for operatives that parse their operands
+//! text, e.g. `{map foo: 8.7, bar: asdf}`.
+//!
+//! A [`Scanner`](struct.Scanner.html) consumes any
+//! [`SourceStream`](../trait.SourceStream.html), e.g. the [`text::Iter`] of an
+//! operands `Text`, and provides parsers for tokens, identifiers, numbers, and
+//! runs of characters, and combinators for delimited, separated, repeated,
+//! optional, and alternative parsers.  The texts it produces are sub-texts of
+//! the input, i.e. zero-copy, and so their characters keep their positions in
+//! the original source, and its errors have the positions of where they
+//! happened.  It doesn't need heap allocation, and so it can also be used with
+//! `no_std`.
+//!
+//! The parsers only look ahead one character.  Like in Parsec, a parser that
+//! fails without consuming any characters lets the combinators try something
+//! else, but a parser that fails after consuming characters makes the whole
+//! parse fail.
+//!
+//! [`text::Iter`]: ../text/iter/struct.Iter.html
+
+use core::fmt;
+
+use crate::{TextBase, SourceStream, SourceIterItem, SourcePosition};
+use crate::parser::{DatumAllocator, AllocError};
+use crate::text::parse::{FromText, ParseError};
+
+
+/// The type of our errors for the given `DatumAllocator`.
+pub type DAScanError<DA> = ScanError<<<DA as DatumAllocator>::TT as TextBase>::Pos>;
+
+/// The result type of our parsers for the given `DatumAllocator`.
+pub type ScanResult<T, DA> = Result<T, DAScanError<DA>>;
+
+
+/// The possible errors of scanning.
+///
+/// The positions are those of the characters that were found instead of what
+/// was expected.  At the end of the input, they are the position of the last
+/// character, or empty if there are no characters.
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+pub enum ScanError<SourcePosition> {
+    /// Something else was expected.
+    Expected {
+        /// Describes what was expected.
+        expected: &'static str,
+        /// What was found instead, or `None` at the end of the input.
+        found: Option<char>,
+        /// Where it was found.
+        pos: SourcePosition,
+    },
+    /// A number was not valid.
+    Invalid {
+        /// Why.
+        error: ParseError,
+        /// The position of the start of the number.
+        pos: SourcePosition,
+    },
+    /// Making a sub-text failed.
+    FailedAlloc(AllocError),
+}
+
+impl<P> From<AllocError> for ScanError<P> {
+    #[inline]
+    fn from(ae: AllocError) -> Self {
+        ScanError::FailedAlloc(ae)
+    }
+}
+
+
+/// Parses a `SourceStream`.  See the [module documentation](index.html).
+///
+/// The parsers are methods, and the combinators are methods that take closures
+/// that are given the `Scanner`, so custom parsers are simply closures or
+/// functions of `&mut Scanner`.
+pub struct Scanner<'a, DA, S>
+    where DA: DatumAllocator,
+{
+    stream: S,
+    dalloc: &'a mut DA,
+    /// How many characters have been consumed, to know whether failed parsers
+    /// consumed any.
+    consumed: usize,
+    /// The position of the last consumed character.
+    last_pos: Option<<DA::TT as TextBase>::Pos>,
+}
+
+/// Must implement this manually because deriving would place unwanted bounds
+/// on the type parameters.
+impl<DA, S> fmt::Debug for Scanner<'_, DA, S>
+    where DA: DatumAllocator,
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Scanner").field("consumed", &self.consumed).finish()
+    }
+}
+
+impl<'a, DA, S> Scanner<'a, DA, S>
+    where DA: DatumAllocator,
+          S: SourceStream<DA>,
+{
+    /// Make a new one that consumes the given `stream` and that uses the given
+    /// `dalloc` to concatenate sub-texts when needed.
+    #[inline]
+    pub fn new(stream: S, dalloc: &'a mut DA) -> Self {
+        Self { stream, dalloc, consumed: 0, last_pos: None }
+    }
+
+    /// Return the next character without consuming it.
+    #[inline]
+    pub fn peek(&mut self) -> Option<char> {
+        self.stream.peek().map(|it| it.ch)
+    }
+
+    /// Return the position of the next character, or at the end of the input
+    /// the position of the last character, or empty if there are no
+    /// characters.
+    pub fn pos(&mut self) -> <DA::TT as TextBase>::Pos {
+        match (self.stream.peek(), &self.last_pos) {
+            (Some(it), _) => it.pos.clone(),
+            (None, Some(pos)) => pos.clone(),
+            (None, None) => SourcePosition::empty(),
+        }
+    }
+
+    /// Return how many characters have been consumed so far.
+    #[inline]
+    pub fn consumed(&self) -> usize {
+        self.consumed
+    }
+
+    /// Return an error that says that `expected` was expected at the next
+    /// character.
+    pub fn expected(&mut self, expected: &'static str) -> DAScanError<DA> {
+        ScanError::Expected { expected, found: self.peek(), pos: self.pos() }
+    }
+
+    fn record(&mut self, item: Option<SourceIterItem<<DA::TT as TextBase>::Pos>>)
+              -> Option<char>
+    {
+        item.map(|SourceIterItem{ch, pos}| {
+            self.consumed += 1;
+            self.last_pos = Some(pos);
+            ch
+        })
+    }
+
+    /// Consume and return the next character.
+    #[allow(clippy::should_implement_trait)]
+    #[inline]
+    pub fn next(&mut self) -> Option<char> {
+        let item = self.stream.next();
+        self.record(item)
+    }
+
+    /// Consume the next character if it is `ch`, and return whether it was.
+    pub fn eat(&mut self, ch: char) -> bool {
+        if self.peek() == Some(ch) {
+            let _ = self.next();
+            true
+        } else {
+            false
+        }
+    }
+
+    /// Consume the characters of `token`.
+    ///
+    /// # Errors
+    ///
+    /// If the next characters are not `token`, return an error at the first
+    /// one that differs.
+    pub fn token(&mut self, token: &'static str) -> ScanResult<(), DA> {
+        for ch in token.chars() {
+            if !self.eat(ch) {
+                return Err(self.expected(token))
+            }
+        }
+        Ok(())
+    }
+
+    /// Consume the characters for which `pred` is true, up to the first for
+    /// which it is false, and return how many.
+    pub fn skip_while<P>(&mut self, mut pred: P) -> usize
+        where P: FnMut(char) -> bool,
+    {
+        let start = self.consumed;
+        while matches!(self.peek(), Some(c) if pred(c)) {
+            let _ = self.next();
+        }
+        self.consumed - start
+    }
+
+    /// Consume any whitespace.
+    #[inline]
+    pub fn skip_ws(&mut self) {
+        let _ = self.skip_while(char::is_whitespace);
+    }
+
+    /// Consume the characters for which `pred` is true, up to the first for
+    /// which it is false, and return them as a sub-text, which is possibly
+    /// empty.
+    ///
+    /// # Errors
+    ///
+    /// If making the sub-text fails, return the error.
+    pub fn take_while<P>(&mut self, mut pred: P) -> ScanResult<DA::TT, DA>
+        where P: FnMut(char) -> bool,
+    {
+        while matches!(self.peek(), Some(c) if pred(c)) {
+            let item = self.stream.next_accum(self.dalloc)?;
+            let _ = self.record(item);
+        }
+        Ok(self.stream.accum_done(self.dalloc)?)
+    }
+
+    /// Like [`take_while`](#method.take_while) but at least one character must
+    /// be taken.
+    ///
+    /// # Errors
+    ///
+    /// If the next character is not one for which `pred` is true, return an
+    /// error that says that `expected` was expected.
+    pub fn take_while1<P>(&mut self, expected: &'static str, mut pred: P)
+                          -> ScanResult<DA::TT, DA>
+        where P: FnMut(char) -> bool,
+    {
+        if matches!(self.peek(), Some(c) if pred(c)) {
+            self.take_while(pred)
+        } else {
+            Err(self.expected(expected))
+        }
+    }
+
+    /// Consume an identifier, i.e. an alphabetic character or `_` followed by
+    /// any alphanumeric characters or `_` or `-`, and return it as a sub-text.
+    ///
+    /// # Errors
+    ///
+    /// If there isn't one, return an error.
+    pub fn ident(&mut self) -> ScanResult<DA::TT, DA> {
+        if matches!(self.peek(), Some(c) if c.is_alphabetic() || c == '_') {
+            self.take_while(|c| c.is_alphanumeric() || c == '_' || c == '-')
+        } else {
+            Err(self.expected("identifier"))
+        }
+    }
+
+    /// Consume a number, i.e. the characters that can be part of integer or
+    /// float literals, and parse it as `N`, e.g. `i64` or `f64`.
+    ///
+    /// # Errors
+    ///
+    /// If there isn't a number, or if it isn't valid for `N`, return an error.
+    pub fn number<N>(&mut self) -> ScanResult<N, DA>
+        where N: FromText,
+    {
+        let pos = self.pos();
+        let text = self.take_while1("number", |c| c.is_ascii_digit()
+                                                   || "+-.eE".contains(c))?;
+        N::from_text(&text).map_err(|error| ScanError::Invalid { error, pos })
+    }
+
+    /// Require the end of the input.
+    ///
+    /// # Errors
+    ///
+    /// If there are more characters, return an error.
+    pub fn end(&mut self) -> ScanResult<(), DA> {
+        match self.peek() {
+            None => Ok(()),
+            Some(_) => Err(self.expected("end")),
+        }
+    }
+
+    /// Parse with `parser` between the tokens `open` and `close`, allowing
+    /// whitespace inside them, and return what `parser` returns.
+    ///
+    /// # Errors
+    ///
+    /// If a token is missing or `parser` fails, return the error.
+    pub fn delimited<T, F>(&mut self, open: &'static str, close: &'static str,
+                           parser: F)
+                           -> ScanResult<T, DA>
+        where F: FnOnce(&mut Self) -> ScanResult<T, DA>,
+    {
+        self.token(open)?;
+        self.skip_ws();
+        let inner = parser(self)?;
+        self.skip_ws();
+        self.token(close)?;
+        Ok(inner)
+    }
+
+    /// Parse with `parser`, and if it fails without consuming any characters,
+    /// return `None` instead.
+    ///
+    /// # Errors
+    ///
+    /// If `parser` fails after consuming characters, return the error.
+    pub fn optional<T, F>(&mut self, parser: F) -> ScanResult<Option<T>, DA>
+        where F: FnOnce(&mut Self) -> ScanResult<T, DA>,
+    {
+        let start = self.consumed;
+        match parser(self) {
+            Ok(it) => Ok(Some(it)),
+            Err(_) if self.consumed == start => Ok(None),
+            Err(e) => Err(e),
+        }
+    }
+
+    /// Parse with `first`, and if it fails without consuming any characters,
+    /// parse with `second` instead.
+    ///
+    /// # Errors
+    ///
+    /// If `first` fails after consuming characters, or if `second` fails,
+    /// return the error.
+    pub fn or<T, F, G>(&mut self, first: F, second: G) -> ScanResult<T, DA>
+        where F: FnOnce(&mut Self) -> ScanResult<T, DA>,
+              G: FnOnce(&mut Self) -> ScanResult<T, DA>,
+    {
+        match self.optional(first)? {
+            Some(it) => Ok(it),
+            None => second(self),
+        }
+    }
+
+    /// Construct a new iterator that parses with `parser` repeatedly until it
+    /// fails without consuming any characters, and that yields each result.
+    /// If it fails after consuming characters, the error is yielded last.
+    #[inline]
+    pub fn many<T, F>(&mut self, parser: F) -> Many<'_, 'a, DA, S, F>
+        where F: FnMut(&mut Self) -> ScanResult<T, DA>,
+    {
+        Many { scanner: self, parser, done: false }
+    }
+
+    /// Construct a new iterator that parses zero or more items with `parser`
+    /// that are separated by the token `sep`, allowing whitespace around the
+    /// separators, and that yields each result.  The items end at the end of
+    /// the input or where the next is not `sep`.  If an item fails, its error
+    /// is yielded last.
+    #[inline]
+    pub fn sep_by<T, F>(&mut self, sep: &'static str, parser: F)
+                        -> SepBy<'_, 'a, DA, S, F>
+        where F: FnMut(&mut Self) -> ScanResult<T, DA>,
+    {
+        SepBy { scanner: self, sep, parser, state: SepByState::First }
+    }
+}
+
+
+/// An `Iterator` returned by [`Scanner::many`].
+///
+/// [`Scanner::many`]: struct.Scanner.html#method.many
+pub struct Many<'s, 'a, DA, S, F>
+    where DA: DatumAllocator,
+{
+    scanner: &'s mut Scanner<'a, DA, S>,
+    parser: F,
+    done: bool,
+}
+
+/// Must implement this manually because deriving would place unwanted bounds
+/// on the type parameters.
+impl<DA, S, F> fmt::Debug for Many<'_, '_, DA, S, F>
+    where DA: DatumAllocator,
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Many").field("done", &self.done).finish()
+    }
+}
+
+impl<'a, T, DA, S, F> Iterator for Many<'_, 'a, DA, S, F>
+    where DA: DatumAllocator,
+          S: SourceStream<DA>,
+          F: FnMut(&mut Scanner<'a, DA, S>) -> ScanResult<T, DA>,
+{
+    type Item = ScanResult<T, DA>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.done {
+            return None
+        }
+        let start = self.scanner.consumed;
+        match (self.parser)(self.scanner) {
+            Ok(it) => Some(Ok(it)),
+            Err(_) if self.scanner.consumed == start => {
+                self.done = true;
+                None
+            }
+            Err(e) => {
+                self.done = true;
+                Some(Err(e))
+            }
+        }
+    }
+}
+
+
+#[derive(Copy, Clone, Debug)]
+enum SepByState {
+    First,
+    Rest,
+    Done,
+}
+
+/// An `Iterator` returned by [`Scanner::sep_by`].
+///
+/// [`Scanner::sep_by`]: struct.Scanner.html#method.sep_by
+pub struct SepBy<'s, 'a, DA, S, F>
+    where DA: DatumAllocator,
+{
+    scanner: &'s mut Scanner<'a, DA, S>,
+    sep: &'static str,
+    parser: F,
+    state: SepByState,
+}
+
+/// Must implement this manually because deriving would place unwanted bounds
+/// on the type parameters.
+impl<DA, S, F> fmt::Debug for SepBy<'_, '_, DA, S, F>
+    where DA: DatumAllocator,
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("SepBy").field("sep", &self.sep).finish()
+    }
+}
+
+impl<'a, T, DA, S, F> Iterator for SepBy<'_, 'a, DA, S, F>
+    where DA: DatumAllocator,
+          S: SourceStream<DA>,
+          F: FnMut(&mut Scanner<'a, DA, S>) -> ScanResult<T, DA>,
+{
+    type Item = ScanResult<T, DA>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        match self.state {
+            SepByState::Done => return None,
+            SepByState::First => {
+                self.scanner.skip_ws();
+                if self.scanner.peek().is_none() {
+                    self.state = SepByState::Done;
+                    return None
+                }
+            }
+            SepByState::Rest => {
+                self.scanner.skip_ws();
+                if self.scanner.peek() != self.sep.chars().next() {
+                    self.state = SepByState::Done;
+                    return None
+                }
+                if let Err(e) = self.scanner.token(self.sep) {
+                    self.state = SepByState::Done;
+                    return Some(Err(e))
+                }
+                self.scanner.skip_ws();
+            }
+        }
+        let result = (self.parser)(self.scanner);
+        self.state = if result.is_ok() { SepByState::Rest } else { SepByState::Done };
+        Some(result)
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        Datum, Text, TextConcat,
+        parser::premade::SliceDatumAllocator,
+        text::{premade::TextDatumList, chunk::premade::{PosStr, StrPos}, iter::Iter},
+    };
+
+    type TT<'d> = TextDatumList<'d, PosStr<'static>, ()>;
+    type DA<'d> = SliceDatumAllocator<'d, TT<'d>, ()>;
+    type Sc<'t, 'a, 'd> = Scanner<'a, DA<'d>, Iter<'t, TT<'d>>>;
+
+    fn char_pos(pos: StrPos<'_>) -> usize {
+        pos.char_pos
+    }
+
+    /// A parser of `NAME: NUMBER` entries, like what a `map` operative might
+    /// use.
+    fn entry<'d>(s: &mut Sc<'_, '_, 'd>) -> ScanResult<(TT<'d>, f64), DA<'d>> {
+        let key = s.ident()?;
+        s.skip_ws();
+        s.token(":")?;
+        s.skip_ws();
+        let val = s.number()?;
+        Ok((key, val))
+    }
+
+    #[test]
+    fn map() {
+        let mut arr = [Datum::EmptyList, Datum::EmptyList];
+        let mut dalloc = DA::new(&mut arr[..]);
+        let text = TT::from_str("foo: 8.7, b").concat(TT::from_str("ar :-1 "), &mut dalloc)
+                                              .unwrap();
+        let mut s = Scanner::new(text.iter(), &mut dalloc);
+        let mut entries = s.sep_by(",", entry);
+        let (k, v) = entries.next().unwrap().unwrap();
+        assert_eq!((k, v), (TT::from_str("foo"), 8.7));
+        // The key crosses the chunks.
+        let (k, v) = entries.next().unwrap().unwrap();
+        assert_eq!((k, v), (TT::from_str("bar"), -1.0));
+        assert!(entries.next().is_none());
+        assert_eq!(s.end(), Ok(()));
+
+        let text = TT::from_str("{a: 1, b: x}");
+        let mut s = Scanner::new(text.iter(), &mut dalloc);
+        let r = s.delimited("{", "}", |s| s.sep_by(",", entry).try_for_each(|e| e.map(|_| ())));
+        match r {
+            Err(ScanError::Expected { expected: "number", found: Some('x'), pos }) =>
+                assert_eq!(char_pos(pos), 10),
+            _ => panic!(),
+        }
+    }
+
+    #[test]
+    fn errors() {
+        let mut arr = [];
+        let mut dalloc = DA::new(&mut arr[..]);
+        let text = TT::from_str("abc");
+        let mut s = Scanner::new(text.iter(), &mut dalloc);
+        match s.token("abd") {
+            Err(ScanError::Expected { expected: "abd", found: Some('c'), pos }) =>
+                assert_eq!(char_pos(pos), 2),
+            _ => panic!(),
+        }
+        match s.token("c!") {
+            Err(ScanError::Expected { expected: "c!", found: None, pos }) =>
+                assert_eq!(char_pos(pos), 2),
+            _ => panic!(),
+        }
+        let text = TT::from_str(" 300");
+        let mut s = Scanner::new(text.iter(), &mut dalloc);
+        s.skip_ws();
+        match s.number::<u8>() {
+            Err(ScanError::Invalid { error: ParseError::Overflow, pos }) =>
+                assert_eq!(char_pos(pos), 1),
+            _ => panic!(),
+        }
+        assert_eq!(s.end(), Ok(()));
+    }
+
+    #[test]
+    fn combinators() {
+        let mut arr = [];
+        let mut dalloc = DA::new(&mut arr[..]);
+        let text = TT::from_str("1 2 3x+ y");
+        let mut s = Scanner::new(text.iter(), &mut dalloc);
+        let mut sum = 0;
+        for n in s.many(|s| { s.skip_ws(); s.number::<i32>() }) {
+            sum += n.unwrap();
+        }
+        assert_eq!(sum, 6);
+        let word = s.or(|s| s.number::<i32>().map(|_| TT::from_str("")), Scanner::ident);
+        assert_eq!(word, Ok(TT::from_str("x")));
+        assert_eq!(s.optional(|s| s.token("++")).map(|_| ()),
+                   Err(ScanError::Expected { expected: "++", found: Some(' '),
+                                             pos: s.pos() }));
+        s.skip_ws();
+        assert_eq!(s.optional(Scanner::number::<i32>), Ok(None));
+        assert_eq!(s.ident(), Ok(TT::from_str("y")));
+        assert_eq!(s.consumed(), 9);
+    }
+}
@@ -3,6 +3,7 @@
 #![allow(clippy::module_name_repetitions)]
 
 use core::{str, iter::Map, cmp::Ordering, hash::{Hash, Hasher}};
+use core::ops::{RangeBounds, Bound};
 
 use crate::{SourceIterItem, SourcePosition};
 use crate::parser::AllocError;
@@ -10,6 +11,10 @@ use crate::parser::AllocError;
 
 pub mod iter;
 
+pub mod parse;
+
+pub mod slice;
+
 /// Implementations provided for ready use.
 pub mod premade {
     mod datum_list;
@@ -47,6 +52,9 @@ pub mod chunk {
     pub mod premade {
         mod pos_str;
         pub use pos_str::*;
+
+        mod pos_chars;
+        pub use pos_chars::*;
     }
 
     /// Like [`kul_core::SourceStream`](TODO), but without `DatumAllocator`,
@@ -95,6 +103,17 @@ pub trait TextChunk: TextBase {
     // need to return borrows related to the call lifetimes to be able to return
     // a `CharsSrcStrm`.
     fn src_strm(&self) -> Self::CharsSrcStrm;
+
+    /// Return a borrow of our character sequence as a `&str`, if we are
+    /// represented as one.  This enables zero-copy use of libraries that take
+    /// string slices.
+    ///
+    /// The default implementation returns `None`, which is always correct, and
+    /// chunk types that are backed by `str` should override this.
+    #[inline]
+    fn as_chunk_str(&self) -> Option<&str> {
+        None
+    }
 }
 
 
@@ -248,6 +267,60 @@ pub trait Text: TextBase
         Ok(as_str!())
     }
 
+    /// Return a borrow of our logical character sequence as a `&str`, if it is
+    /// contained in a single chunk (ignoring empty chunks) that can provide it
+    /// via [`TextChunk::as_chunk_str`].  This is the common case for texts that
+    /// were not concatenated, and it enables zero-copy use of libraries that
+    /// take string slices.  If the sequence spans multiple chunks, return
+    /// `None`, in which case iterating [`chars`](#method.chars) or
+    /// [`encode_utf8`](#method.encode_utf8) may be used instead.
+    ///
+    /// [`TextChunk::as_chunk_str`]: trait.TextChunk.html#method.as_chunk_str
+    fn as_single_str(&self) -> Option<&str> {
+        let mut chunks = self.iter_chunks().filter(|chunk| !chunk.is_empty());
+        match (chunks.next(), chunks.next()) {
+            (None, _) => Some(""),
+            (Some(chunk), None) => chunk.as_chunk_str(),
+            (Some(_), Some(_)) => None,
+        }
+    }
+
+    /// Return the index, in `char`s, of the first character of our logical
+    /// character sequence for which `pred` is true, or `None` if there isn't
+    /// one.  The index can be given to the slicing methods of [`TextConcat`].
+    ///
+    /// [`TextConcat`]: trait.TextConcat.html
+    #[inline]
+    fn find<P>(&self, pred: P) -> Option<usize>
+        where P: FnMut(char) -> bool,
+    {
+        self.chars().position(pred)
+    }
+
+    /// Like [`find`](#method.find) but for the last character for which `pred`
+    /// is true.
+    fn rfind<P>(&self, mut pred: P) -> Option<usize>
+        where P: FnMut(char) -> bool,
+    {
+        self.chars().enumerate().fold(None, |last, (i, ch)| if pred(ch) { Some(i) }
+                                                            else { last })
+    }
+
+    /// Parse our logical character sequence as some type of scalar value, like
+    /// `str::parse`, without heap allocation.  See the [`parse`](parse/index.html)
+    /// module.
+    ///
+    /// # Errors
+    ///
+    /// If the text is not of the syntax of the type, return an error that says
+    /// why.
+    #[inline]
+    fn parse<F>(&self) -> Result<F, parse::ParseError>
+        where F: parse::FromText,
+    {
+        F::from_text(self)
+    }
+
     /// Return a borrow of our `self`'s particular representation of chained
     /// chunks to be used by our special iterator types.
     ///
@@ -310,6 +383,157 @@ pub trait TextConcat<DA>: Text {
     /// like `TextDatumList`.  If the implementation ignores `datum_alloc`, it
     /// is safe to use `unwrap` on the returned `Result`.
     fn concat(self, other: Self, datum_alloc: &mut DA) -> Result<Self, AllocError>;
+
+    /// Make a new `Self` that represents the given range, in `char` indices, of
+    /// our logical character sequence.  The new `Self` shares our underlying
+    /// chunks, i.e. is zero-copy, and so its characters keep their positions.
+    /// If the range extends beyond our end, only the characters up to our end
+    /// are included.
+    ///
+    /// This and the following methods use `datum_alloc` only to concatenate the
+    /// pieces of chunks when the range crosses chunk boundaries.
+    ///
+    /// # Errors
+    ///
+    /// If concatenating fails, return the error.
+    fn slice<R>(&self, range: R, datum_alloc: &mut DA) -> Result<Self, AllocError>
+        where R: RangeBounds<usize>,
+    {
+        let start = match range.start_bound() {
+            Bound::Included(&start) => start,
+            Bound::Excluded(&start) => start.saturating_add(1),
+            Bound::Unbounded => 0,
+        };
+        let end = match range.end_bound() {
+            Bound::Included(&end) => end.saturating_add(1),
+            Bound::Excluded(&end) => end,
+            Bound::Unbounded => usize::MAX,
+        };
+        let mut cursor = slice::Cursor::new(self);
+        cursor.skip(start);
+        cursor.take(end.saturating_sub(start), datum_alloc)
+    }
+
+    /// Make two new `Self`s that represent our logical character sequence
+    /// before and after the given `char` index, like
+    /// [`slice`](#method.slice).
+    ///
+    /// # Errors
+    ///
+    /// If concatenating fails, return the error.
+    fn split_at(&self, mid: usize, datum_alloc: &mut DA)
+                -> Result<(Self, Self), AllocError>
+    {
+        let mut cursor = slice::Cursor::new(self);
+        let before = cursor.take(mid, datum_alloc)?;
+        let after = cursor.take_while(|_| true, datum_alloc)?;
+        Ok((before, after))
+    }
+
+    /// Construct a new iterator that yields the sub-texts, like
+    /// [`slice`](#method.slice), of our logical character sequence that are
+    /// separated by the characters for which `pred` is true.
+    #[inline]
+    fn split<'l, 'a, P>(&'l self, pred: P, datum_alloc: &'a mut DA)
+                        -> slice::Split<'l, 'a, Self, DA, P>
+        where P: FnMut(char) -> bool,
+    {
+        slice::Split::new(self, pred, datum_alloc)
+    }
+
+    /// Make a new `Self`, like [`slice`](#method.slice), without the leading
+    /// and trailing characters for which `pred` is true.
+    ///
+    /// # Errors
+    ///
+    /// If concatenating fails, return the error.
+    fn trim_matches<P>(&self, mut pred: P, datum_alloc: &mut DA) -> Result<Self, AllocError>
+        where P: FnMut(char) -> bool,
+    {
+        let (mut start, mut end) = (None, 0);
+        for (i, ch) in self.chars().enumerate() {
+            if !pred(ch) {
+                start = start.or(Some(i));
+                end = i + 1;
+            }
+        }
+        match start {
+            Some(start) => self.slice(start .. end, datum_alloc),
+            None => Ok(Self::empty()),
+        }
+    }
+
+    /// Make a new `Self`, like [`slice`](#method.slice), without leading and
+    /// trailing whitespace, like `str::trim`.
+    ///
+    /// # Errors
+    ///
+    /// If concatenating fails, return the error.
+    #[inline]
+    fn trim(&self, datum_alloc: &mut DA) -> Result<Self, AllocError> {
+        self.trim_matches(char::is_whitespace, datum_alloc)
+    }
+
+    /// Make a new `Self`, like [`slice`](#method.slice), without leading
+    /// whitespace, like `str::trim_start`.
+    ///
+    /// # Errors
+    ///
+    /// If concatenating fails, return the error.
+    fn trim_start(&self, datum_alloc: &mut DA) -> Result<Self, AllocError> {
+        let mut cursor = slice::Cursor::new(self);
+        cursor.skip_while(char::is_whitespace);
+        cursor.take_while(|_| true, datum_alloc)
+    }
+
+    /// Make a new `Self`, like [`slice`](#method.slice), without trailing
+    /// whitespace, like `str::trim_end`.
+    ///
+    /// # Errors
+    ///
+    /// If concatenating fails, return the error.
+    fn trim_end(&self, datum_alloc: &mut DA) -> Result<Self, AllocError> {
+        match self.rfind(|ch| !ch.is_whitespace()) {
+            Some(last) => self.slice(..= last, datum_alloc),
+            None => Ok(Self::empty()),
+        }
+    }
+
+    /// If our logical character sequence starts with `prefix`, make a new
+    /// `Self`, like [`slice`](#method.slice), of the rest after it, else return
+    /// `None`.
+    ///
+    /// # Errors
+    ///
+    /// If concatenating fails, return the error.
+    fn strip_prefix(&self, prefix: &str, datum_alloc: &mut DA)
+                    -> Result<Option<Self>, AllocError>
+    {
+        let mut chars = self.chars();
+        if prefix.chars().all(|p| chars.next() == Some(p)) {
+            self.slice(prefix.chars().count() .., datum_alloc).map(Some)
+        } else {
+            Ok(None)
+        }
+    }
+
+    /// If our logical character sequence ends with `suffix`, make a new `Self`,
+    /// like [`slice`](#method.slice), of the rest before it, else return
+    /// `None`.
+    ///
+    /// # Errors
+    ///
+    /// If concatenating fails, return the error.
+    fn strip_suffix(&self, suffix: &str, datum_alloc: &mut DA)
+                    -> Result<Option<Self>, AllocError>
+    {
+        let (len, suffix_len) = (self.chars().count(), suffix.chars().count());
+        if suffix_len <= len && self.chars().skip(len - suffix_len).eq(suffix.chars()) {
+            self.slice(.. len - suffix_len, datum_alloc).map(Some)
+        } else {
+            Ok(None)
+        }
+    }
 }
 
 
@@ -342,4 +566,16 @@ mod tests {
         assert_eq!(TT::from_str("▷ λ").encode_utf8(&mut [0; 1]), Err(""));
         assert_eq!(TT::from_str("▷ λ").encode_utf8(&mut []), Err(""));
     }
+
+    #[test]
+    fn as_single_str() {
+        use crate::{Datum, parser::premade::SliceDatumAllocator};
+
+        let mut arr = [Datum::EmptyList];
+        let mut dalloc = SliceDatumAllocator::new(&mut arr[..]);
+        assert_eq!(TT::from_str("").as_single_str(), Some(""));
+        assert_eq!(TT::from_str("λ x").as_single_str(), Some("λ x"));
+        let two = TT::from_str("a").concat(TT::from_str("b"), &mut dalloc).unwrap();
+        assert_eq!(two.as_single_str(), None);
+    }
 }
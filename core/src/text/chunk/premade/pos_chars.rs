@@ -0,0 +1,220 @@
+//! A `TextChunk` implementation for borrowed `char` slices (`&[char]`) that
+//! knows what position in its original source slice it is at.  This is useful
+//! for zero-copy parsing of in-memory inputs that are already decoded to
+//! `char`s, e.g. from UTF-32 sources, without re-encoding them as UTF-8.
+
+use core::{slice, iter::{Peekable, Enumerate}};
+
+use crate::text;
+use crate::{TextBase, TextChunk, SourceIterItem, SourcePosition};
+
+
+/// A `SourcePosition` type for character or slice values from text sources that
+/// are entire `[char]` slices where we can know the original source and the
+/// relative position.
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+pub struct CharsPos<'s> {
+    /// Original entire source slice that the value is in. Might equal the
+    /// slice.
+    pub src: &'s [char],
+    /// Character position of start of the value, relative to `src`.
+    pub char_pos: usize,
+}
+
+impl SourcePosition for CharsPos<'_> {
+    #[inline]
+    fn empty() -> Self {
+        CharsPos {
+            src: &[],
+            char_pos: 0,
+        }
+    }
+}
+
+
+/// A `TextChunk` implementation for borrowed `char` slices (`&[char]`) that
+/// knows what position in its original source slice it is at.  This is useful
+/// for zero-copy parsing of in-memory inputs that are already decoded to
+/// `char`s.  A `Vec<char>` can be used by borrowing it as a slice.
+///
+/// Note: The comparison traits are not implemented intentionally, because this
+/// is intended as a chunk type and comparing those directly shouldn't be done.
+#[derive(Copy, Clone, Debug)]
+pub struct PosChars<'s> {
+    /// The represented `char` slice.
+    pub val: &'s [char],
+    /// Information about `val`'s position relative to its original source.
+    pub pos: CharsPos<'s>,
+}
+
+impl<'s> PosChars<'s> {
+    fn empty() -> PosChars<'s> {
+        PosChars {
+            val: &[],
+            pos: CharsPos::empty(),
+        }
+    }
+}
+
+
+impl<'s1, 's2> From<&'s2 [char]> for PosChars<'s1>
+    where 's2: 's1,
+{
+    fn from(val: &'s2 [char]) -> Self {
+        Self {
+            val,
+            pos: CharsPos {
+                src: val,
+                char_pos: 0,
+            },
+        }
+    }
+}
+
+
+impl<'s> TextBase for PosChars<'s> {
+    type Pos = CharsPos<'s>;
+
+    #[inline]
+    fn empty() -> Self {
+        PosChars::empty()
+    }
+
+    #[inline]
+    fn is_empty(&self) -> bool {
+        self.val.is_empty()
+    }
+}
+
+impl<'s> TextChunk for PosChars<'s> {
+    type CharsSrcStrm = PosCharsIter<'s>;
+
+    #[inline]
+    fn src_strm(&self) -> Self::CharsSrcStrm {
+        PosCharsIter::new(self)
+    }
+}
+
+
+/// A [`chunk::SourceStream`] (and `Iterator`) of the characters, and their
+/// positions, of the text chunk that a `PosChars` represents.
+///
+/// The positions of the characters remain correct relative to the original
+/// source slice that the `PosChars`'s slice is from.  (I.e. not relative to the
+/// slice.)
+#[derive(Debug)]
+pub struct PosCharsIter<'s> {
+    iter: Peekable<Enumerate<slice::Iter<'s, char>>>,
+    /// Where the chunk starts, to make the positions of the items from.
+    pos: CharsPos<'s>,
+    /// Where the items are peeked from, to make the peeked item from.
+    peeked: Option<SourceIterItem<CharsPos<'s>>>,
+    accum: Option<PosChars<'s>>,
+}
+
+impl<'s> PosCharsIter<'s> {
+    fn new(poschars: &PosChars<'s>) -> Self {
+        Self {
+            iter: poschars.val.iter().enumerate().peekable(),
+            pos: poschars.pos,
+            peeked: None,
+            accum: None,
+        }
+    }
+
+    #[inline]
+    fn item(&self, (i, &ch): (usize, &char)) -> SourceIterItem<CharsPos<'s>> {
+        SourceIterItem {
+            ch,
+            pos: CharsPos { src: self.pos.src, char_pos: self.pos.char_pos + i },
+        }
+    }
+
+    fn next_item(&mut self) -> Option<SourceIterItem<CharsPos<'s>>> {
+        self.peeked = None;
+        let next = self.iter.next();
+        next.map(|it| self.item(it))
+    }
+}
+
+
+/// Required by `chunk::SourceStream`.
+impl<'s> Iterator for PosCharsIter<'s> {
+    type Item = SourceIterItem<CharsPos<'s>>;
+
+    /// Note: If `next_accum` was previously called (to do an accumulation) and
+    /// returned some item but `accum_done` was not called (to finish an
+    /// accumulation), i.e. if we have an unfinished accumulation, this will
+    /// abort and drop the unfinished accumulation.
+    #[inline]
+    fn next(&mut self) -> Option<Self::Item> {
+        self.accum = None;
+        self.next_item()
+    }
+}
+
+
+/// Enables `PosChars` to be used as the `Chunk` type of `Text` types that
+/// implement `TextConcat` so they can be iterated with
+/// `kul_core::SourceStream`.  This simply records and grows a slice, to
+/// represent each accumulation, which is zero-copy.
+impl<'s> text::chunk::SourceStream<PosChars<'s>> for PosCharsIter<'s>
+{
+    fn peek(&mut self) -> Option<&SourceIterItem<CharsPos<'s>>> {
+        if self.peeked.is_none() {
+            let next = self.iter.peek().copied();
+            self.peeked = next.map(|it| self.item(it));
+        }
+        self.peeked.as_ref()
+    }
+
+    fn next_accum(&mut self) -> Option<SourceIterItem<CharsPos<'s>>> {
+        let next = self.next_item();
+        if let Some(next) = &next {
+            let end = next.pos.char_pos + 1;
+            if let Some(accum) = &mut self.accum {
+                // Already set, so extend
+                accum.val = &accum.pos.src[accum.pos.char_pos .. end];
+            } else {
+                // Not set yet, so set
+                self.accum = Some(PosChars{
+                    val: &next.pos.src[next.pos.char_pos .. end],
+                    pos: next.pos,
+                });
+            }
+        }
+        next
+    }
+
+    #[inline]
+    fn accum_done(&mut self) -> PosChars<'s> {
+        self.accum.take().unwrap_or_else(PosChars::empty)
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::text::chunk::SourceStream as _;
+
+    #[test]
+    fn accum() {
+        let src = ['a', 'λ', 'c', 'd'];
+        let chunk = PosChars::from(&src[..]);
+        let mut strm = chunk.src_strm();
+        assert_eq!(strm.next().map(|it| it.ch), Some('a'));
+        assert_eq!(strm.peek().map(|it| (it.ch, it.pos.char_pos)), Some(('λ', 1)));
+        assert_eq!(strm.next_accum().map(|it| it.pos.char_pos), Some(1));
+        assert_eq!(strm.next_accum().map(|it| it.ch), Some('c'));
+        let sub = strm.accum_done();
+        assert_eq!((sub.val, sub.pos.char_pos), (&['λ', 'c'][..], 1));
+        // The sub-chunk's positions are still relative to the original source.
+        let mut strm = sub.src_strm();
+        assert_eq!(strm.next().map(|it| it.pos.char_pos), Some(1));
+        assert_eq!(strm.next_accum().map(|it| it.pos.char_pos), Some(2));
+        assert_eq!(strm.accum_done().val, ['c']);
+        assert_eq!(strm.next(), None);
+        assert!(strm.accum_done().is_empty());
+    }
+}
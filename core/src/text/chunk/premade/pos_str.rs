@@ -98,6 +98,11 @@ impl<'s> TextChunk for PosStr<'s> {
     fn src_strm(&self) -> Self::CharsSrcStrm {
         PosStrIter::new(self)
     }
+
+    #[inline]
+    fn as_chunk_str(&self) -> Option<&str> {
+        Some(self.val)
+    }
 }
 
 
@@ -0,0 +1,242 @@
+//! Parsing the logical character sequence of any `Text` as scalar values,
+//! without heap allocation and so also for `no_std` `Text` types like
+//! [`TextDatumList`](../premade/struct.TextDatumList.html).
+//!
+//! See [`FromText`](trait.FromText.html) and [`Text::parse`].
+//!
+//! [`Text::parse`]: ../trait.Text.html#method.parse
+
+use core::str;
+
+use crate::Text;
+
+
+/// The maximum length, in bytes, of the UTF-8 encoding of texts that can be
+/// parsed as floats when they are not a single chunk.  Such texts must be
+/// copied into a buffer on the stack, because float parsing needs a `&str`.
+pub const MAX_FLOAT_LEN: usize = 256;
+
+
+/// Errors possibly returned by [`FromText::from_text`].
+///
+/// [`FromText::from_text`]: trait.FromText.html#tymethod.from_text
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+pub enum ParseError {
+    /// The text is empty.
+    Empty,
+    /// The text is not of the syntax of the type.
+    Invalid,
+    /// The text is of the syntax of an integer but its value does not fit in
+    /// the type.
+    Overflow,
+    /// The text is longer than [`MAX_FLOAT_LEN`](constant.MAX_FLOAT_LEN.html)
+    /// and is not a single chunk.
+    TooLong,
+}
+
+
+/// Types that can be parsed from the logical character sequence of any `Text`,
+/// like `FromStr` but without needing a `&str`.
+///
+/// The syntax is the same as that of the type's `FromStr`, i.e. leading and
+/// trailing whitespace is not allowed.
+pub trait FromText: Sized {
+    /// Parse the given `text` as `Self`.
+    ///
+    /// # Errors
+    ///
+    /// If the `text` is not the syntax of `Self`, return an error that says
+    /// why.
+    fn from_text<TT>(text: &TT) -> Result<Self, ParseError>
+        where TT: Text;
+}
+
+
+impl FromText for bool {
+    fn from_text<TT>(text: &TT) -> Result<Self, ParseError>
+        where TT: Text,
+    {
+        if text.chars().eq("true".chars()) {
+            Ok(true)
+        } else if text.chars().eq("false".chars()) {
+            Ok(false)
+        } else if text.is_empty() {
+            Err(ParseError::Empty)
+        } else {
+            Err(ParseError::Invalid)
+        }
+    }
+}
+
+
+/// Accumulate the decimal digits of `text`, with an optional leading sign, with
+/// the given checked operations.  Negative values are accumulated downwards so
+/// that the minimum of signed types can be reached.
+macro_rules! parse_int {
+    ($text:expr, $t:ty, $signed:expr) => {{
+        let mut chars = $text.chars().peekable();
+        let negative = match chars.peek() {
+            None => return Err(ParseError::Empty),
+            Some('-') if $signed => { let _ = chars.next(); true }
+            Some('+') => { let _ = chars.next(); false }
+            Some(_) => false,
+        };
+        if chars.peek().is_none() {
+            return Err(ParseError::Invalid);
+        }
+        let mut acc: $t = 0;
+        for ch in chars {
+            let digit = ch.to_digit(10).ok_or(ParseError::Invalid)?;
+            #[allow(trivial_numeric_casts, clippy::cast_possible_truncation,
+                    clippy::cast_possible_wrap, clippy::cast_lossless)]
+            let digit = digit as $t;
+            acc = acc.checked_mul(10)
+                     .and_then(|acc| if negative { acc.checked_sub(digit) }
+                                     else { acc.checked_add(digit) })
+                     .ok_or(ParseError::Overflow)?;
+        }
+        Ok(acc)
+    }}
+}
+
+macro_rules! impl_from_text_int {
+    ($signed:expr => $($t:ty)*) => {
+        $(impl FromText for $t {
+            fn from_text<TT>(text: &TT) -> Result<Self, ParseError>
+                where TT: Text,
+            {
+                parse_int!(text, $t, $signed)
+            }
+        })*
+    }
+}
+
+impl_from_text_int!(true => i8 i16 i32 i64 i128 isize);
+impl_from_text_int!(false => u8 u16 u32 u64 u128 usize);
+
+
+macro_rules! impl_from_text_float {
+    ($($t:ty)*) => {
+        $(impl FromText for $t {
+            fn from_text<TT>(text: &TT) -> Result<Self, ParseError>
+                where TT: Text,
+            {
+                if let Some(s) = text.as_single_str() {
+                    return parse_float_str(s);
+                }
+                let mut buf = [0; MAX_FLOAT_LEN];
+                match text.encode_utf8(&mut buf) {
+                    Ok(s) => parse_float_str(s),
+                    Err(_) => Err(ParseError::TooLong),
+                }
+            }
+        })*
+    }
+}
+
+impl_from_text_float!(f32 f64);
+
+fn parse_float_str<F>(s: &str) -> Result<F, ParseError>
+    where F: str::FromStr,
+{
+    if s.is_empty() {
+        Err(ParseError::Empty)
+    } else {
+        s.parse().map_err(|_| ParseError::Invalid)
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        Datum,
+        parser::premade::SliceDatumAllocator,
+        text::{premade::TextDatumList, chunk::premade::PosStr, TextConcat},
+    };
+
+    type TT<'d> = TextDatumList<'d, PosStr<'static>, ()>;
+    type DA<'d> = SliceDatumAllocator<'d, TT<'d>, ()>;
+
+    /// Parse `s` as a single chunk and also split into a chunk per `char`.
+    fn both<F>(s: &'static str) -> (Result<F, ParseError>, Result<F, ParseError>)
+        where F: FromText,
+    {
+        let mut arr = [Datum::EmptyList, Datum::EmptyList, Datum::EmptyList,
+                       Datum::EmptyList, Datum::EmptyList, Datum::EmptyList,
+                       Datum::EmptyList, Datum::EmptyList, Datum::EmptyList,
+                       Datum::EmptyList, Datum::EmptyList, Datum::EmptyList];
+        let mut dalloc = DA::new(&mut arr[..]);
+        let mut split = TT::from_str("");
+        for (i, ch) in s.char_indices() {
+            let chunk = TT::from_str(&s[i .. i + ch.len_utf8()]);
+            split = split.concat(chunk, &mut dalloc).unwrap();
+        }
+        (TT::from_str(s).parse(), split.parse())
+    }
+
+    fn same<F>(s: &'static str) -> Result<F, ParseError>
+        where F: FromText + PartialEq + core::fmt::Debug,
+    {
+        let (single, split) = both(s);
+        assert_eq!(single, split);
+        single
+    }
+
+    #[test]
+    fn ints() {
+        assert_eq!(same::<i32>("0"), Ok(0));
+        assert_eq!(same::<i32>("+42"), Ok(42));
+        assert_eq!(same::<i32>("-42"), Ok(-42));
+        assert_eq!(same::<i8>("-128"), Ok(-128));
+        assert_eq!(same::<i8>("127"), Ok(127));
+        assert_eq!(same::<i8>("128"), Err(ParseError::Overflow));
+        assert_eq!(same::<i8>("-129"), Err(ParseError::Overflow));
+        assert_eq!(same::<u8>("255"), Ok(255));
+        assert_eq!(same::<u8>("256"), Err(ParseError::Overflow));
+        assert_eq!(same::<u8>("-1"), Err(ParseError::Invalid));
+        assert_eq!(same::<u64>(""), Err(ParseError::Empty));
+        assert_eq!(same::<u64>("+"), Err(ParseError::Invalid));
+        assert_eq!(same::<i64>("-"), Err(ParseError::Invalid));
+        assert_eq!(same::<i64>(" 1"), Err(ParseError::Invalid));
+        assert_eq!(same::<i64>("1x"), Err(ParseError::Invalid));
+        assert_eq!(same::<i64>("١"), Err(ParseError::Invalid));
+        assert_eq!(TT::from_str("-170141183460469231731687303715884105728").parse(),
+                   Ok(i128::MIN));
+    }
+
+    #[test]
+    fn floats() {
+        assert_eq!(same::<f64>("1.5"), Ok(1.5));
+        assert_eq!(same::<f64>("-2e3"), Ok(-2000.0));
+        assert_eq!(same::<f32>("inf"), Ok(f32::INFINITY));
+        assert_eq!(same::<f64>(""), Err(ParseError::Empty));
+        assert_eq!(same::<f64>("1.5.2"), Err(ParseError::Invalid));
+        assert_eq!(same::<f64>("λ"), Err(ParseError::Invalid));
+        let (single, split) = both::<f64>("NaN");
+        assert!(single.unwrap().is_nan() && split.unwrap().is_nan());
+    }
+
+    #[test]
+    fn too_long() {
+        let mut arr = [Datum::EmptyList];
+        let mut dalloc = DA::new(&mut arr[..]);
+        let zeros = "0000000000000000000000000000000000000000000000000000000000000000\
+                     0000000000000000000000000000000000000000000000000000000000000000\
+                     0000000000000000000000000000000000000000000000000000000000000000\
+                     0000000000000000000000000000000000000000000000000000000000000000";
+        assert_eq!(TT::from_str(zeros).parse::<f64>(), Ok(0.0));
+        let split = TT::from_str(zeros).concat(TT::from_str("1"), &mut dalloc).unwrap();
+        assert_eq!(split.parse::<f64>(), Err(ParseError::TooLong));
+    }
+
+    #[test]
+    fn bools() {
+        assert_eq!(same::<bool>("true"), Ok(true));
+        assert_eq!(same::<bool>("false"), Ok(false));
+        assert_eq!(same::<bool>("True"), Err(ParseError::Invalid));
+        assert_eq!(same::<bool>("truex"), Err(ParseError::Invalid));
+        assert_eq!(same::<bool>(""), Err(ParseError::Empty));
+    }
+}
@@ -0,0 +1,266 @@
+//! Parts for making sub-texts of a `Text` that share its underlying chunks,
+//! i.e. zero-copy, by accumulating with the chunks' `chunk::SourceStream`s and
+//! concatenating across chunk boundaries with `TextConcat`.
+//!
+//! The operations are the provided methods of [`TextConcat`], and this module
+//! has their supporting types.
+//!
+//! [`TextConcat`]: ../trait.TextConcat.html
+
+use core::fmt;
+
+use crate::{Text, TextChunk, TextConcat};
+use crate::text::{iter::chunks, chunk::SourceStream as ChunkSourceStream};
+use crate::parser::AllocError;
+
+
+/// A position in the logical character sequence of a `Text`, which can skip
+/// characters and take the following characters as a sub-text.
+pub(crate) struct Cursor<'l, TT>
+    where TT: Text,
+{
+    chunks: chunks::Iter<'l, TT>,
+    cur: Option<<TT::Chunk as TextChunk>::CharsSrcStrm>,
+}
+
+impl<'l, TT> Cursor<'l, TT>
+    where TT: Text,
+          TT::Chunk: 'l,
+{
+    pub(crate) fn new(text: &'l TT) -> Self {
+        let mut chunks = text.iter_chunks();
+        let cur = chunks.next().map(TextChunk::src_strm);
+        Self { chunks, cur }
+    }
+
+    /// Return the next character without consuming it, moving on to the next
+    /// chunk as needed.  Must not be called while accumulating.
+    pub(crate) fn peek(&mut self) -> Option<char> {
+        loop {
+            match &mut self.cur {
+                Some(strm) => match strm.peek() {
+                    Some(it) => break Some(it.ch),
+                    None => self.cur = self.chunks.next().map(TextChunk::src_strm),
+                },
+                None => break None,
+            }
+        }
+    }
+
+    /// Consume the characters for which `pred` is true, up to the first for
+    /// which it is false.
+    pub(crate) fn skip_while<P>(&mut self, mut pred: P)
+        where P: FnMut(char) -> bool,
+    {
+        while let Some(ch) = self.peek() {
+            if !pred(ch) {
+                break
+            }
+            if let Some(strm) = &mut self.cur {
+                let _ = strm.next();
+            }
+        }
+    }
+
+    /// Consume the characters for which `pred` is true, up to the first for
+    /// which it is false, and return them as a sub-text that shares our chunks.
+    pub(crate) fn take_while<DA, P>(&mut self, mut pred: P, dalloc: &mut DA)
+                                    -> Result<TT, AllocError>
+        where TT: TextConcat<DA>,
+              P: FnMut(char) -> bool,
+    {
+        let mut accum = TT::empty();
+        loop {
+            match &mut self.cur {
+                Some(strm) => match strm.peek().map(|it| it.ch) {
+                    Some(ch) if pred(ch) => {
+                        let _ = strm.next_accum();
+                    }
+                    Some(_) => break accum.concat(strm.accum_done().into(), dalloc),
+                    None => {
+                        accum = accum.concat(strm.accum_done().into(), dalloc)?;
+                        self.cur = self.chunks.next().map(TextChunk::src_strm);
+                    }
+                },
+                None => break Ok(accum),
+            }
+        }
+    }
+
+    /// Consume `n` characters, or as many as remain.
+    pub(crate) fn skip(&mut self, mut n: usize) {
+        self.skip_while(|_| if n > 0 { n -= 1; true } else { false });
+    }
+
+    /// Consume `n` characters, or as many as remain, and return them as a
+    /// sub-text.
+    pub(crate) fn take<DA>(&mut self, mut n: usize, dalloc: &mut DA) -> Result<TT, AllocError>
+        where TT: TextConcat<DA>,
+    {
+        self.take_while(|_| if n > 0 { n -= 1; true } else { false }, dalloc)
+    }
+}
+
+
+/// An `Iterator` of the sub-texts of a `Text` that are separated by characters
+/// that match a predicate.  Returned by [`TextConcat::split`].
+///
+/// Like `str::split`, an empty text yields a single empty sub-text, and
+/// adjacent separators yield empty sub-texts between them.
+///
+/// [`TextConcat::split`]: ../trait.TextConcat.html#method.split
+pub struct Split<'l, 'a, TT, DA, P>
+    where TT: Text,
+{
+    cursor: Cursor<'l, TT>,
+    dalloc: &'a mut DA,
+    pred: P,
+    done: bool,
+}
+
+impl<'l, 'a, TT, DA, P> Split<'l, 'a, TT, DA, P>
+    where TT: Text,
+          TT::Chunk: 'l,
+{
+    pub(crate) fn new(text: &'l TT, pred: P, dalloc: &'a mut DA) -> Self {
+        Self { cursor: Cursor::new(text), dalloc, pred, done: false }
+    }
+}
+
+/// Must implement this manually because deriving would place unwanted bounds
+/// on the type parameters.
+impl<TT, DA, P> fmt::Debug for Split<'_, '_, TT, DA, P>
+    where TT: Text,
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Split").field("done", &self.done).finish()
+    }
+}
+
+impl<'l, TT, DA, P> Iterator for Split<'l, '_, TT, DA, P>
+    where TT: TextConcat<DA>,
+          TT::Chunk: 'l,
+          P: FnMut(char) -> bool,
+{
+    type Item = Result<TT, AllocError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.done {
+            return None
+        }
+        let pred = &mut self.pred;
+        let piece = self.cursor.take_while(|ch| !pred(ch), self.dalloc);
+        // Consume the separator, if any.
+        if piece.is_err() || self.cursor.peek().is_none() {
+            self.done = true;
+        } else {
+            self.cursor.skip(1);
+        }
+        Some(piece)
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        Datum, Text, TextBase, TextConcat, SourceIterItem,
+        parser::{premade::SliceDatumAllocator, AllocError},
+        text::{premade::TextDatumList, chunk::premade::PosStr},
+    };
+
+    type TT<'d> = TextDatumList<'d, PosStr<'static>, ()>;
+    type DA<'d> = SliceDatumAllocator<'d, TT<'d>, ()>;
+
+    fn chars_pos(text: &TT<'_>) -> ([char; 8], [usize; 8]) {
+        let (mut chars, mut poss) = ([' '; 8], [0; 8]);
+        for (i, SourceIterItem{ch, pos}) in text.iter().enumerate() {
+            chars[i] = ch;
+            poss[i] = pos.char_pos;
+        }
+        (chars, poss)
+    }
+
+    #[test]
+    fn slice_and_split_at() {
+        let mut arr = [Datum::EmptyList, Datum::EmptyList, Datum::EmptyList,
+                       Datum::EmptyList, Datum::EmptyList, Datum::EmptyList];
+        let mut dalloc = DA::new(&mut arr[..]);
+        let text = TT::from_str("ab").concat(TT::from_str("cd"), &mut dalloc).unwrap();
+        assert_eq!(text, TT::from_str("abcd"));
+
+        let sub = text.slice(1 .. 3, &mut dalloc).unwrap();
+        assert_eq!(sub, TT::from_str("bc"));
+        // The positions are those of the original chunks.
+        assert_eq!(chars_pos(&sub).1[.. 2], [1, 0]);
+        assert_eq!(text.slice(.., &mut dalloc).unwrap(), text);
+        assert_eq!(text.slice(3 .., &mut dalloc).unwrap(), TT::from_str("d"));
+        assert_eq!(text.slice(2 ..= 9, &mut dalloc).unwrap(), TT::from_str("cd"));
+        assert!(text.slice(5 .., &mut dalloc).unwrap().is_empty());
+
+        let (l, r) = text.split_at(1, &mut dalloc).unwrap();
+        assert!(l == TT::from_str("a") && r == TT::from_str("bcd"));
+        let (l, r) = text.split_at(9, &mut dalloc).unwrap();
+        assert!(l == text && r.is_empty());
+    }
+
+    #[test]
+    fn find_and_split() {
+        let mut arr = [Datum::EmptyList, Datum::EmptyList, Datum::EmptyList,
+                       Datum::EmptyList, Datum::EmptyList, Datum::EmptyList];
+        let mut dalloc = DA::new(&mut arr[..]);
+        let text = TT::from_str("a:").concat(TT::from_str("b::c"), &mut dalloc).unwrap();
+        assert_eq!(text.find(|c| c == ':'), Some(1));
+        assert_eq!(text.rfind(|c| c == ':'), Some(4));
+        assert_eq!(text.find(|c| c == 'x'), None);
+
+        let mut pieces = text.split(|c| c == ':', &mut dalloc);
+        for expected in &["a", "b", "", "c"] {
+            assert_eq!(pieces.next().unwrap().unwrap(), TT::from_str(expected));
+        }
+        assert!(pieces.next().is_none());
+
+        let mut arr = [Datum::EmptyList];
+        let mut dalloc = DA::new(&mut arr[..]);
+        let empty = TT::from_str("");
+        let mut pieces = empty.split(|c| c == ':', &mut dalloc);
+        assert!(pieces.next().unwrap().unwrap().is_empty());
+        assert!(pieces.next().is_none());
+        let text = TT::from_str(",x,");
+        let mut pieces = text.split(|c| c == ',', &mut dalloc);
+        assert!(pieces.next().unwrap().unwrap().is_empty());
+        assert_eq!(pieces.next().unwrap().unwrap(), TT::from_str("x"));
+        assert!(pieces.next().unwrap().unwrap().is_empty());
+        assert!(pieces.next().is_none());
+    }
+
+    #[test]
+    fn trim_and_strip() {
+        let mut arr = [Datum::EmptyList, Datum::EmptyList, Datum::EmptyList,
+                       Datum::EmptyList, Datum::EmptyList, Datum::EmptyList];
+        let mut dalloc = DA::new(&mut arr[..]);
+        let text = TT::from_str(" \t").concat(TT::from_str("x y "), &mut dalloc).unwrap();
+        assert_eq!(text.trim(&mut dalloc).unwrap(), TT::from_str("x y"));
+        assert_eq!(text.trim_start(&mut dalloc).unwrap(), TT::from_str("x y "));
+        assert_eq!(text.trim_end(&mut dalloc).unwrap(), TT::from_str(" \tx y"));
+        assert_eq!(chars_pos(&text.trim(&mut dalloc).unwrap()).1[.. 3], [0, 1, 2]);
+        assert!(TT::from_str("  ").trim(&mut dalloc).unwrap().is_empty());
+
+        let text = TT::from_str("{a").concat(TT::from_str("b}"), &mut dalloc).unwrap();
+        let inner = text.strip_prefix("{a", &mut dalloc).unwrap().unwrap();
+        assert_eq!(inner, TT::from_str("b}"));
+        assert_eq!(text.strip_suffix("b}", &mut dalloc).unwrap().unwrap(),
+                   TT::from_str("{a"));
+        assert!(text.strip_prefix("{ab}x", &mut dalloc).unwrap().is_none());
+        assert!(text.strip_suffix("a}", &mut dalloc).unwrap().is_none());
+        assert_eq!(text.strip_suffix("", &mut dalloc).unwrap().unwrap(), text);
+    }
+
+    #[test]
+    fn alloc_error() {
+        let mut arr = [Datum::EmptyList];
+        let mut dalloc = DA::new(&mut arr[..]);
+        let text = TT::from_str("ab").concat(TT::from_str("cd"), &mut dalloc).unwrap();
+        assert_eq!(text.slice(1 .. 3, &mut dalloc).map(|_| ()), Err(AllocError::AllocExhausted));
+    }
+}
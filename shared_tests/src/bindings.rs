@@ -60,7 +60,11 @@ impl<DA, CE, S>
           DA::DR: Hash + Eq,
           S: BuildHasher,
 {
-    fn set_bindings(&mut self, bindings: BindingsSpec<DA, Self::CE>) {
+    // Note: The parameter type is spelled with `CE`, instead of the trait's
+    // `Self::CE`, because the projection through `Self` makes rustc (as of
+    // 1.95) crash in its `impl_trait_overcaptures` lint when this crate is
+    // compiled.  The types are the same.
+    fn set_bindings(&mut self, bindings: BindingsSpec<DA, CE>) {
         self.hashmap.clear();
         self.hashmap.extend(bindings.into_iter());
     }
@@ -77,7 +81,8 @@ impl<DA, CE>
     where DA: DatumAllocator,
           DADatum<DA>: PartialEq,
 {
-    fn set_bindings(&mut self, bindings: BindingsSpec<DA, Self::CE>) {
+    // Note: Spelled with `CE` for the same reason as above.
+    fn set_bindings(&mut self, bindings: BindingsSpec<DA, CE>) {
         self.pairs = bindings;
     }
 }
@@ -0,0 +1,316 @@
+//! A [`Document`] that owns its source text and its parsed top-level forms,
+//! for loading inputs from files, readers, or `String`s and keeping the results
+//! without borrowing anything.
+//!
+//! [`Document`]: struct.Document.html
+
+use std::{
+    fs, io::{self, Read}, path::Path, rc::Rc, str::FromStr, hash::Hash,
+    iter::once,
+};
+
+use crate::{
+    Parser as ParserStruct, Datum as DatumEnum, Error as ErrorEnum,
+    parser::{BoxDatumAllocator, HashMapOperatorBindings},
+    text::{TextVec, chunk::{PosStrish, CharPos}},
+    datum::DatumBox,
+    combiner::{OpFn, ApFn},
+    source_stream::StrishIterSourceStream,
+};
+
+
+#[doc(no_inline)]
+pub use crate::parser::DefaultCharClassifier as CharClassifier;
+
+
+/// Chosen so that the referenced parts of a document's source are zero-copy,
+/// by shared ownership of the source, and so that `char` positions are tracked
+/// (relative to the source), and so that, when chunks are broken around escape
+/// characters, a simple `Vec` is used to logically concatenate them.
+///
+/// This `Text` type is a `TextVec` of `PosStrish<Rc<str>>` chunks.
+pub type Text = TextVec<PosStrish<Rc<str>>>;
+
+/// Chosen so that the [`Datum`]s of a document are simply allocated in heap
+/// `Box`es without limit.
+///
+/// This `DatumAllocator` type is a `BoxDatumAllocator` of our `Text` type and
+/// of the given `Extra` type, which defaults to `()`.
+///
+/// [`Datum`]: type.Datum.html
+pub type DatumAllocator<Extra = ()> = BoxDatumAllocator<Text, Extra>;
+
+/// The type of the top-level forms of a document.
+///
+/// This is a [`Datum`] of our `Text` type, of the given `Extra` type, which
+/// defaults to `()`, and of `DatumBox` references.
+///
+/// [`Datum`]: ../../../kul_core/enum.Datum.html
+pub type Datum<Extra = ()> = DatumEnum<Text, Extra, DatumBox<Text, Extra>>;
+
+/// The type of errors from parsing a document.
+///
+/// This is an [`Error`] with `CharPos` positions, relative to the source, and
+/// with the given `CombinerError` type, which defaults to `()`.
+///
+/// [`Error`]: ../../../kul_core/enum.Error.html
+pub type Error<CombinerError = ()> = ErrorEnum<CharPos, CombinerError>;
+
+/// Chosen so that you may establish bindings simply using the `std` [`HashMap`]
+/// and with flexible trait objects for the function types.
+///
+/// This `OperatorBindings` type is a `HashMapOperatorBindings` that binds
+/// operator sub-forms, as our [`Datum`] type, to [`Combiner`] macro
+/// functions/closures, as `Box`ed `dyn` trait objects.
+///
+/// [`Datum`]: type.Datum.html
+/// [`Combiner`]: ../../../kul_core/enum.Combiner.html
+/// [`HashMap`]: http://doc.rust-lang.org/std/collections/struct.HashMap.html
+pub type OperatorBindings<Extra = (), CombinerError = ()>
+    = HashMapOperatorBindings<DatumAllocator<Extra>,
+                              Box<OpFn<DatumAllocator<Extra>,
+                                       CombinerError>>,
+                              Box<ApFn<DatumAllocator<Extra>,
+                                       CombinerError>>,
+                              CombinerError>;
+
+/// The [`Parser`] type used to parse documents.
+///
+/// [`Parser`]: ../../../kul_core/struct.Parser.html
+pub type Parser<Extra = (), CombinerError = ()>
+    = ParserStruct<CharClassifier,
+                   DatumAllocator<Extra>,
+                   OperatorBindings<Extra, CombinerError>>;
+
+
+/// The errors that might occur when loading a document from a path or reader.
+#[derive(Debug)]
+pub enum LoadError<CombinerError = ()> {
+    /// Reading failed, or the input was not valid UTF-8.
+    Io(io::Error),
+    /// Parsing failed.
+    Parse(Error<CombinerError>),
+}
+
+impl<CE> From<io::Error> for LoadError<CE> {
+    fn from(e: io::Error) -> Self {
+        LoadError::Io(e)
+    }
+}
+
+impl<CE> From<Error<CE>> for LoadError<CE> {
+    fn from(e: Error<CE>) -> Self {
+        LoadError::Parse(e)
+    }
+}
+
+
+/// A parsed document that owns its source text, as an `Rc<str>`, and its
+/// top-level forms.
+///
+/// The `Text`s in the forms share ownership of the source, and so they are
+/// zero-copy and a `Document` doesn't borrow anything, unlike the results of
+/// [`inmem::parse_str`], and so it may be kept however desired.
+///
+/// Parsing stops at the first error, which is returned instead of a `Document`.
+///
+/// [`inmem::parse_str`]: ../inmem/fn.parse_str.html
+#[derive(Debug)]
+pub struct Document<Extra = ()> {
+    source: Rc<str>,
+    forms: Vec<Datum<Extra>>,
+}
+
+impl<Extra> Document<Extra>
+    where Extra: Hash + Eq,
+{
+    /// Parse the given source, with no bindings of operators, and make a new
+    /// instance of `Self` that owns it.
+    ///
+    /// # Errors
+    ///
+    /// If parsing fails, the first `Error` is returned.
+    #[inline]
+    pub fn parse<S>(source: S) -> Result<Self, Error>
+        where S: Into<Rc<str>>,
+    {
+        Self::parse_with(source, OperatorBindings::default())
+    }
+
+    /// Parse the given source, with the given bindings, and make a new instance
+    /// of `Self` that owns it.
+    ///
+    /// # Errors
+    ///
+    /// If parsing fails, the first `Error` is returned.
+    pub fn parse_with<S, CE>(source: S, bindings: OperatorBindings<Extra, CE>)
+                             -> Result<Self, Error<CE>>
+        where S: Into<Rc<str>>,
+    {
+        let source = source.into();
+        let mut parser = ParserStruct {
+            classifier: CharClassifier,
+            allocator: DatumAllocator::default(),
+            bindings,
+        };
+        let forms = parser.parse(StrishIterSourceStream::new(once(Rc::clone(&source))))
+                          .collect::<Result<_, _>>()?;
+        Ok(Self { source, forms })
+    }
+
+    /// Read all of the given reader, parse it with no bindings of operators,
+    /// and make a new instance of `Self` that owns it.
+    ///
+    /// # Errors
+    ///
+    /// If reading fails, or if parsing fails, the error is returned.
+    #[inline]
+    pub fn from_reader<R>(reader: R) -> Result<Self, LoadError>
+        where R: Read,
+    {
+        Self::from_reader_with(reader, OperatorBindings::default())
+    }
+
+    /// Read all of the given reader, parse it with the given bindings, and make
+    /// a new instance of `Self` that owns it.
+    ///
+    /// # Errors
+    ///
+    /// If reading fails, or if parsing fails, the error is returned.
+    pub fn from_reader_with<R, CE>(mut reader: R, bindings: OperatorBindings<Extra, CE>)
+                                   -> Result<Self, LoadError<CE>>
+        where R: Read,
+    {
+        let mut source = String::new();
+        let _ = reader.read_to_string(&mut source)?;
+        Ok(Self::parse_with(source, bindings)?)
+    }
+
+    /// Read the file at the given path, parse it with no bindings of operators,
+    /// and make a new instance of `Self` that owns it.
+    ///
+    /// # Errors
+    ///
+    /// If reading fails, or if parsing fails, the error is returned.
+    #[inline]
+    pub fn from_path<P>(path: P) -> Result<Self, LoadError>
+        where P: AsRef<Path>,
+    {
+        Self::from_path_with(path, OperatorBindings::default())
+    }
+
+    /// Read the file at the given path, parse it with the given bindings, and
+    /// make a new instance of `Self` that owns it.
+    ///
+    /// # Errors
+    ///
+    /// If reading fails, or if parsing fails, the error is returned.
+    pub fn from_path_with<P, CE>(path: P, bindings: OperatorBindings<Extra, CE>)
+                                 -> Result<Self, LoadError<CE>>
+        where P: AsRef<Path>,
+    {
+        let source = fs::read_to_string(path)?;
+        Ok(Self::parse_with(source, bindings)?)
+    }
+}
+
+impl<Extra> Document<Extra> {
+    /// The original source text.
+    #[inline]
+    #[must_use]
+    pub fn source(&self) -> &str {
+        &self.source
+    }
+
+    /// The shared owner of the original source text, which is also shared by
+    /// the `Text`s in the forms.
+    #[inline]
+    #[must_use]
+    pub fn source_rc(&self) -> &Rc<str> {
+        &self.source
+    }
+
+    /// The top-level forms.
+    #[inline]
+    #[must_use]
+    pub fn forms(&self) -> &[Datum<Extra>] {
+        &self.forms
+    }
+
+    /// Consume `self` and return its top-level forms, which continue to share
+    /// ownership of the source text.
+    #[inline]
+    #[must_use]
+    pub fn into_forms(self) -> Vec<Datum<Extra>> {
+        self.forms
+    }
+}
+
+impl<Extra> FromStr for Document<Extra>
+    where Extra: Hash + Eq,
+{
+    type Err = Error;
+
+    /// Parse the given string, with no bindings of operators, by copying it
+    /// into a new source that is owned.
+    #[inline]
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Self::parse(s)
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Combiner, Text as _};
+    use std::{collections::HashMap, iter::FromIterator};
+
+    type Comb = Combiner<Box<OpFn<DatumAllocator<u8>, ()>>, Box<ApFn<DatumAllocator<u8>, ()>>>;
+
+    fn text(s: &str) -> Datum<u8> {
+        DatumEnum::Text(Text::from_str(s))
+    }
+
+    #[test]
+    fn parse() {
+        let doc: Document = "a{b c}".parse().unwrap();
+        assert_eq!(doc.source(), "a{b c}");
+        assert_eq!(doc.forms().len(), 2);
+        // The texts share the source.
+        assert!(Rc::strong_count(doc.source_rc()) > 1);
+
+        assert_eq!("x}".parse::<Document>().map(|_| ()),
+                   Err(ErrorEnum::UnbalancedEndChar(CharPos(1))));
+        assert!(Document::<()>::parse(String::new()).unwrap().forms().is_empty());
+    }
+
+    #[test]
+    fn owned() {
+        fn load() -> Document<u8> {
+            let source = String::from("{op x} {e} y");
+            let pairs: Vec<(_, Comb)> = vec![
+                (text("op"), Combiner::Operative(Box::new(|_, _, _| Ok(Some(DatumEnum::Extra(7)))))),
+                (text("e"), Combiner::Applicative(Box::new(|_, _, _| Ok(None)))),
+            ];
+            Document::parse_with(source, OperatorBindings::new(HashMap::from_iter(pairs)))
+                .unwrap()
+        }
+        let doc = load();
+        assert_eq!(doc.forms(), [DatumEnum::Extra(7), text(" "), text(" y")]);
+        let forms = doc.into_forms();
+        assert_eq!(forms[2], text(" y"));
+    }
+
+    #[test]
+    fn reader() {
+        let doc = Document::<()>::from_reader("{a}".as_bytes()).unwrap();
+        assert_eq!(doc.source(), "{a}");
+        assert!(matches!(Document::<()>::from_reader(&[0xFF_u8][..]),
+                         Err(LoadError::Io(_))));
+        assert!(matches!(Document::<()>::from_reader("{".as_bytes()),
+                         Err(LoadError::Parse(ErrorEnum::MissingEndChar))));
+        assert!(matches!(Document::<()>::from_path("/nonexistent/kul/document"),
+                         Err(LoadError::Io(_))));
+    }
+}
@@ -0,0 +1,560 @@
+//! Loading documents that are split across files, by splicing the top-level
+//! forms of other files in place of `{include path}` forms.
+//!
+//! See [`Includer`](struct.Includer.html).
+//!
+//! Because the forms of a load come from several files, the types of this
+//! module are like those of the [`document`](../document/index.html) module
+//! except that their positions are [`FilePos`]s, of the files as registered in
+//! a [`SourceMap`].
+//!
+//! Includes are expanded after each file is parsed, not by a combiner, and so
+//! combiners never see the included forms.  In particular, an applicative, e.g.
+//! [`stdlib::list`], that is given an include form among its operands is given
+//! the include form itself, and whatever it makes of it is what is loaded.
+//!
+//! [`FilePos`]: ../../source_map/struct.FilePos.html
+//! [`SourceMap`]: ../../source_map/struct.SourceMap.html
+//! [`stdlib::list`]: ../../stdlib/fn.list.html
+
+use std::{
+    fs, io, mem, hash::Hash, iter::once, rc::Rc,
+    path::{Path, PathBuf},
+};
+
+use crate::{
+    Parser as ParserStruct, Datum as DatumEnum, Error as ErrorEnum, Text as _, SourceIterItem,
+    parser::{CharClassifier as CharClassifierTrait, BoxDatumAllocator, HashMapOperatorBindings},
+    text::{TextVec, chunk::{CharPos, PosStrish}},
+    datum::DatumBox,
+    combiner::{OpFn, ApFn},
+    source_map::{SourceMap, FileChunk, FilePos},
+};
+use super::document::CharClassifier;
+
+
+/// Like [`document::Text`] but with the chunks as [`FileChunk`]s, so that the
+/// `Text`s of the forms know which of the loaded files they're from.
+///
+/// [`document::Text`]: ../document/type.Text.html
+/// [`FileChunk`]: ../../source_map/struct.FileChunk.html
+pub type Text = TextVec<FileChunk<PosStrish<Rc<str>>>>;
+
+/// A `BoxDatumAllocator` of our `Text` type and of the given `Extra` type,
+/// which defaults to `()`.
+pub type DatumAllocator<Extra = ()> = BoxDatumAllocator<Text, Extra>;
+
+/// The type of the loaded top-level forms.
+///
+/// This is a [`Datum`] of our `Text` type, of the given `Extra` type, which
+/// defaults to `()`, and of `DatumBox` references.
+///
+/// [`Datum`]: ../../../kul_core/enum.Datum.html
+pub type Datum<Extra = ()> = DatumEnum<Text, Extra, DatumBox<Text, Extra>>;
+
+/// The type of errors from parsing the loaded files.
+///
+/// This is an [`Error`] with `FilePos` positions and with the given
+/// `CombinerError` type, which defaults to `()`.
+///
+/// [`Error`]: ../../../kul_core/enum.Error.html
+pub type Error<CombinerError = ()> = ErrorEnum<FilePos, CombinerError>;
+
+/// Like [`document::OperatorBindings`] but for our `DatumAllocator` type.
+///
+/// [`document::OperatorBindings`]: ../document/type.OperatorBindings.html
+pub type OperatorBindings<Extra = (), CombinerError = ()>
+    = HashMapOperatorBindings<DatumAllocator<Extra>,
+                              Box<OpFn<DatumAllocator<Extra>,
+                                       CombinerError>>,
+                              Box<ApFn<DatumAllocator<Extra>,
+                                       CombinerError>>,
+                              CombinerError>;
+
+
+/// The errors that might occur when loading with includes, attributed to the
+/// `file` that they occurred in.
+#[derive(Debug)]
+pub struct IncludeError<CombinerError = ()> {
+    /// The file that the error occurred in.  For `Parse` errors, and `Io`
+    /// errors of reading, this is the file that was being parsed or read.  For
+    /// the others, including `Io` errors of resolving an included path, this
+    /// is the file that has the offending include form.
+    pub file: PathBuf,
+    /// The position, in `file`, of the operator of the offending include form.
+    /// This is `None` for `Parse` errors, for `Io` errors of reading or of
+    /// resolving the path given to `load`, and if the operator is empty.
+    pub pos: Option<FilePos>,
+    /// What went wrong.
+    pub kind: IncludeErrorKind<CombinerError>,
+}
+
+/// The kinds of [`IncludeError`](struct.IncludeError.html).
+#[derive(Debug)]
+pub enum IncludeErrorKind<CombinerError = ()> {
+    /// Reading failed, or the input was not valid UTF-8, or the path could not
+    /// be resolved.
+    Io(io::Error),
+    /// Parsing failed.
+    Parse(Error<CombinerError>),
+    /// The operands of an include form are not a single path.
+    InvalidInclude,
+    /// The included path, as resolved, is outside the root directory.
+    OutsideRoot(PathBuf),
+    /// The included path, as resolved, is already being included, by the
+    /// chain of files that ends with it.
+    Cycle(Vec<PathBuf>),
+    /// Including the path would exceed the maximum depth.
+    DepthLimit(PathBuf),
+}
+
+
+/// Loads a file, with the same bindings for it and for all the files it
+/// includes, and splices the top-level forms of the included files in place of
+/// the include forms.
+///
+/// An include form is a form whose operator is the `operator` text and whose
+/// operands are a path, e.g. `{include parts/a.kul}`.  The `operator` must not
+/// be bound by the bindings given to [`load`], so that include forms are parsed
+/// as `Datum::Combination`s, because they are expanded after each file is
+/// parsed.  They are spliced where they appear at the top level and in
+/// `Datum::List`s, which includes the operands of unbound forms and lists made
+/// by applicatives (e.g. [`stdlib::list`]), and an included file that has no
+/// forms causes its include form to be removed.  (But an applicative that is
+/// given an include form as an operand sees the form as it is.)
+///
+/// The files are parsed with the default `CharClassifier` by [`load`], or with
+/// the given one by [`load_with`], which should be the same as that of any
+/// other parsing done with the same bindings.
+///
+/// Each file is registered, as it's read, in the `SourceMap` given to `load`,
+/// with its resolved path as its name, and the positions of the forms and of
+/// the errors are of those files.  So a position can be looked-up as the file,
+/// line, and column that it's from.
+///
+/// Relative paths are resolved relative to the directory of the including
+/// file.  All files, as resolved with symbolic links followed, must be inside
+/// the `root` directory, which defaults to the directory of the file given to
+/// `load`.  Including a file that is already being included is an error, as is
+/// nesting includes deeper than `max_depth`.
+///
+/// [`load`]: #method.load
+/// [`load_with`]: #method.load_with
+/// [`stdlib::list`]: ../../stdlib/fn.list.html
+#[derive(Clone, Debug)]
+pub struct Includer {
+    /// The directory that all files must be inside of, or `None` for the
+    /// directory of the file given to `load`.
+    pub root: Option<PathBuf>,
+    /// The maximum nesting depth of includes, where the forms of the file given
+    /// to `load` are at depth 0.
+    pub max_depth: usize,
+    /// The operator text of include forms.
+    pub operator: String,
+}
+
+impl Default for Includer {
+    /// Make a new instance of `Self` with no `root`, with a `max_depth` of 32,
+    /// and with the `"include"` `operator`.
+    #[inline]
+    fn default() -> Self {
+        Self {
+            root: None,
+            max_depth: 32,
+            operator: String::from("include"),
+        }
+    }
+}
+
+/// The state of loading a file and all the files it includes.
+struct Env<'l, CC, Extra, CE>
+    where Extra: Hash + Eq,
+{
+    root: PathBuf,
+    classifier: &'l CC,
+    bindings: &'l OperatorBindings<Extra, CE>,
+    sources: &'l mut SourceMap,
+    /// The files being included, ending with the one being loaded.
+    chain: Vec<PathBuf>,
+}
+
+impl Includer {
+    /// Read, parse, and expand the includes of, the file at the given path,
+    /// with the given bindings and the default `CharClassifier`, and return the
+    /// resulting top-level forms.  The files are registered in `sources`.
+    ///
+    /// # Errors
+    ///
+    /// If resolving, reading, or parsing any of the files fails, or if an
+    /// include form is invalid or not allowed, the first error is returned,
+    /// attributed to its file.
+    #[inline]
+    pub fn load<P, Extra, CE>(&self, path: P, bindings: &OperatorBindings<Extra, CE>,
+                              sources: &mut SourceMap)
+                              -> Result<Vec<Datum<Extra>>, IncludeError<CE>>
+        where P: AsRef<Path>,
+              Extra: Hash + Eq,
+    {
+        self.load_with(path, &CharClassifier, bindings, sources)
+    }
+
+    /// Like [`load`](#method.load) but parse the files with the given
+    /// `CharClassifier`.
+    ///
+    /// # Errors
+    ///
+    /// The same as `load`.
+    pub fn load_with<P, CC, Extra, CE>(&self, path: P, classifier: &CC,
+                                       bindings: &OperatorBindings<Extra, CE>,
+                                       sources: &mut SourceMap)
+                                       -> Result<Vec<Datum<Extra>>, IncludeError<CE>>
+        where P: AsRef<Path>,
+              CC: CharClassifierTrait,
+              Extra: Hash + Eq,
+    {
+        let path = path.as_ref();
+        let file = path.canonicalize().map_err(|e| error(path, None, IncludeErrorKind::Io(e)))?;
+        let root = match &self.root {
+            Some(root) => root.canonicalize()
+                              .map_err(|e| error(root, None, IncludeErrorKind::Io(e)))?,
+            None => file.parent().map_or_else(PathBuf::new, Path::to_path_buf),
+        };
+        if !file.starts_with(&root) {
+            return Err(error(path, None, IncludeErrorKind::OutsideRoot(file)));
+        }
+        self.load_file(&mut Env { root, classifier, bindings, sources, chain: vec![file] })
+    }
+
+    /// Load the last file of the chain, which is already resolved and allowed.
+    fn load_file<CC, Extra, CE>(&self, env: &mut Env<'_, CC, Extra, CE>)
+                                -> Result<Vec<Datum<Extra>>, IncludeError<CE>>
+        where CC: CharClassifierTrait,
+              Extra: Hash + Eq,
+    {
+        let file = env.chain.last().unwrap().clone();
+        let source: Rc<str> = fs::read_to_string(&file)
+            .map_err(|e| error(&file, None, IncludeErrorKind::Io(e)))?
+            .into();
+        let id = env.sources.add(file.to_string_lossy(), Rc::clone(&source));
+        let text = Text::from(FileChunk::new(PosStrish::new(source, CharPos(0)), id));
+        let mut parser = ParserStruct {
+            classifier: env.classifier,
+            allocator: DatumAllocator::default(),
+            bindings: env.bindings,
+        };
+        let forms = parser.parse(text.iter())
+                          .collect::<Result<Vec<_>, _>>()
+                          .map_err(|e| error(&file, None, IncludeErrorKind::Parse(e)))?;
+        let mut expanded = Vec::with_capacity(forms.len());
+        for mut form in forms {
+            if let Some((target, pos)) = self.target(&form, env)? {
+                expanded.extend(self.include(&target, pos, env)?);
+            } else {
+                self.expand_within(&mut form, env)?;
+                expanded.push(form);
+            }
+        }
+        Ok(expanded)
+    }
+
+    /// Splice includes that are elements of lists anywhere within the given
+    /// form.  Walks iteratively, to not overflow the stack for long lists.
+    fn expand_within<CC, Extra, CE>(&self, form: &mut Datum<Extra>,
+                                    env: &mut Env<'_, CC, Extra, CE>)
+                                    -> Result<(), IncludeError<CE>>
+        where CC: CharClassifierTrait,
+              Extra: Hash + Eq,
+    {
+        let mut stack = vec![form];
+        while let Some(datum) = stack.pop() {
+            let target = match datum {
+                DatumEnum::List { elem, .. } => self.target(elem, env)?,
+                _ => None,
+            };
+            match (target, datum) {
+                (Some((target, pos)), datum) => {
+                    let forms = self.include(&target, pos, env)?;
+                    stack.push(splice(datum, forms));
+                }
+                (None, DatumEnum::List { elem, next }) => {
+                    stack.push(next);
+                    stack.push(elem);
+                }
+                (None, DatumEnum::Combination { operator, operands }) => {
+                    stack.push(operands);
+                    stack.push(operator);
+                }
+                (None, _) => (),
+            }
+        }
+        Ok(())
+    }
+
+    /// Resolve the path of an include form, whose operator is at the given
+    /// position, in the last file of the chain, check that it is allowed, and
+    /// load it.
+    fn include<CC, Extra, CE>(&self, target: &Path, pos: Option<FilePos>,
+                              env: &mut Env<'_, CC, Extra, CE>)
+                              -> Result<Vec<Datum<Extra>>, IncludeError<CE>>
+        where CC: CharClassifierTrait,
+              Extra: Hash + Eq,
+    {
+        let chain = &mut env.chain;
+        let including = chain.last().unwrap().clone();
+        let dir = including.parent().unwrap_or_else(|| Path::new(""));
+        let file = match dir.join(target).canonicalize() {
+            Ok(file) => file,
+            Err(e) => return Err(error(&including, pos, IncludeErrorKind::Io(e))),
+        };
+        let kind = if !file.starts_with(&env.root) {
+            Some(IncludeErrorKind::OutsideRoot(file))
+        } else if chain.contains(&file) {
+            Some(IncludeErrorKind::Cycle(chain.iter().cloned().chain(once(file)).collect()))
+        } else if chain.len() > self.max_depth {
+            Some(IncludeErrorKind::DepthLimit(file))
+        } else {
+            chain.push(file);
+            None
+        };
+        if let Some(kind) = kind {
+            return Err(error(&including, pos, kind));
+        }
+        let result = self.load_file(env);
+        let _ = env.chain.pop();
+        result
+    }
+
+    /// If the given form, in the last file of the chain, is an include form,
+    /// return its path and the position of its operator.
+    fn target<CC, Extra, CE>(&self, form: &Datum<Extra>, env: &Env<'_, CC, Extra, CE>)
+                             -> Result<Option<(PathBuf, Option<FilePos>)>, IncludeError<CE>>
+        where CC: CharClassifierTrait,
+              Extra: Hash + Eq,
+    {
+        if let DatumEnum::Combination { operator, operands } = form {
+            if let DatumEnum::Text(op) = &**operator {
+                if op.chars().eq(self.operator.chars()) {
+                    let pos = op.iter().next().map(|SourceIterItem{pos, ..}| pos);
+                    if let DatumEnum::List { elem, next } = &**operands {
+                        if let (DatumEnum::Text(t), DatumEnum::EmptyList) = (&**elem, &**next) {
+                            let path = t.chars().collect::<String>();
+                            let path = path.trim_matches(|c| env.classifier.is_whitespace(c));
+                            if !path.is_empty() {
+                                return Ok(Some((PathBuf::from(path), pos)));
+                            }
+                        }
+                    }
+                    let file = env.chain.last().unwrap();
+                    return Err(error(file, pos, IncludeErrorKind::InvalidInclude));
+                }
+            }
+        }
+        Ok(None)
+    }
+}
+
+
+fn error<CE>(file: &Path, pos: Option<FilePos>, kind: IncludeErrorKind<CE>) -> IncludeError<CE> {
+    IncludeError { file: file.to_path_buf(), pos, kind }
+}
+
+/// Replace the first element of the given list with the given forms, and
+/// return the rest of the list after them.
+fn splice<Extra>(list: &mut Datum<Extra>, forms: Vec<Datum<Extra>>) -> &mut Datum<Extra> {
+    let count = forms.len();
+    let rest = match list {
+        DatumEnum::List { next, .. } => mem::replace(&mut **next, DatumEnum::EmptyList),
+        _ => unreachable!(),
+    };
+    *list = forms.into_iter().rev().fold(rest, |next, elem| {
+        DatumEnum::List { elem: DatumBox::new(elem), next: DatumBox::new(next) }
+    });
+    let mut rest = list;
+    for _ in 0 .. count {
+        rest = match rest {
+            DatumEnum::List { next, .. } => next,
+            _ => unreachable!(),
+        };
+    }
+    rest
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Combiner, parser::CharClassifier, source_map::FileId, datum_util::list_elems};
+    use std::{collections::HashMap, iter::FromIterator, process};
+
+    /// A fresh directory with the given files.
+    fn dir(name: &str, files: &[(&str, &str)]) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("kul-include-{}-{}", name, process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        for (path, content) in files {
+            let path = dir.join(path);
+            fs::create_dir_all(path.parent().unwrap()).unwrap();
+            fs::write(path, content).unwrap();
+        }
+        dir
+    }
+
+    fn text(s: &str) -> Datum {
+        DatumEnum::Text(Text::from_str(s))
+    }
+
+    /// Show the structure, with `|` between list elements.
+    fn show(datum: &Datum) -> String {
+        match datum {
+            DatumEnum::Text(t) => t.chars().collect(),
+            DatumEnum::Combination { operator, operands } =>
+                format!("{{{} {}}}", show(operator), show(operands)),
+            DatumEnum::List { elem, next } => match &**next {
+                DatumEnum::EmptyList => show(elem),
+                next => format!("{}|{}", show(elem), show(next)),
+            },
+            _ => String::new(),
+        }
+    }
+
+    fn show_all(forms: &[Datum]) -> Vec<String> {
+        forms.iter().map(show).collect()
+    }
+
+    #[test]
+    fn splicing() {
+        let d = dir("splicing", &[("main.kul", "a{include sub/b.kul}c{include e.kul}"),
+                                  ("sub/b.kul", "b1{x}{include ../d.kul}"),
+                                  ("d.kul", "d"),
+                                  ("e.kul", "")]);
+        let mut sources = SourceMap::new();
+        let forms = Includer::default().load(d.join("main.kul"),
+                                             &OperatorBindings::<(), ()>::default(),
+                                             &mut sources)
+                                       .unwrap();
+        assert_eq!(forms[.. 2], [text("a"), text("b1")]);
+        assert_eq!(show_all(&forms[2 ..]), ["{x }", "d", "c"]);
+
+        // The positions of the included forms are of the included files.
+        assert_eq!(sources.len(), 4);
+        let pos = |datum: &Datum| match datum {
+            DatumEnum::Text(t) => t.iter().next().unwrap().pos,
+            _ => unreachable!(),
+        };
+        let b = sources.lookup(pos(&forms[1])).unwrap();
+        assert_eq!((b.file, b.name, b.line, b.column),
+                   (FileId(1), &*d.join("sub/b.kul").canonicalize().unwrap().to_string_lossy(),
+                    1, 1));
+        let c = sources.lookup(pos(&forms[4])).unwrap();
+        assert_eq!((c.file, c.column), (FileId(0), 21));
+
+        // Within lists, with an applicative that makes lists of its operands.
+        let list: Box<ApFn<DatumAllocator, ()>> = Box::new(|_, operands, _| Ok(Some(operands)));
+        let bindings = OperatorBindings::new(HashMap::from_iter(vec![
+            (text("list"), Combiner::Applicative(list))]));
+        let _ = fs::remove_dir_all(&d);
+        let d = dir("lists", &[("main.kul", "{list {include e.kul}{include b.kul} {x {include b.kul}}}"),
+                               ("b.kul", "b1 {include e.kul}b2"),
+                               ("e.kul", "")]);
+        let forms = Includer::default().load(d.join("main.kul"), &bindings, &mut sources)
+                                       .unwrap();
+        assert_eq!(show_all(&forms), ["b1 |b2| |{x b1 |b2}"]);
+        let _ = fs::remove_dir_all(&d);
+    }
+
+    #[test]
+    fn operands_of_applicatives() {
+        // Includes are expanded after parsing, and so an applicative is given
+        // an include form as it is, not the included forms.
+        let count: Box<ApFn<DatumAllocator, ()>> = Box::new(|_, operands, _| {
+            Ok(Some(text(&list_elems(&operands).len().to_string())))
+        });
+        let bindings = OperatorBindings::new(HashMap::from_iter(vec![
+            (text("count"), Combiner::Applicative(count))]));
+        let d = dir("applicatives", &[("main.kul", "{count {include b.kul}}"),
+                                      ("b.kul", "b1{x}b2")]);
+        let forms = Includer::default().load(d.join("main.kul"), &bindings,
+                                             &mut SourceMap::new())
+                                       .unwrap();
+        assert_eq!(forms, [text("1")]);
+        let _ = fs::remove_dir_all(&d);
+    }
+
+    #[test]
+    fn errors() {
+        let bindings = OperatorBindings::<(), ()>::default();
+        let d = dir("errors", &[("main.kul", "{include a.kul}"),
+                                ("a.kul", "{include b.kul}"),
+                                ("b.kul", "x\n{include a.kul}"),
+                                ("bad.kul", "x\n}"),
+                                ("has-bad.kul", "{include bad.kul}"),
+                                ("invalid.kul", "{include a {b}}"),
+                                ("dangling.kul", "x\n {include nope.kul}"),
+                                ("sub/escape.kul", "{include ../main.kul}")]);
+        let includer = Includer::default();
+        let load = |path: &str| {
+            let mut sources = SourceMap::new();
+            let e = includer.load(d.join(path), &bindings, &mut sources).unwrap_err();
+            let at = e.pos.map(|pos| {
+                let loc = sources.lookup(pos).unwrap();
+                (PathBuf::from(loc.name), loc.line, loc.column)
+            });
+            (e, at)
+        };
+        let file = |path: &str| d.join(path).canonicalize().unwrap();
+
+        let (e, at) = load("main.kul");
+        assert_eq!((e.file, at), (file("b.kul"), Some((file("b.kul"), 2, 2))));
+        assert!(matches!(&e.kind, IncludeErrorKind::Cycle(chain) if chain.len() == 4));
+
+        let (e, at) = load("has-bad.kul");
+        assert_eq!((e.file, at), (file("bad.kul"), None));
+        assert!(matches!(e.kind, IncludeErrorKind::Parse(Error::UnbalancedEndChar(_))));
+
+        let (e, at) = load("invalid.kul");
+        assert!(matches!(e.kind, IncludeErrorKind::InvalidInclude));
+        assert_eq!(at, Some((file("invalid.kul"), 1, 2)));
+        let (e, at) = load("sub/escape.kul");
+        assert!(matches!(e.kind, IncludeErrorKind::OutsideRoot(_)));
+        assert_eq!(at, Some((file("sub/escape.kul"), 1, 2)));
+        assert!(matches!(load("missing.kul").0.kind, IncludeErrorKind::Io(_)));
+        let (e, at) = load("dangling.kul");
+        assert!(matches!(e.kind, IncludeErrorKind::Io(_)));
+        assert_eq!((e.file, at), (file("dangling.kul"), Some((file("dangling.kul"), 2, 3))));
+
+        // With the root above, escaping the sub-directory is allowed.
+        let mut sources = SourceMap::new();
+        let includer = Includer { root: Some(d.clone()), ..Includer::default() };
+        let e = includer.load(d.join("sub/escape.kul"), &bindings, &mut sources).unwrap_err();
+        assert!(matches!(e.kind, IncludeErrorKind::Cycle(_)));
+
+        let includer = Includer { max_depth: 1, ..Includer::default() };
+        let e = includer.load(d.join("main.kul"), &bindings, &mut sources).unwrap_err();
+        assert_eq!(e.file, file("a.kul"));
+        let a = sources.lookup(e.pos.unwrap()).unwrap();
+        assert_eq!(a.name, &*file("a.kul").to_string_lossy());
+        assert!(matches!(e.kind, IncludeErrorKind::DepthLimit(_)));
+        let _ = fs::remove_dir_all(&d);
+    }
+
+    /// Uses square brackets instead of curly braces.
+    struct SquareClassifier;
+
+    impl CharClassifier for SquareClassifier {
+        fn is_nest_start(&self, c: char) -> bool { c == '[' }
+        fn is_nest_end(&self, c: char) -> bool { c == ']' }
+        fn is_nest_escape(&self, c: char) -> bool { c == '\\' }
+        fn is_whitespace(&self, c: char) -> bool { c.is_whitespace() }
+    }
+
+    #[test]
+    fn classifier() {
+        let d = dir("classifier", &[("main.kul", "[include b.kul] {not nested}"),
+                                    ("b.kul", "[x y]")]);
+        let forms = Includer::default().load_with(d.join("main.kul"), &SquareClassifier,
+                                                  &OperatorBindings::<(), ()>::default(),
+                                                  &mut SourceMap::new())
+                                       .unwrap();
+        assert_eq!(show_all(&forms), ["{x y}", " {not nested}"]);
+        let _ = fs::remove_dir_all(&d);
+    }
+}
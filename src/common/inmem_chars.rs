@@ -0,0 +1,159 @@
+//! A `Parser` for parsing inputs that are single in-memory `char` slices.
+//!
+//! This is like the [`inmem`](../inmem/index.html) module but for inputs that
+//! are already decoded to `char`s, e.g. from UTF-32 sources or terminal
+//! emulators, which are parsed zero-copy without re-encoding them as UTF-8.  A
+//! `Vec<char>` can be parsed by borrowing it as a slice.
+
+use std::hash::Hash;
+
+use crate::{
+    Parser as ParserStruct, ParseIterItem, Text as _,
+    parser::{BoxDatumAllocator, HashMapOperatorBindings},
+    text::{TextVec, chunk::PosChars},
+    combiner::{OpFn, ApFn},
+};
+
+use super::helper::collect_up_to_first_err;
+
+
+#[doc(no_inline)]
+pub use crate::parser::DefaultCharClassifier as CharClassifier;
+
+
+/// Chosen so that the referenced parts of an input slice are zero-copy, and so
+/// that `char` positions are tracked (relative to the input `&[char]`), and so
+/// that, when chunks are broken around escape characters, a simple `Vec` is
+/// used to logically concatenate them.
+///
+/// This `Text` type is a `TextVec` of `PosChars` chunks.
+pub type Text<'input> = TextVec<PosChars<'input>>;
+
+/// Like [`inmem::DatumAllocator`] but for our `Text` type.
+///
+/// [`inmem::DatumAllocator`]: ../inmem/type.DatumAllocator.html
+pub type DatumAllocator<'input, Extra = ()> = BoxDatumAllocator<Text<'input>, Extra>;
+
+/// Like [`inmem::OperatorBindings`] but for our `DatumAllocator` type.
+///
+/// [`inmem::OperatorBindings`]: ../inmem/type.OperatorBindings.html
+pub type OperatorBindings<'input, Extra = (), CombinerError = ()>
+    = HashMapOperatorBindings<DatumAllocator<'input, Extra>,
+                              Box<OpFn<DatumAllocator<'input, Extra>,
+                                       CombinerError>>,
+                              Box<ApFn<DatumAllocator<'input, Extra>,
+                                       CombinerError>>,
+                              CombinerError>;
+
+/// Like [`inmem::Parser`] but for inputs that are single in-memory `char`
+/// slices, as `&[char]`s.
+///
+/// [`inmem::Parser`]: ../inmem/type.Parser.html
+pub type Parser<'input, Extra = (), CombinerError = ()>
+    = ParserStruct<CharClassifier,
+                   DatumAllocator<'input, Extra>,
+                   OperatorBindings<'input, Extra, CombinerError>>;
+
+/// Like [`inmem::TopFormResult`] but for our `Parser` type, and the type of
+/// elements returned by [`parse_chars`] and [`parse_chars_with`].
+///
+/// [`inmem::TopFormResult`]: ../inmem/type.TopFormResult.html
+/// [`parse_chars`]: fn.parse_chars.html
+/// [`parse_chars_with`]: fn.parse_chars_with.html
+pub type TopFormResult<'input, Extra = (), CombinerError = ()>
+    = ParseIterItem<DatumAllocator<'input, Extra>,
+                    OperatorBindings<'input, Extra, CombinerError>>;
+
+
+/// Like [`inmem::parser`] but makes our `Parser` type.
+///
+/// [`inmem::parser`]: ../inmem/fn.parser.html
+#[inline]
+#[must_use]
+pub fn parser<Extra, CombinerError>(
+    bindings: OperatorBindings<'_, Extra, CombinerError>
+) -> Parser<'_, Extra, CombinerError>
+    where Extra: Hash + Eq,
+{
+    ParserStruct {
+        classifier: CharClassifier,
+        allocator: DatumAllocator::default(),
+        bindings,
+    }
+}
+
+/// Like [`inmem::parse_str_with`] but for an input `char` slice.
+///
+/// [`inmem::parse_str_with`]: ../inmem/fn.parse_str_with.html
+#[must_use]
+pub fn parse_chars_with<'i, Extra, CombinerError>(
+    input: &'i [char],
+    bindings: OperatorBindings<'i, Extra, CombinerError>
+) -> Vec<TopFormResult<'i, Extra, CombinerError>>
+    where Extra: Hash + Eq,
+{
+    let input_text = Text::from_chunkish(input);
+    let input_source_stream = input_text.iter();
+    collect_up_to_first_err(parser(bindings).parse(input_source_stream))
+}
+
+/// Like [`inmem::parse_str`] but for an input `char` slice.
+///
+/// [`inmem::parse_str`]: ../inmem/fn.parse_str.html
+#[inline]
+#[must_use]
+pub fn parse_chars(input: &[char]) -> Vec<TopFormResult<'_>> {
+    let empty_bindings = OperatorBindings::default();
+    parse_chars_with(input, empty_bindings)
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        Datum, Combiner, Error,
+        datum::DatumBox,
+        text::chunk::CharsPos,
+    };
+    use std::{collections::HashMap, iter::FromIterator};
+
+    fn chars(s: &str) -> Vec<char> {
+        s.chars().collect()
+    }
+
+    fn text(s: &[char]) -> Text<'_> {
+        Text::from_chunkish(s)
+    }
+
+    #[test]
+    fn parse_chars() {
+        assert_eq!(super::parse_chars(&[]), []);
+        let input = chars("c{b x\\}y} d");
+        let (operands, rest) = (chars("x}y"), chars(" d"));
+        assert_eq!(super::parse_chars(&input),
+                   [Ok(Datum::Text(text(&['c']))),
+                    Ok(Datum::Combination {
+                        operator: DatumBox::new(Datum::Text(text(&['b']))),
+                        operands: DatumBox::new(Datum::List {
+                            elem: DatumBox::new(Datum::Text(text(&operands))),
+                            next: DatumBox::new(Datum::EmptyList),
+                        }),
+                    }),
+                    Ok(Datum::Text(text(&rest)))]);
+        let input = chars("λ} h");
+        assert_eq!(super::parse_chars(&input),
+                   [Err(Error::UnbalancedEndChar(CharsPos{src: &input, char_pos: 1}))]);
+    }
+
+    #[test]
+    fn parse_chars_with() {
+        let (op, input) = (chars("op"), chars("{op ignored}"));
+        let pairs: Vec<(_, Combiner<Box<OpFn<_, _>>, Box<ApFn<_, _>>>)> = vec![
+            (Datum::Text(text(&op)),
+             Combiner::Operative(Box::new(|_, _, _| Ok(Some(Datum::Extra(1u16)))))),
+        ];
+        let bindings = OperatorBindings::<'_, _, ()>::new(HashMap::from_iter(pairs));
+        assert_eq!(super::parse_chars_with(&input, bindings), [Ok(Datum::Extra(1))]);
+    }
+}
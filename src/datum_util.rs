@@ -0,0 +1,302 @@
+//! Helpers for working with `Datum`s that are shared by the other modules, such
+//! as printing forms back as Kul.
+//!
+//! See [`to_kul`](fn.to_kul.html).
+
+use crate::{
+    Datum, DerefTryMut, Text,
+    parser::{CharClassifier, DefaultCharClassifier},
+    source_map::CharOffset,
+};
+
+
+/// A [`CharClassifier`] that also gives the characters to write for its
+/// delimiters, so that forms can be printed in its syntax.
+///
+/// [`CharClassifier`]: ../parser/trait.CharClassifier.html
+pub trait Delimiters: CharClassifier {
+    /// The character to write to start a nesting form.
+    fn nest_start(&self) -> char;
+
+    /// The character to write to end a nesting form.
+    fn nest_end(&self) -> char;
+
+    /// The character to write before a character to escape it.
+    fn nest_escape(&self) -> char;
+
+    /// The character to write between the operator and the operands of a
+    /// nesting form.
+    #[inline]
+    fn whitespace(&self) -> char { ' ' }
+}
+
+impl Delimiters for DefaultCharClassifier {
+    #[inline]
+    fn nest_start(&self) -> char { '{' }
+
+    #[inline]
+    fn nest_end(&self) -> char { '}' }
+
+    #[inline]
+    fn nest_escape(&self) -> char { '\\' }
+}
+
+impl<D> Delimiters for &D
+    where D: Delimiters + ?Sized,
+{
+    #[inline]
+    fn nest_start(&self) -> char { (**self).nest_start() }
+
+    #[inline]
+    fn nest_end(&self) -> char { (**self).nest_end() }
+
+    #[inline]
+    fn nest_escape(&self) -> char { (**self).nest_escape() }
+
+    #[inline]
+    fn whitespace(&self) -> char { (**self).whitespace() }
+}
+
+
+/// Is the given character one that the given classifier would take as a
+/// delimiter or an escape?
+#[inline]
+fn is_special<CC: CharClassifier>(c: char, classifier: &CC) -> bool {
+    classifier.is_nest_start(c) || classifier.is_nest_end(c) || classifier.is_nest_escape(c)
+}
+
+/// Accumulates printed Kul, and counts its `char`s so that printing can stop
+/// once there are more than a limit.
+struct Printer<'d, D> {
+    out: String,
+    len: usize,
+    limit: usize,
+    delims: &'d D,
+}
+
+impl<D: Delimiters> Printer<'_, D> {
+    #[inline]
+    fn push(&mut self, c: char) {
+        self.out.push(c);
+        self.len += 1;
+    }
+
+    /// Push the given character, escaped if it's special to the classifier, or
+    /// if it's whitespace and `in_operator`, because whitespace would end the
+    /// operator.
+    fn push_escaped(&mut self, c: char, in_operator: bool) {
+        if is_special(c, self.delims) || (in_operator && self.delims.is_whitespace(c)) {
+            self.push(self.delims.nest_escape());
+        }
+        self.push(c);
+    }
+
+    #[inline]
+    fn is_full(&self) -> bool {
+        self.len > self.limit
+    }
+}
+
+/// Escape the delimiter and escape characters, of the given syntax, in the
+/// given text, so that it is taken as text when printed as Kul and parsed.
+#[must_use]
+pub fn escape<D: Delimiters>(s: &str, delims: &D) -> String {
+    let mut printer = Printer { out: String::with_capacity(s.len()), len: 0,
+                                limit: usize::MAX, delims };
+    for c in s.chars() {
+        printer.push_escaped(c, false);
+    }
+    printer.out
+}
+
+/// Print the given forms as Kul, of the given syntax, with their texts
+/// [`escape`](fn.escape.html)d, and so that parsing the result with the same
+/// syntax and without bindings gives the same forms, except that adjacent texts
+/// are joined.  `Extra`s are not printed.
+#[inline]
+pub fn to_kul<TT, ET, DR, D>(forms: &[Datum<TT, ET, DR>], delims: &D) -> String
+    where TT: Text,
+          DR: DerefTryMut<Target = Datum<TT, ET, DR>>,
+          D: Delimiters,
+{
+    to_kul_prefix(forms, delims, usize::MAX)
+}
+
+/// Like [`to_kul`](fn.to_kul.html) but stop printing once more than `limit`
+/// `char`s are printed, and so return at most `limit + 1` `char`s, so that only
+/// the start of big forms is printed.
+pub(crate) fn to_kul_prefix<TT, ET, DR, D>(forms: &[Datum<TT, ET, DR>], delims: &D,
+                                           limit: usize)
+                                           -> String
+    where TT: Text,
+          DR: DerefTryMut<Target = Datum<TT, ET, DR>>,
+          D: Delimiters,
+{
+    enum Work<'d, TT, ET, DR>
+        where DR: DerefTryMut<Target = Datum<TT, ET, DR>>,
+    {
+        Form(&'d Datum<TT, ET, DR>),
+        Char(char),
+    }
+
+    let mut printer = Printer { out: String::new(), len: 0, limit, delims };
+    let mut stack: Vec<Work<'_, TT, ET, DR>> = forms.iter().rev().map(Work::Form).collect();
+    while let Some(work) = stack.pop() {
+        if printer.is_full() {
+            break;
+        }
+        match work {
+            Work::Form(Datum::Text(t)) =>
+                for c in t.chars() {
+                    if printer.is_full() {
+                        break;
+                    }
+                    printer.push_escaped(c, false);
+                },
+            Work::Form(Datum::Combination { operator, operands }) => {
+                printer.push(delims.nest_start());
+                stack.push(Work::Char(delims.nest_end()));
+                if !matches!(&**operands, Datum::EmptyList) {
+                    stack.push(Work::Form(operands));
+                    stack.push(Work::Char(delims.whitespace()));
+                }
+                if let Datum::Text(t) = &**operator {
+                    for c in t.chars() {
+                        if printer.is_full() {
+                            break;
+                        }
+                        printer.push_escaped(c, true);
+                    }
+                } else {
+                    stack.push(Work::Form(operator));
+                }
+            }
+            Work::Form(Datum::EmptyNest) => {
+                printer.push(delims.nest_start());
+                printer.push(delims.nest_end());
+            }
+            Work::Form(Datum::List { elem, next }) => {
+                stack.push(Work::Form(next));
+                stack.push(Work::Form(elem));
+            }
+            Work::Form(Datum::EmptyList | Datum::Extra(_)) => (),
+            Work::Char(c) => printer.push(c),
+        }
+    }
+    let mut out = printer.out;
+    if printer.len > limit {
+        out = out.chars().take(limit + 1).collect();
+    }
+    out
+}
+
+
+/// The text of the given `Datum` if it's a `Datum::Text`.
+pub(crate) fn word_of<TT, ET, DR>(datum: &Datum<TT, ET, DR>) -> Option<String>
+    where TT: Text,
+          DR: DerefTryMut<Target = Datum<TT, ET, DR>>,
+{
+    match datum {
+        Datum::Text(text) => Some(text.chars().collect()),
+        _ => None,
+    }
+}
+
+/// The operator text of the given `Datum` if it's a `Datum::Combination`.
+pub(crate) fn operator_of<TT, ET, DR>(datum: &Datum<TT, ET, DR>) -> Option<String>
+    where TT: Text,
+          DR: DerefTryMut<Target = Datum<TT, ET, DR>>,
+{
+    match datum {
+        Datum::Combination { operator, .. } => word_of(operator),
+        _ => None,
+    }
+}
+
+/// The elements of the given list, ignoring any improper tail.
+pub(crate) fn list_elems<TT, ET, DR>(mut list: &Datum<TT, ET, DR>) -> Vec<&Datum<TT, ET, DR>>
+    where DR: DerefTryMut<Target = Datum<TT, ET, DR>>,
+{
+    let mut elems = Vec::new();
+    while let Datum::List { elem, next } = list {
+        elems.push(&**elem);
+        list = next;
+    }
+    elems
+}
+
+/// The `char` offset of the first text in the given `Datum`, if any.
+pub(crate) fn pos_of<TT, ET, DR>(datum: &Datum<TT, ET, DR>) -> Option<usize>
+    where TT: Text,
+          TT::Pos: CharOffset,
+          DR: DerefTryMut<Target = Datum<TT, ET, DR>>,
+{
+    let mut stack = vec![datum];
+    while let Some(datum) = stack.pop() {
+        match datum {
+            Datum::Text(text) => {
+                if let Some(item) = text.iter().next() {
+                    return Some(item.pos.char_offset());
+                }
+            }
+            Datum::Combination { operator: first, operands: second }
+            | Datum::List { elem: first, next: second } => {
+                stack.push(second);
+                stack.push(first);
+            }
+            _ => (),
+        }
+    }
+    None
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        Parser,
+        common::document::{Document, DatumAllocator, OperatorBindings},
+        source_stream::StrishIterSourceStream,
+    };
+    use std::{iter::once, rc::Rc};
+
+    /// Uses square brackets and `|` instead of curly braces and `\`.
+    struct SquareClassifier;
+
+    impl CharClassifier for SquareClassifier {
+        fn is_nest_start(&self, c: char) -> bool { c == '[' }
+        fn is_nest_end(&self, c: char) -> bool { c == ']' }
+        fn is_nest_escape(&self, c: char) -> bool { c == '|' }
+        fn is_whitespace(&self, c: char) -> bool { c.is_whitespace() }
+    }
+
+    impl Delimiters for SquareClassifier {
+        fn nest_start(&self) -> char { '[' }
+        fn nest_end(&self) -> char { ']' }
+        fn nest_escape(&self) -> char { '|' }
+    }
+
+    #[test]
+    fn default_syntax() {
+        let source = r"a\{{b  c {d}}{}{{e} f}{x\ y}";
+        let doc = Document::<()>::parse(source).unwrap();
+        assert_eq!(to_kul(doc.forms(), &DefaultCharClassifier), source);
+        assert_eq!(escape(r"x{\}", &DefaultCharClassifier), r"x\{\\\}");
+    }
+
+    #[test]
+    fn other_syntax() {
+        let source = "{[a|] b|| c [] [[e] f]]";
+        let mut parser = Parser {
+            classifier: SquareClassifier,
+            allocator: DatumAllocator::default(),
+            bindings: OperatorBindings::<(), ()>::default(),
+        };
+        let forms = parser.parse(StrishIterSourceStream::new(once(Rc::<str>::from(source))))
+                          .collect::<Result<Vec<_>, _>>()
+                          .unwrap();
+        assert_eq!(to_kul(&forms, &SquareClassifier), source);
+        assert_eq!(escape("{[|]}", &SquareClassifier), "{|[|||]}");
+    }
+}
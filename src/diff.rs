@@ -0,0 +1,735 @@
+//! Structural differences between two ASTs, e.g. of the old and new versions
+//! of a config, as an edit script of the `Datum`s that were inserted, deleted,
+//! replaced, or moved, which renders as a human-readable report like:
+//!
+//! ```text
+//! operand 1 of `{level}` in operand 1 of `{logging}` in form 2 changed from `info` to `debug` (at 20 → 20)
+//! ```
+//!
+//! The two ASTs may be of different `Datum` types, and they are compared like
+//! by the cross-type `PartialEq` of `Datum`.  Forms with equal operators are
+//! compared operand by operand, and lists, including the top-level forms, are
+//! aligned element by element, where elements that are equal and unique in
+//! both lists anchor the alignment, equal elements that are out of order are
+//! moves, and the elements between the anchors are paired in order.
+//!
+//! A fingerprint of every `Datum` is computed once, from those of its children,
+//! and `Datum`s are only compared in full when their fingerprints are equal.
+//! Aligning is `O(n log n)` in the length of the lists.  Fingerprinting,
+//! comparing, and printing use explicit stacks instead of recursion, and the
+//! printed `Datum`s of edits are cut at [`MAX_KUL_LEN`], and so very long lists
+//! and very deep nests don't overflow the stack nor make huge reports.
+//!
+//! [`MAX_KUL_LEN`]: constant.MAX_KUL_LEN.html
+//!
+//! See [`diff`](fn.diff.html).
+
+use std::{
+    collections::{HashMap, hash_map::DefaultHasher},
+    fmt, hash::{Hash, Hasher}, slice,
+};
+
+use crate::{
+    Datum, Text, DerefTryMut,
+    source_map::CharOffset,
+    parser::DefaultCharClassifier,
+    datum_util::{to_kul_prefix, operator_of, list_elems, pos_of},
+};
+
+
+/// The maximum number of `char`s of the printed `Datum`s of `Node`s.
+pub const MAX_KUL_LEN: usize = 40;
+
+/// Where a `Datum` is in the `Datum` it is in.
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+pub enum Place {
+    /// The top-level form with the given index.
+    Top(usize),
+    /// The operand, with the given index, of a form whose operands are a list.
+    Operand(usize),
+    /// The operands of a form, when they are not a list.
+    Operands,
+    /// The element, with the given index, of a list that is not operands.
+    Elem(usize),
+}
+
+/// One step of the path to a `Datum`, from the top level.
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct Step {
+    /// Where the `Datum` is in the `Datum` of the previous step.
+    pub place: Place,
+    /// The operator of the `Datum`, if it is a form whose operator is a text.
+    pub operator: Option<String>,
+}
+
+/// A `Datum` in the old or the new ASTs.
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct Node {
+    /// The path to the `Datum`, in its AST.
+    pub path: Vec<Step>,
+    /// The `char` offset of the start of the `Datum`'s first text, if it has
+    /// any.
+    pub pos: Option<usize>,
+    /// The `Datum` printed as Kul, on one line, and shortened to
+    /// [`MAX_KUL_LEN`](constant.MAX_KUL_LEN.html) `char`s if longer.
+    pub kul: String,
+}
+
+/// A difference between the old and the new ASTs.
+///
+/// The paths of the old `Datum`s are in the old AST, and the paths of the new
+/// `Datum`s are in the new AST.  Rendering with `Display` gives a line of a
+/// report.
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub enum Edit {
+    /// A `Datum` is only in the new AST.
+    Insert {
+        /// The inserted `Datum`.
+        new: Node,
+    },
+    /// A `Datum` is only in the old AST.
+    Delete {
+        /// The deleted `Datum`.
+        old: Node,
+    },
+    /// A `Datum` was replaced by a different one in the same place.
+    Replace {
+        /// The replaced `Datum`.
+        old: Node,
+        /// What replaced it.
+        new: Node,
+    },
+    /// An equal `Datum` is in a different place of the same list.
+    Move {
+        /// Where it was.
+        old: Node,
+        /// Where it is.
+        new: Node,
+    },
+}
+
+impl fmt::Display for Edit {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Edit::Insert { new } =>
+                write!(f, "{} added: `{}`{}", Location(&new.path), new.kul, At(None, new.pos)),
+            Edit::Delete { old } =>
+                write!(f, "{} removed: `{}`{}", Location(&old.path), old.kul, At(old.pos, None)),
+            Edit::Replace { old, new } =>
+                write!(f, "{} changed from `{}` to `{}`{}",
+                       Location(&old.path), old.kul, new.kul, At(old.pos, new.pos)),
+            Edit::Move { old, new } =>
+                write!(f, "{} moved to {}: `{}`{}",
+                       Location(&old.path), Location(&new.path), old.kul, At(old.pos, new.pos)),
+        }
+    }
+}
+
+/// Renders a path as, e.g., "operand 3 of `{logging}` in form 1".
+struct Location<'p>(&'p [Step]);
+
+impl fmt::Display for Location<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let path = self.0;
+        for (k, step) in path.iter().enumerate().rev() {
+            if k + 1 < path.len() {
+                f.write_str(" in ")?;
+            }
+            let form = match k.checked_sub(1).and_then(|p| path[p].operator.as_ref()) {
+                Some(operator) => format!("`{{{operator}}}`"),
+                None => String::from("a form"),
+            };
+            match step.place {
+                Place::Top(i) => write!(f, "form {}", i + 1)?,
+                Place::Operand(i) => write!(f, "operand {} of {form}", i + 1)?,
+                Place::Operands => write!(f, "the operands of {form}")?,
+                Place::Elem(i) => write!(f, "element {} of a list", i + 1)?,
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Renders the known positions of the old and the new `Datum`s.
+struct At(Option<usize>, Option<usize>);
+
+impl fmt::Display for At {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let show = |pos: Option<usize>| pos.map_or_else(|| String::from("?"), |p| p.to_string());
+        match *self {
+            At(None, None) => Ok(()),
+            At(Some(pos), None) | At(None, Some(pos)) => write!(f, " (at {pos})"),
+            At(old, new) => write!(f, " (at {} → {})", show(old), show(new)),
+        }
+    }
+}
+
+
+/// Compare the given old and new top-level forms, and return their
+/// differences, in the order of the old forms.  See the [module](index.html)
+/// documentation.
+pub fn diff<TT1, ET1, DR1, TT2, ET2, DR2>(old: &[Datum<TT1, ET1, DR1>],
+                                          new: &[Datum<TT2, ET2, DR2>])
+                                          -> Vec<Edit>
+    where TT1: Text + PartialEq<TT2>,
+          TT1::Pos: CharOffset,
+          TT2: Text,
+          TT2::Pos: CharOffset,
+          ET1: PartialEq<ET2>,
+          DR1: DerefTryMut<Target = Datum<TT1, ET1, DR1>>,
+          DR2: DerefTryMut<Target = Datum<TT2, ET2, DR2>>,
+{
+    let mut differ = Differ {
+        old_fps: Fingerprints::new(old),
+        new_fps: Fingerprints::new(new),
+        old_steps: Vec::new(),
+        new_steps: Vec::new(),
+        work: Vec::new(),
+        edits: Vec::new(),
+    };
+    differ.align(&old.iter().collect::<Vec<_>>(), &new.iter().collect::<Vec<_>>(),
+                 None, None, Place::Top);
+    while let Some(work) = differ.work.pop() {
+        match work {
+            Work::Pair(old, new, old_step, new_step) =>
+                differ.compare(old, new, old_step, new_step),
+            Work::Edit(edit) => differ.edits.push(edit),
+        }
+    }
+    differ.edits
+}
+
+/// Render the given differences as a report, one line per edit.
+#[must_use]
+pub fn report(edits: &[Edit]) -> String {
+    let mut out = String::new();
+    for edit in edits {
+        out.push_str(&edit.to_string());
+        out.push('\n');
+    }
+    out
+}
+
+
+/// A pending `Datum` pair to compare, or an edit to emit after the edits of
+/// the pairs before it.
+enum Work<'o, 'n, TT1, ET1, DR1, TT2, ET2, DR2>
+    where DR1: DerefTryMut<Target = Datum<TT1, ET1, DR1>>,
+          DR2: DerefTryMut<Target = Datum<TT2, ET2, DR2>>,
+{
+    Pair(&'o Datum<TT1, ET1, DR1>, &'n Datum<TT2, ET2, DR2>, usize, usize),
+    Edit(Edit),
+}
+
+/// Steps are kept as trees of parent indexes, like for schema validation, so
+/// that whole paths are only made for edits.
+type Steps = Vec<(Option<usize>, Step)>;
+
+struct Differ<'o, 'n, TT1, ET1, DR1, TT2, ET2, DR2>
+    where DR1: DerefTryMut<Target = Datum<TT1, ET1, DR1>>,
+          DR2: DerefTryMut<Target = Datum<TT2, ET2, DR2>>,
+{
+    old_fps: Fingerprints,
+    new_fps: Fingerprints,
+    old_steps: Steps,
+    new_steps: Steps,
+    work: Vec<Work<'o, 'n, TT1, ET1, DR1, TT2, ET2, DR2>>,
+    edits: Vec<Edit>,
+}
+
+impl<'o, 'n, TT1, ET1, DR1, TT2, ET2, DR2> Differ<'o, 'n, TT1, ET1, DR1, TT2, ET2, DR2>
+    where TT1: Text + PartialEq<TT2>,
+          TT1::Pos: CharOffset,
+          TT2: Text,
+          TT2::Pos: CharOffset,
+          ET1: PartialEq<ET2>,
+          DR1: DerefTryMut<Target = Datum<TT1, ET1, DR1>>,
+          DR2: DerefTryMut<Target = Datum<TT2, ET2, DR2>>,
+{
+    /// Are the given `Datum`s equal?  Their fingerprints are compared first, to
+    /// avoid most full comparisons.
+    fn same(&self, old: &Datum<TT1, ET1, DR1>, new: &Datum<TT2, ET2, DR2>) -> bool {
+        self.old_fps.of(old) == self.new_fps.of(new) && equal(old, new)
+    }
+
+    /// Compare a pair of `Datum`s that are in corresponding places.
+    fn compare(&mut self,
+               old: &'o Datum<TT1, ET1, DR1>,
+               new: &'n Datum<TT2, ET2, DR2>,
+               old_step: usize,
+               new_step: usize)
+    {
+        match (old, new) {
+            (Datum::Combination { operator: old_rator, operands: old_rands },
+             Datum::Combination { operator: new_rator, operands: new_rands })
+                if self.same(old_rator, new_rator) =>
+            {
+                if is_list(old_rands) && is_list(new_rands) {
+                    self.align(&list_elems(old_rands), &list_elems(new_rands),
+                               Some(old_step), Some(new_step), Place::Operand);
+                } else if !self.same(old_rands, new_rands) {
+                    let old_step = add_step(&mut self.old_steps, Some(old_step),
+                                            Place::Operands, old_rands);
+                    let new_step = add_step(&mut self.new_steps, Some(new_step),
+                                            Place::Operands, new_rands);
+                    self.work.push(Work::Pair(old_rands, new_rands, old_step, new_step));
+                }
+            }
+            (Datum::List { .. }, Datum::List { .. } | Datum::EmptyList)
+            | (Datum::EmptyList, Datum::List { .. }) =>
+                self.align(&list_elems(old), &list_elems(new),
+                           Some(old_step), Some(new_step), Place::Elem),
+            _ => if !self.same(old, new) {
+                self.edits.push(Edit::Replace {
+                    old: node(&self.old_steps, old_step, old),
+                    new: node(&self.new_steps, new_step, new),
+                });
+            }
+        }
+    }
+
+    /// Align the elements of a pair of lists, and queue the work for them, in
+    /// order.
+    fn align(&mut self,
+             old: &[&'o Datum<TT1, ET1, DR1>],
+             new: &[&'n Datum<TT2, ET2, DR2>],
+             old_parent: Option<usize>,
+             new_parent: Option<usize>,
+             place: fn(usize) -> Place)
+    {
+        let old_fps: Vec<u64> = old.iter().map(|datum| self.old_fps.of(*datum)).collect();
+        let new_fps: Vec<u64> = new.iter().map(|datum| self.new_fps.of(*datum)).collect();
+        let ops = align(old, new, &old_fps, &new_fps);
+        let old_node = |steps: &mut Steps, i: usize| add_node(steps, old_parent, place(i), old[i]);
+        let new_node = |steps: &mut Steps, j: usize| add_node(steps, new_parent, place(j), new[j]);
+        let mut work = Vec::with_capacity(ops.len());
+        for op in ops {
+            work.push(match op {
+                Op::Pair(i, j) => Work::Pair(
+                    old[i], new[j],
+                    add_step(&mut self.old_steps, old_parent, place(i), old[i]),
+                    add_step(&mut self.new_steps, new_parent, place(j), new[j])),
+                Op::Delete(i) => Work::Edit(Edit::Delete {
+                    old: old_node(&mut self.old_steps, i),
+                }),
+                Op::Insert(j) => Work::Edit(Edit::Insert {
+                    new: new_node(&mut self.new_steps, j),
+                }),
+                Op::Move(i, j) => Work::Edit(Edit::Move {
+                    old: old_node(&mut self.old_steps, i),
+                    new: new_node(&mut self.new_steps, j),
+                }),
+            });
+        }
+        self.work.extend(work.into_iter().rev());
+    }
+}
+
+
+/// What to do with the elements, with the given indexes, of a pair of aligned
+/// lists.  Elements that are equal in place are not included.
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+enum Op {
+    Pair(usize, usize),
+    Delete(usize),
+    Insert(usize),
+    Move(usize, usize),
+}
+
+/// The number of occurrences of an element, and the index of the last, in a
+/// list.
+type Count = (usize, usize);
+
+/// Align the elements of a pair of lists, which have the given fingerprints.
+/// The elements that are equal and
+/// unique in both are the anchors, of which the longest in-order sequence is
+/// kept in place and the others are moved.  Between the kept anchors, equal
+/// leading and trailing elements are kept in place, and the rest are paired in
+/// order, and the unpaired ones are deleted or inserted, unless they are equal
+/// to another unpaired one and so are moved.
+fn align<TT1, ET1, DR1, TT2, ET2, DR2>(old: &[&Datum<TT1, ET1, DR1>],
+                                       new: &[&Datum<TT2, ET2, DR2>],
+                                       old_fps: &[u64],
+                                       new_fps: &[u64])
+                                       -> Vec<Op>
+    where TT1: Text + PartialEq<TT2>,
+          TT2: Text,
+          ET1: PartialEq<ET2>,
+          DR1: DerefTryMut<Target = Datum<TT1, ET1, DR1>>,
+          DR2: DerefTryMut<Target = Datum<TT2, ET2, DR2>>,
+{
+    // The fingerprints are compared first, to avoid most full comparisons.
+    let same = |i: usize, j: usize| old_fps[i] == new_fps[j] && equal(old[i], new[j]);
+
+    let mut counts: HashMap<u64, (Count, Count)> = HashMap::new();
+    for (i, fp) in old_fps.iter().enumerate() {
+        let (old_count, _) = counts.entry(*fp).or_default();
+        *old_count = (old_count.0 + 1, i);
+    }
+    for (j, fp) in new_fps.iter().enumerate() {
+        let (_, new_count) = counts.entry(*fp).or_default();
+        *new_count = (new_count.0 + 1, j);
+    }
+    let mut anchors: Vec<(usize, usize)>
+        = counts.values()
+                .filter(|&&((old_count, i), (new_count, j))|
+                        old_count == 1 && new_count == 1 && same(i, j))
+                .map(|&((_, i), (_, j))| (i, j))
+                .collect();
+    anchors.sort_unstable();
+    let in_order = longest_increasing(&anchors);
+    let mut moved_to = vec![None; old.len()];
+    let mut moved_from = vec![false; new.len()];
+    let mut kept = Vec::new();
+    for (&(i, j), in_order) in anchors.iter().zip(in_order) {
+        if in_order {
+            kept.push((i, j));
+        } else {
+            moved_to[i] = Some(j);
+            moved_from[j] = true;
+        }
+    }
+
+    let mut ops = Vec::new();
+    let (mut old_start, mut new_start) = (0, 0);
+    kept.push((old.len(), new.len()));
+    for (old_end, new_end) in kept {
+        let mut old_gap = Vec::new();
+        for (i, moved_to) in (old_start ..).zip(&moved_to[old_start .. old_end]) {
+            match *moved_to {
+                Some(j) => ops.push(Op::Move(i, j)),
+                None => old_gap.push(i),
+            }
+        }
+        let new_gap: Vec<usize> = (new_start .. new_end).filter(|&j| !moved_from[j]).collect();
+        let (mut front, mut back) = (0, 0);
+        let len = old_gap.len().min(new_gap.len());
+        while front < len && same(old_gap[front], new_gap[front]) {
+            front += 1;
+        }
+        while back < len - front
+              && same(old_gap[old_gap.len() - 1 - back], new_gap[new_gap.len() - 1 - back])
+        {
+            back += 1;
+        }
+        let old_gap = &old_gap[front .. old_gap.len() - back];
+        let new_gap = &new_gap[front .. new_gap.len() - back];
+        let paired = old_gap.len().min(new_gap.len());
+        ops.extend(old_gap.iter().zip(new_gap).map(|(&i, &j)| Op::Pair(i, j)));
+        ops.extend(old_gap[paired ..].iter().map(|&i| Op::Delete(i)));
+        ops.extend(new_gap[paired ..].iter().map(|&j| Op::Insert(j)));
+        old_start = old_end + 1;
+        new_start = new_end + 1;
+    }
+
+    // An inserted element that is equal to a deleted one was moved.  Only the
+    // first not-yet-moved deleted candidate is tried, to stay linear.
+    let mut deleted: HashMap<u64, (usize, Vec<usize>)> = HashMap::new();
+    for (k, op) in ops.iter().enumerate() {
+        if let Op::Delete(i) = *op {
+            deleted.entry(old_fps[i]).or_default().1.push(k);
+        }
+    }
+    let mut gone = vec![false; ops.len()];
+    for k in 0 .. ops.len() {
+        if let Op::Insert(j) = ops[k] {
+            if let Some((next, candidates)) = deleted.get_mut(&new_fps[j]) {
+                if let Some(&d) = candidates.get(*next) {
+                    if let Op::Delete(i) = ops[d] {
+                        if same(i, j) {
+                            ops[d] = Op::Move(i, j);
+                            gone[k] = true;
+                            *next += 1;
+                        }
+                    }
+                }
+            }
+        }
+    }
+    ops.into_iter().zip(gone).filter(|&(_, gone)| !gone).map(|(op, _)| op).collect()
+}
+
+/// Return which of the given pairs, which are sorted by their firsts, are in a
+/// longest sequence of them whose seconds also increase.
+fn longest_increasing(pairs: &[(usize, usize)]) -> Vec<bool> {
+    // For each length, the index of the pair that ends the sequences of that
+    // length with the least second.
+    let mut tails: Vec<usize> = Vec::new();
+    let mut prev = Vec::with_capacity(pairs.len());
+    for (k, &(_, j)) in pairs.iter().enumerate() {
+        let len = tails.partition_point(|&t| pairs[t].1 < j);
+        prev.push(len.checked_sub(1).map(|l| tails[l]));
+        if len == tails.len() {
+            tails.push(k);
+        } else {
+            tails[len] = k;
+        }
+    }
+    let mut in_order = vec![false; pairs.len()];
+    let mut k = tails.last().copied();
+    while let Some(i) = k {
+        in_order[i] = true;
+        k = prev[i];
+    }
+    in_order
+}
+
+/// The fingerprints of all the `Datum`s of an AST, by their addresses.
+struct Fingerprints(HashMap<usize, u64>);
+
+impl Fingerprints {
+    /// Compute the fingerprints of the given forms and all the `Datum`s in them,
+    /// children before parents so that each is computed once.  `Datum`s that are
+    /// shared, e.g. by hash-consing, are only visited once.
+    fn new<TT, ET, DR>(forms: &[Datum<TT, ET, DR>]) -> Self
+        where TT: Text,
+              DR: DerefTryMut<Target = Datum<TT, ET, DR>>,
+    {
+        let mut fps = HashMap::new();
+        let mut stack: Vec<(&Datum<TT, ET, DR>, bool)> = forms.iter().map(|f| (f, false))
+                                                              .collect();
+        while let Some((datum, children_done)) = stack.pop() {
+            match datum {
+                Datum::Combination { operator: first, operands: second }
+                | Datum::List { elem: first, next: second } if !children_done => {
+                    if !fps.contains_key(&address(datum)) {
+                        stack.push((datum, true));
+                        stack.push((second, false));
+                        stack.push((first, false));
+                    }
+                }
+                _ => {
+                    let fp = fingerprint(datum, |child| fps[&address(child)]);
+                    let _ = fps.insert(address(datum), fp);
+                }
+            }
+        }
+        Self(fps)
+    }
+
+    /// The fingerprint of the given `Datum`, which must be of our AST.
+    #[inline]
+    fn of<TT, ET, DR>(&self, datum: &Datum<TT, ET, DR>) -> u64
+        where DR: DerefTryMut<Target = Datum<TT, ET, DR>>,
+    {
+        self.0[&address(datum)]
+    }
+}
+
+#[inline]
+fn address<T>(datum: &T) -> usize {
+    let ptr: *const T = datum;
+    ptr as usize
+}
+
+/// A hash of the structure and texts of the given `Datum`, from the given
+/// fingerprints of its children, which is equal for equal `Datum`s even of
+/// different types.  The values of `Datum::Extra`s are not included.
+fn fingerprint<TT, ET, DR, F>(datum: &Datum<TT, ET, DR>, child: F) -> u64
+    where TT: Text,
+          DR: DerefTryMut<Target = Datum<TT, ET, DR>>,
+          F: Fn(&Datum<TT, ET, DR>) -> u64,
+{
+    let mut hasher = DefaultHasher::new();
+    match datum {
+        Datum::Text(text) => {
+            0_u8.hash(&mut hasher);
+            for c in text.chars() {
+                c.hash(&mut hasher);
+            }
+        }
+        Datum::Combination { operator: first, operands: second }
+        | Datum::List { elem: first, next: second } => {
+            (if matches!(datum, Datum::List { .. }) { 1_u8 } else { 2 }).hash(&mut hasher);
+            child(first).hash(&mut hasher);
+            child(second).hash(&mut hasher);
+        }
+        Datum::EmptyNest => 3_u8.hash(&mut hasher),
+        Datum::EmptyList => 4_u8.hash(&mut hasher),
+        Datum::Extra(_) => 5_u8.hash(&mut hasher),
+    }
+    hasher.finish()
+}
+
+/// Are the given `Datum`s equal?  Like their `PartialEq` but with an explicit
+/// stack, because that recurses for each nested operator and list element.
+fn equal<TT1, ET1, DR1, TT2, ET2, DR2>(old: &Datum<TT1, ET1, DR1>, new: &Datum<TT2, ET2, DR2>)
+                                       -> bool
+    where TT1: PartialEq<TT2>,
+          ET1: PartialEq<ET2>,
+          DR1: DerefTryMut<Target = Datum<TT1, ET1, DR1>>,
+          DR2: DerefTryMut<Target = Datum<TT2, ET2, DR2>>,
+{
+    let mut stack = vec![(old, new)];
+    while let Some(pair) = stack.pop() {
+        match pair {
+            (Datum::Text(old), Datum::Text(new)) => if *old != *new { return false; },
+            (Datum::Combination { operator: old_first, operands: old_second },
+             Datum::Combination { operator: new_first, operands: new_second })
+            | (Datum::List { elem: old_first, next: old_second },
+               Datum::List { elem: new_first, next: new_second }) => {
+                stack.push((old_second, new_second));
+                stack.push((old_first, new_first));
+            }
+            (Datum::EmptyNest, Datum::EmptyNest) | (Datum::EmptyList, Datum::EmptyList) => (),
+            (Datum::Extra(old), Datum::Extra(new)) => if *old != *new { return false; },
+            _ => return false,
+        }
+    }
+    true
+}
+
+fn is_list<TT, ET, DR>(datum: &Datum<TT, ET, DR>) -> bool
+    where DR: DerefTryMut<Target = Datum<TT, ET, DR>>,
+{
+    matches!(datum, Datum::List { .. } | Datum::EmptyList)
+}
+
+/// Add a step for a `Datum`, and return its index.
+fn add_step<TT, ET, DR>(steps: &mut Steps,
+                        parent: Option<usize>,
+                        place: Place,
+                        datum: &Datum<TT, ET, DR>)
+                        -> usize
+    where TT: Text,
+          DR: DerefTryMut<Target = Datum<TT, ET, DR>>,
+{
+    steps.push((parent, Step { place, operator: operator_of(datum) }));
+    steps.len() - 1
+}
+
+/// Add a step for a `Datum`, and make its `Node`.
+fn add_node<TT, ET, DR>(steps: &mut Steps,
+                        parent: Option<usize>,
+                        place: Place,
+                        datum: &Datum<TT, ET, DR>)
+                        -> Node
+    where TT: Text,
+          TT::Pos: CharOffset,
+          DR: DerefTryMut<Target = Datum<TT, ET, DR>>,
+{
+    let step = add_step(steps, parent, place, datum);
+    node(steps, step, datum)
+}
+
+fn node<TT, ET, DR>(steps: &[(Option<usize>, Step)], step: usize, datum: &Datum<TT, ET, DR>)
+                    -> Node
+    where TT: Text,
+          TT::Pos: CharOffset,
+          DR: DerefTryMut<Target = Datum<TT, ET, DR>>,
+{
+    let mut path = vec![steps[step].1.clone()];
+    let mut at = step;
+    while let Some(parent) = steps[at].0 {
+        path.push(steps[parent].1.clone());
+        at = parent;
+    }
+    path.reverse();
+    let kul = to_kul_prefix(slice::from_ref(datum), &DefaultCharClassifier, MAX_KUL_LEN);
+    let mut kul: String = kul.chars().map(|c| if c.is_whitespace() { ' ' } else { c }).collect();
+    if kul.chars().count() > MAX_KUL_LEN {
+        kul = kul.chars().take(MAX_KUL_LEN - 1).chain(Some('…')).collect();
+    }
+    Node { path, pos: pos_of(datum), kul }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        datum::DatumBox,
+        common::{inmem::parse_str, inmem_chars::parse_chars, document::Datum as DocDatum},
+    };
+
+    #[test]
+    fn replace_nested() {
+        let old = "{server x}\n{logging {level info} {debug yes}}";
+        let new: Vec<char> = "{server x}\n{logging {level info} {debug no}}".chars().collect();
+        let old: Vec<_> = parse_str(old).into_iter().map(Result::unwrap).collect();
+        let new: Vec<_> = parse_chars(&new).into_iter().map(Result::unwrap).collect();
+        let edits = diff(&old, &new);
+        assert_eq!(report(&edits),
+                   "operand 1 of `{debug}` in operand 3 of `{logging}` in form 3 \
+                    changed from `yes` to `no` (at 40 → 40)\n");
+        assert!(diff(&old, &old).is_empty());
+    }
+
+    #[test]
+    fn moves_inserts_deletes() {
+        let old: Vec<_> = parse_str("{a}{b 1}{c}{d}{x}").into_iter().map(Result::unwrap).collect();
+        let new: Vec<_> = parse_str("{a}{c}{b 1}{d}{e}{f {x}}").into_iter().map(Result::unwrap).collect();
+        let edits = diff(&old, &new);
+        let lines: Vec<String> = edits.iter().map(ToString::to_string).collect();
+        assert_eq!(lines, [
+            "form 2 moved to form 3: `{b 1}` (at 4 → 7)",
+            "form 5 changed from `{x}` to `{e}` (at 15 → 15)",
+            "form 6 added: `{f {x}}` (at 18)",
+        ]);
+    }
+
+    #[test]
+    fn long_lists() {
+        let len = 100_000;
+        let items: Vec<String> = (0 .. len).map(|i| format!("{{i {i}}}")).collect();
+        let old = format!("{{l {}}}", items.concat());
+        let mut new_items = items.clone();
+        let _ = new_items.remove(10);
+        new_items[50_000] = String::from("{i changed}");
+        new_items.push(String::from("{i new}"));
+        new_items.swap(70_000, 70_001);
+        let new = format!("{{l {}}}", new_items.concat());
+        let old: Vec<_> = parse_str(&old).into_iter().map(Result::unwrap).collect();
+        let new: Vec<_> = parse_str(&new).into_iter().map(Result::unwrap).collect();
+        let edits = diff(&old, &new);
+        let kinds: Vec<&str> = edits.iter().map(|edit| match edit {
+            Edit::Insert { .. } => "insert",
+            Edit::Delete { .. } => "delete",
+            Edit::Replace { .. } => "replace",
+            Edit::Move { .. } => "move",
+        }).collect();
+        assert_eq!(kinds, ["delete", "replace", "move", "insert"]);
+        assert_eq!(edits[1].to_string(),
+                   "operand 1 of `{i}` in operand 50002 of `{l}` in form 1 \
+                    changed from `50001` to `changed` (at 438905 → 438899)");
+    }
+
+    #[test]
+    fn deep_nests() {
+        // Nested deeper than the parser could.
+        let depth = 100_000;
+        let text = |s| -> DocDatum { Datum::Text(Text::from_str(s)) };
+        let nest = |innermost| {
+            let mut form = text(innermost);
+            for _ in 0 .. depth {
+                form = Datum::Combination {
+                    operator: DatumBox::new(text("a")),
+                    operands: DatumBox::new(Datum::List { elem: DatumBox::new(form),
+                                                          next: DatumBox::new(Datum::EmptyList) }),
+                };
+            }
+            form
+        };
+        let old = vec![nest("x"), nest("y")];
+        let new = vec![nest("x"), nest("z")];
+        let edits = diff(&old, &new);
+        assert_eq!(edits.len(), 1);
+        match &edits[0] {
+            Edit::Replace { old, new } => {
+                assert_eq!((old.path.len(), old.kul.as_str(), new.kul.as_str()),
+                           (depth + 1, "y", "z"));
+            }
+            edit => panic!("{:?}", edit),
+        }
+
+        let edits = diff(&old[.. 1], &new);
+        assert_eq!(edits.len(), 1);
+        match &edits[0] {
+            Edit::Insert { new } => {
+                assert_eq!(new.kul.chars().count(), MAX_KUL_LEN);
+                assert!(new.kul.starts_with("{a {a {a ") && new.kul.ends_with('…'));
+            }
+            edit => panic!("{:?}", edit),
+        }
+    }
+}
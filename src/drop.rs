@@ -67,10 +67,12 @@ use Datum::EmptyList as TempLeaf;
 /// to take out the inner `Datum` values so that any weak references are
 /// invalidated and do not prevent us from doing the restructuring.  But
 /// unwrapping will fail when there are additional strong references to a
-/// `Datum`, and so we can't do the mutation and so we abort.  This is fine
-/// because when there are additional strong references then the referenced
-/// `Datum` won't be dropped anyway when our reference to it is dropped, and so
-/// drop-recursion down into its branches won't happen anyway.
+/// `Datum`, and so we can't do the mutation and so we treat it like a leaf.
+/// This is fine because when there are additional strong references then the
+/// referenced `Datum` won't be dropped anyway when our reference to it is
+/// dropped, and so drop-recursion down into its branches won't happen anyway.
+/// This is what allows DAGs, where sub-trees are shared by many nodes (e.g. the
+/// elements of a long list), to be dropped without extensive recursion.
 ///
 /// However, there is a possible race condition when `Arc` is used for the
 /// reference type where additional strong references are all dropped by other
@@ -179,28 +181,39 @@ pub fn algo1<TT, ET, DR>(top_dr: &mut DR)
           | Combination{operator: mut left_dr, operands: mut right_dr}
           | List{elem: mut left_dr, next: mut right_dr}
           => {
-              let (left_class, right_class) = (class(&left_dr), class(&right_dr));
+              let (mut left_class, mut right_class)
+                  = (class(&left_dr), class(&right_dr));
 
               // Try to select a side that also branches.  When both branch and
               // we're not yet locked in a mode, give preference to the left
               // side (which is often shallower than the right for long lists,
               // which are the most common deep structure).  When locked in a
               // mode, we must go with its side, until we reach its end.
+              //
+              // A branch that we cannot take out has other strong references
+              // to it (e.g. a sub-tree shared in a DAG), and so dropping our
+              // reference to it will not recurse down into it, and so it is
+              // re-classified as a leaf and the selection is retried.  This
+              // avoids aborting, and then recursing down into the other side,
+              // when only one side is shared.
               let (mode, selected_datum, mut reuse_dr, other_class, other_dr)
-                  = match (mode_lock, left_class, right_class)
+                  = loop { match (mode_lock, left_class, right_class)
               {
                   // If the left node is a branch and we're either at the end of
                   // a mode phase where the right node is a leaf, or we're
                   // locked in left mode, we must be able to take out the left
-                  // datum to proceed.
+                  // datum to proceed.  If both nodes are branches and we're not
+                  // locked in a mode, we must be able to take out one of the
+                  // datums to proceed.  Preference is given to the left datum,
+                  // but the right can be used instead (by the retry).
                   | (_, Class::Branch, Class::Leaf)
-                  | (Some(Side::Left), Class::Branch, Class::Branch)
+                  | (None | Some(Side::Left), Class::Branch, Class::Branch)
                   =>
                       if let Some(left_datum) = trytake(&mut left_dr) {
-                          (Side::Left, left_datum, left_dr, right_class, right_dr)
+                          break (Side::Left, left_datum, left_dr, right_class,
+                                 right_dr)
                       } else {
-                          // Abort, do nothing more, drop `left_dr` and `right_dr`
-                          break
+                          left_class = Class::Leaf;
                       },
 
                   // If the right node is a branch and we're either at the end
@@ -211,32 +224,18 @@ pub fn algo1<TT, ET, DR>(top_dr: &mut DR)
                   | (Some(Side::Right), Class::Branch, Class::Branch)
                   =>
                       if let Some(right_datum) = trytake(&mut right_dr) {
-                          (Side::Right, right_datum, right_dr, left_class, left_dr)
-                      } else {
-                          // Abort, do nothing more, drop `left_dr` and `right_dr`
-                          break
-                      },
-
-                  // If both nodes are branches and we're not locked in a mode,
-                  // we must be able to take out one of the datums to proceed.
-                  // Preference is given to the left datum, but the right can be
-                  // used instead.
-                  | (None, Class::Branch, Class::Branch)
-                  =>
-                      if let Some(left_datum) = trytake(&mut left_dr) {
-                          (Side::Left, left_datum, left_dr, right_class, right_dr)
-                      } else if let Some(right_datum) = trytake(&mut right_dr) {
-                          (Side::Right, right_datum, right_dr, left_class, left_dr)
+                          break (Side::Right, right_datum, right_dr, left_class,
+                                 left_dr)
                       } else {
-                          // Abort, do nothing more, drop `left_dr` and `right_dr`
-                          break
+                          right_class = Class::Leaf;
                       },
 
-                  // If neither left nor right is a branch, we're all done.
-                  // Abort, do nothing more, drop `left_dr` and `right_dr`.
+                  // If neither left nor right is a branch (that we can take
+                  // out), we're all done.  Abort, do nothing more, drop
+                  // `left_dr` and `right_dr`.
                   | (_, Class::Leaf, Class::Leaf)
-                  => break
-              };
+                  => return
+              } };
 
               // Restructure or drop, and then iterate on branch next
               match (selected_datum, other_class) {
@@ -574,6 +573,7 @@ impl<TT, ET> Drop for DatumArc<TT, ET> {
 #[cfg(test)]
 mod tests {
     use kul_shared_tests::utils::tree_shapes::*;
+    use crate::{Datum::{List, EmptyNest, EmptyList}, datum::DatumRc};
 
     // Pure lists (right-sided depth), pure nests (left-sided depth), and pure
     // zig-zags (alternating left-right depth) have the optimal shapes for our
@@ -753,4 +753,25 @@ mod tests {
         // Rest of the dropping, using our algorithm, occurs when `_strong_refs`
         // is dropped here.
     }
+
+    // Branches shared by many nodes, as in DAGs.  Requires treating the shared
+    // branches like leaves, to allow our algorithm to continue with the other
+    // sides.
+
+    fn make_rc_shared_elem_list(len: usize) -> DatumRc<(), ()> {
+        let shared = DatumRc::new(List{elem: DatumRc::new(EmptyNest),
+                                       next: DatumRc::new(EmptyList)});
+        let mut list = DatumRc::new(EmptyList);
+        for _ in 0 .. len {
+            list = DatumRc::new(List{elem: shared.clone(), next: list});
+        }
+        list
+    }
+
+    #[test]
+    fn deep_rc_shared_elem_list() {
+        let len = list_len(get_arg_tree_size());
+        let rcs = make_rc_shared_elem_list(len);
+        drop(rcs);
+    }
 }
@@ -0,0 +1,539 @@
+//! Editing of `Datum` trees, e.g. of parsed ASTs, via a [`Zipper`].
+//!
+//! A `Zipper` has a focus on one node of a tree, which it can move to the
+//! node's parent, children, and siblings, and it can replace the focused node
+//! and insert and remove list elements around it.  It does the `DerefTryMut`
+//! mutation and the relinking of `List` cells, and it allocates new nodes with
+//! a [`DatumAllocator`].
+//!
+//! The children of a `Combination` are its operator and its operands, in that
+//! order, and the children of a `List` are its elements.  Moving to a child
+//! requires mutating the node, to detach the child from it, and so it fails
+//! with [`EditError::Shared`] when the node is shared, e.g. by other
+//! [`DatumRc`]s, and so can't be mutated.  Shared nodes can still be replaced
+//! as wholes.
+//!
+//! Moving and editing is done without recursion and without re-walking the
+//! tree from the root, and so very long lists and very deep nests are fine.
+//!
+//! [`Zipper`]: struct.Zipper.html
+//! [`DatumAllocator`]: ../parser/trait.DatumAllocator.html
+//! [`EditError::Shared`]: enum.EditError.html#variant.Shared
+//! [`DatumRc`]: ../datum/struct.DatumRc.html
+
+use std::{fmt, mem};
+
+use crate::{Datum, DerefTryMut};
+use crate::parser::{DatumAllocator, AllocError};
+
+use Datum::{Combination, List, EmptyList};
+
+
+/// The possible errors of moving and editing with a [`Zipper`].  When one is
+/// returned, the `Zipper` and its tree are as they were before the call.
+///
+/// [`Zipper`]: struct.Zipper.html
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+pub enum EditError {
+    /// There is no node in the requested direction, e.g. the parent of the root
+    /// or the child of a `Text`.
+    NoSuchNode,
+    /// The operation requires the focus to be an element of a list, but it's
+    /// not.
+    NotListElem,
+    /// The operation requires the focus to be a `List` or `EmptyList`, but it's
+    /// not.
+    NotList,
+    /// A node is shared, e.g. by other `DatumRc`s, or the allocator gave a
+    /// shared node, and so it can't be mutated.
+    Shared,
+    /// Allocating a new node failed.
+    FailedAlloc(AllocError),
+}
+
+impl From<AllocError> for EditError {
+    #[inline]
+    fn from(e: AllocError) -> Self {
+        EditError::FailedAlloc(e)
+    }
+}
+
+
+/// Which child of its parent node a detached node is.
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+enum Step {
+    Operator,
+    Operands,
+    Elem,
+    Next,
+}
+
+/// A node that the focus is under.  Its `Datum` value has been moved out, to
+/// detach the child, and so it holds a temporary leaf until the child is put
+/// back.
+struct Frame<DR> {
+    /// The node, which is uniquely held.
+    shell: DR,
+    /// The node's other child.
+    sibling: DR,
+    /// Which child of the node the detached one is.
+    step: Step,
+}
+
+type DADatum<DA> = Datum<<DA as DatumAllocator>::TT,
+                         <DA as DatumAllocator>::ET,
+                         <DA as DatumAllocator>::DR>;
+
+
+/// A cursor that moves around a `Datum` tree, which it owns while editing, and
+/// that edits the tree at its focus.  See the [module](index.html)
+/// documentation.
+///
+/// The tree is given back by [`into_root`](#method.into_root).
+pub struct Zipper<'a, DA>
+    where DA: DatumAllocator,
+{
+    focus: DA::DR,
+    path: Vec<Frame<DA::DR>>,
+    dalloc: &'a mut DA,
+}
+
+/// Must implement this manually because deriving would place unwanted bounds
+/// on the type parameters.
+impl<DA> fmt::Debug for Zipper<'_, DA>
+    where DA: DatumAllocator,
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Zipper").field("depth", &self.path.len()).finish()
+    }
+}
+
+impl<'a, DA> Zipper<'a, DA>
+    where DA: DatumAllocator,
+{
+    /// Make a new one that focuses on the given `root` of a tree and that uses
+    /// the given `dalloc` to allocate new nodes.
+    #[inline]
+    pub fn new(root: DA::DR, dalloc: &'a mut DA) -> Self {
+        Self { focus: root, path: Vec::new(), dalloc }
+    }
+
+    /// Return the focused node.
+    #[inline]
+    pub fn focus(&self) -> &DADatum<DA> {
+        &self.focus
+    }
+
+    /// Return the focused node for mutating it in place.
+    ///
+    /// # Errors
+    ///
+    /// If the focused node is shared, `EditError::Shared` is returned.
+    #[inline]
+    pub fn focus_mut(&mut self) -> Result<&mut DADatum<DA>, EditError> {
+        DerefTryMut::get_mut(&mut self.focus).ok_or(EditError::Shared)
+    }
+
+    /// Return whether the focus is on the root.
+    #[inline]
+    pub fn is_root(&self) -> bool {
+        self.path.is_empty()
+    }
+
+    /// Move to the parent.  For a list element, this is the head `List` of
+    /// the list.
+    ///
+    /// # Errors
+    ///
+    /// If the focus is on the root, `EditError::NoSuchNode` is returned.
+    pub fn parent(&mut self) -> Result<(), EditError> {
+        match self.ascend() {
+            Some(Step::Elem | Step::Next) => {
+                while self.top_step() == Some(Step::Next) {
+                    let _ = self.ascend();
+                }
+                Ok(())
+            }
+            Some(_) => Ok(()),
+            None => Err(EditError::NoSuchNode),
+        }
+    }
+
+    /// Move to the root.
+    #[inline]
+    pub fn root(&mut self) {
+        while self.ascend().is_some() {}
+    }
+
+    /// Move to the root and give back the tree.
+    #[inline]
+    pub fn into_root(mut self) -> DA::DR {
+        self.root();
+        self.focus
+    }
+
+    /// Move to the operator of the focused `Combination`.
+    ///
+    /// # Errors
+    ///
+    /// If the focus is not on a `Combination`, `EditError::NoSuchNode` is
+    /// returned.  If it's shared, `EditError::Shared` is returned.
+    #[inline]
+    pub fn operator(&mut self) -> Result<(), EditError> {
+        self.descend(Step::Operator)
+    }
+
+    /// Move to the operands of the focused `Combination`.
+    ///
+    /// # Errors
+    ///
+    /// If the focus is not on a `Combination`, `EditError::NoSuchNode` is
+    /// returned.  If it's shared, `EditError::Shared` is returned.
+    #[inline]
+    pub fn operands(&mut self) -> Result<(), EditError> {
+        self.descend(Step::Operands)
+    }
+
+    /// Move to the first child, i.e. the operator of a `Combination` or the
+    /// first element of a `List`.
+    ///
+    /// # Errors
+    ///
+    /// If the focused node has no children, `EditError::NoSuchNode` is
+    /// returned.  If it's shared, `EditError::Shared` is returned.
+    pub fn first_child(&mut self) -> Result<(), EditError> {
+        match *self.focus {
+            Combination{..} => self.descend(Step::Operator),
+            _ => self.descend(Step::Elem),
+        }
+    }
+
+    /// Move to the next sibling, i.e. from the operator of a `Combination` to
+    /// its operands or from a list element to the next element.
+    ///
+    /// # Errors
+    ///
+    /// If there is no next sibling, `EditError::NoSuchNode` is returned.  If
+    /// the rest of the list is shared, `EditError::Shared` is returned.
+    pub fn next_sibling(&mut self) -> Result<(), EditError> {
+        match self.top_step() {
+            Some(Step::Operator) => {
+                let _ = self.ascend();
+                self.descend(Step::Operands)
+            }
+            Some(Step::Elem) => {
+                let _ = self.ascend();
+                self.descend(Step::Next)?;
+                match self.descend(Step::Elem) {
+                    Ok(()) => Ok(()),
+                    Err(e) => {
+                        // Go back, which can't fail because that cell was
+                        // already detached.
+                        let _ = self.ascend();
+                        let _ = self.descend(Step::Elem);
+                        Err(e)
+                    }
+                }
+            }
+            _ => Err(EditError::NoSuchNode),
+        }
+    }
+
+    /// Move to the previous sibling, i.e. from the operands of a `Combination`
+    /// to its operator or from a list element to the previous element.
+    ///
+    /// # Errors
+    ///
+    /// If there is no previous sibling, `EditError::NoSuchNode` is returned.
+    pub fn prev_sibling(&mut self) -> Result<(), EditError> {
+        let len = self.path.len();
+        match self.top_step() {
+            Some(Step::Operands) => {
+                let _ = self.ascend();
+                self.descend(Step::Operator)
+            }
+            Some(Step::Elem) if len >= 2 && self.path[len - 2].step == Step::Next => {
+                let _ = self.ascend();
+                let _ = self.ascend();
+                self.descend(Step::Elem)
+            }
+            _ => Err(EditError::NoSuchNode),
+        }
+    }
+
+    /// Replace the focused node with a new one of the given value, and return
+    /// the old one.
+    ///
+    /// # Errors
+    ///
+    /// If allocating fails, `EditError::FailedAlloc` is returned.
+    #[inline]
+    pub fn replace(&mut self, with: DADatum<DA>) -> Result<DA::DR, EditError> {
+        let new = self.dalloc.new_datum(with)?;
+        Ok(self.replace_ref(new))
+    }
+
+    /// Replace the focused node with the given one, and return the old one.
+    #[inline]
+    pub fn replace_ref(&mut self, with: DA::DR) -> DA::DR {
+        mem::replace(&mut self.focus, with)
+    }
+
+    /// Insert a new element of the given value before the focused list
+    /// element.  The focus stays on the same element.
+    ///
+    /// # Errors
+    ///
+    /// If the focus is not on a list element, `EditError::NotListElem` is
+    /// returned.  If allocating fails, `EditError::FailedAlloc` is returned.
+    pub fn insert_before(&mut self, elem: DADatum<DA>) -> Result<(), EditError> {
+        if self.top_step() != Some(Step::Elem) {
+            return Err(EditError::NotListElem)
+        }
+        let new = self.dalloc.new_datum(elem)?;
+        let cell = self.new_shell()?;
+        // The focus's cell becomes the new element's, and a new cell becomes
+        // the focus's and is linked after it.
+        if let Some(frame) = self.path.last_mut() {
+            let next = mem::replace(&mut frame.sibling, new);
+            frame.step = Step::Next;
+            self.path.push(Frame { shell: cell, sibling: next, step: Step::Elem });
+        }
+        Ok(())
+    }
+
+    /// Insert a new element of the given value after the focused list element.
+    /// The focus stays on the same element.
+    ///
+    /// # Errors
+    ///
+    /// If the focus is not on a list element, `EditError::NotListElem` is
+    /// returned.  If allocating fails, `EditError::FailedAlloc` is returned.
+    pub fn insert_after(&mut self, elem: DADatum<DA>) -> Result<(), EditError> {
+        if self.top_step() != Some(Step::Elem) {
+            return Err(EditError::NotListElem)
+        }
+        let new = self.dalloc.new_datum(elem)?;
+        let mut cell = self.new_shell()?;
+        if let Some(frame) = self.path.last_mut() {
+            mem::swap(&mut frame.sibling, &mut cell);
+            fill(&mut frame.sibling, List { elem: new, next: cell });
+        }
+        Ok(())
+    }
+
+    /// Insert a new element of the given value at the start of the focused
+    /// list, which may be empty.  The focus stays on the list, which now
+    /// starts with the new element.
+    ///
+    /// # Errors
+    ///
+    /// If the focus is not on a `List` or `EmptyList`, `EditError::NotList` is
+    /// returned.  If allocating fails, `EditError::FailedAlloc` is returned.
+    pub fn insert_first(&mut self, elem: DADatum<DA>) -> Result<(), EditError> {
+        match *self.focus {
+            List{..} | EmptyList => (),
+            _ => return Err(EditError::NotList),
+        }
+        let new = self.dalloc.new_datum(elem)?;
+        let cell = self.new_shell()?;
+        let rest = mem::replace(&mut self.focus, cell);
+        fill(&mut self.focus, List { elem: new, next: rest });
+        Ok(())
+    }
+
+    /// Remove the focused list element from its list, and return it.  The
+    /// focus moves to the rest of the list after the element, i.e. to the
+    /// `List` of the next element or to the `EmptyList` at the end.
+    ///
+    /// # Errors
+    ///
+    /// If the focus is not on a list element, `EditError::NotListElem` is
+    /// returned.
+    pub fn remove(&mut self) -> Result<DA::DR, EditError> {
+        if self.top_step() != Some(Step::Elem) {
+            return Err(EditError::NotListElem)
+        }
+        match self.path.pop() {
+            Some(Frame { sibling: next, .. }) => Ok(mem::replace(&mut self.focus, next)),
+            None => Err(EditError::NotListElem),
+        }
+    }
+
+    fn top_step(&self) -> Option<Step> {
+        self.path.last().map(|frame| frame.step)
+    }
+
+    /// Detach the given child of the focused node and move to it.
+    fn descend(&mut self, step: Step) -> Result<(), EditError> {
+        match (&*self.focus, step) {
+            (Combination{..}, Step::Operator | Step::Operands)
+            | (List{..}, Step::Elem | Step::Next) => (),
+            _ => return Err(EditError::NoSuchNode),
+        }
+        let node = DerefTryMut::get_mut(&mut self.focus).ok_or(EditError::Shared)?;
+        let (child, sibling) = match (mem::replace(node, EmptyList), step) {
+            (Combination { operator, operands }, Step::Operator) => (operator, operands),
+            (Combination { operator, operands }, Step::Operands) => (operands, operator),
+            (List { elem, next }, Step::Elem) => (elem, next),
+            (List { elem, next }, Step::Next) => (next, elem),
+            (other, _) => {
+                *node = other;
+                return Err(EditError::NoSuchNode)
+            }
+        };
+        let shell = mem::replace(&mut self.focus, child);
+        self.path.push(Frame { shell, sibling, step });
+        Ok(())
+    }
+
+    /// Reattach the focused node to its parent and move to it, and return
+    /// which child it was.
+    fn ascend(&mut self) -> Option<Step> {
+        let Frame { shell, sibling, step } = self.path.pop()?;
+        let child = mem::replace(&mut self.focus, shell);
+        fill(&mut self.focus, match step {
+            Step::Operator => Combination { operator: child, operands: sibling },
+            Step::Operands => Combination { operator: sibling, operands: child },
+            Step::Elem => List { elem: child, next: sibling },
+            Step::Next => List { elem: sibling, next: child },
+        });
+        Some(step)
+    }
+
+    /// Allocate a new uniquely-held node, which `fill` can be used on.
+    fn new_shell(&mut self) -> Result<DA::DR, EditError> {
+        let mut shell = self.dalloc.new_datum(EmptyList)?;
+        if DerefTryMut::get_mut(&mut shell).is_some() {
+            Ok(shell)
+        } else {
+            Err(EditError::Shared)
+        }
+    }
+}
+
+/// Set the value of a node that is known to be uniquely held, i.e. one that
+/// was detached by `descend` or made by `new_shell`.
+fn fill<TT, ET, DR>(shell: &mut DR, datum: Datum<TT, ET, DR>)
+    where DR: DerefTryMut<Target = Datum<TT, ET, DR>>,
+{
+    match DerefTryMut::get_mut(shell) {
+        Some(node) => *node = datum,
+        None => unreachable!("shell is uniquely held"),
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::rc::Rc;
+    use crate::{
+        Text as _,
+        common::inmem::{Text, DatumAllocator as BoxDA},
+        datum::{DatumBox, DatumRc},
+        parser::RcDatumAllocator,
+    };
+
+    type DR = DatumBox<Text<'static>, ()>;
+
+    /// Make a `Combination` of the given operator text and operand texts.
+    fn form(operator: &'static str, operands: &[&'static str]) -> DR {
+        let list = operands.iter().rev().fold(DatumBox::new(EmptyList), |next, elem|
+            DatumBox::new(List { elem: DatumBox::new(text(elem)), next }));
+        DatumBox::new(Combination { operator: DatumBox::new(text(operator)),
+                                    operands: list })
+    }
+
+    fn text(s: &'static str) -> Datum<Text<'static>, (), DR> {
+        Datum::Text(Text::from_str(s))
+    }
+
+    #[test]
+    fn navigate_and_edit() {
+        let mut dalloc = BoxDA::default();
+        let mut z = Zipper::new(form("f", &["a", "b", "c"]), &mut dalloc);
+        assert_eq!(z.parent(), Err(EditError::NoSuchNode));
+        assert_eq!(z.next_sibling(), Err(EditError::NoSuchNode));
+        assert_eq!(z.first_child(), Ok(()));
+        assert_eq!(*z.focus(), text("f"));
+        assert_eq!(z.insert_after(text("g")), Err(EditError::NotListElem));
+        assert_eq!(z.next_sibling(), Ok(()));
+        assert_eq!(z.prev_sibling(), Ok(()));
+        assert_eq!(z.next_sibling(), Ok(()));
+        assert_eq!(z.first_child(), Ok(()));
+        assert_eq!(z.prev_sibling(), Err(EditError::NoSuchNode));
+        assert_eq!(z.next_sibling(), Ok(()));
+        assert_eq!(*z.replace(text("x")).unwrap(), text("b"));
+        assert_eq!(z.insert_before(text("w")), Ok(()));
+        assert_eq!(z.insert_after(text("y")), Ok(()));
+        assert_eq!(z.prev_sibling(), Ok(()));
+        assert_eq!(*z.focus(), text("w"));
+        assert_eq!(z.next_sibling(), Ok(()));
+        assert_eq!(z.next_sibling(), Ok(()));
+        assert_eq!(*z.remove().unwrap(), text("y"));
+        assert_eq!(z.first_child(), Ok(()));
+        assert_eq!(*z.focus(), text("c"));
+        assert_eq!(z.next_sibling(), Err(EditError::NoSuchNode));
+        assert_eq!(*z.focus(), text("c"));
+        assert_eq!(z.parent(), Ok(()));
+        assert_eq!(z.parent(), Ok(()));
+        assert!(z.is_root());
+        assert_eq!(z.into_root(), form("f", &["a", "w", "x", "c"]));
+    }
+
+    #[test]
+    fn insert_first_and_long() {
+        let mut dalloc = BoxDA::default();
+        let mut z = Zipper::new(form("f", &[]), &mut dalloc);
+        assert_eq!(z.operands(), Ok(()));
+        assert_eq!(z.first_child(), Err(EditError::NoSuchNode));
+        for _ in 0 .. 100_000 {
+            assert_eq!(z.insert_first(text("a")), Ok(()));
+        }
+        assert_eq!(z.first_child(), Ok(()));
+        while z.next_sibling().is_ok() {}
+        *z.focus_mut().unwrap() = text("b");
+        assert_eq!(z.insert_first(text("c")), Err(EditError::NotList));
+        assert_eq!(z.parent(), Ok(()));
+        let root = z.into_root();
+        let mut operands = vec!["a"; 100_000 - 1];
+        operands.push("b");
+        assert!(root == form("f", &operands));
+    }
+
+    #[test]
+    fn shared() {
+        let mut dalloc = RcDatumAllocator::<Text<'static>, ()>::default();
+        let operands = DatumRc::new(List {
+            elem: DatumRc::new(Datum::Text(Text::from_str("a"))),
+            next: DatumRc::new(EmptyList),
+        });
+        let root = DatumRc::new(Combination {
+            operator: DatumRc::new(Datum::Text(Text::from_str("f"))),
+            operands: DatumRc(Rc::clone(&operands.0)),
+        });
+        let mut z = Zipper::new(root, &mut dalloc);
+        assert_eq!(z.operands(), Ok(()));
+        assert_eq!(z.first_child(), Err(EditError::Shared));
+        assert_eq!(z.focus_mut(), Err(EditError::Shared));
+        assert_eq!(z.insert_first(Datum::Text(Text::from_str("b"))), Ok(()));
+        assert_eq!(z.first_child(), Ok(()));
+        assert_eq!(z.next_sibling(), Err(EditError::Shared));
+        assert_eq!(*z.focus(), *DatumRc::new(Datum::Text(Text::from_str("b"))));
+        let root = z.into_root();
+        // The shared list was not mutated but was linked after the new element.
+        assert_eq!(*operands, List {
+            elem: DatumRc::new(Datum::Text(Text::from_str("a"))),
+            next: DatumRc::new(EmptyList),
+        });
+        assert_eq!(Rc::strong_count(&operands.0), 2);
+        assert_eq!(*root, Combination {
+            operator: DatumRc::new(Datum::Text(Text::from_str("f"))),
+            operands: DatumRc::new(List {
+                elem: DatumRc::new(Datum::Text(Text::from_str("b"))),
+                next: operands,
+            }),
+        });
+    }
+}
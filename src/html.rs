@@ -0,0 +1,497 @@
+//! Rendering ASTs, of Kul used as a markup language, to HTML.
+//!
+//! See [`HtmlRenderer`](struct.HtmlRenderer.html).
+
+use std::{collections::HashMap, fmt::{self, Write}, slice};
+
+use crate::{
+    Datum, Text, DerefTryMut,
+    parser::DefaultCharClassifier,
+    datum_util::{to_kul, word_of},
+};
+
+
+/// How a form with a bound operator is rendered as an HTML element.
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct Element {
+    /// The tag name.
+    pub tag: String,
+    /// Attributes that are always given, as names and unescaped values.
+    pub attrs: Vec<(String, String)>,
+    /// The name of an attribute whose value is the first word of the operands,
+    /// e.g. `href` for `{link http://example.com the text}`.  If it's a URL
+    /// attribute, e.g. `href`, `src`, or `formaction`, the value must be
+    /// allowed by the renderer's `url_schemes`.
+    pub operand_attr: Option<String>,
+    /// Whether the element is void, i.e. has no content or end tag, and so
+    /// the operands, other than for `operand_attr`, are ignored.
+    pub void: bool,
+}
+
+impl Element {
+    /// Make a new instance of `Self` for a normal element with the given tag
+    /// name and with no attributes.
+    #[inline]
+    pub fn new<T>(tag: T) -> Self
+        where T: Into<String>,
+    {
+        Self { tag: tag.into(), attrs: Vec::new(), operand_attr: None, void: false }
+    }
+
+    /// Make a new instance of `Self` for a void element with the given tag
+    /// name.
+    #[inline]
+    pub fn void<T>(tag: T) -> Self
+        where T: Into<String>,
+    {
+        Self { void: true, ..Self::new(tag) }
+    }
+
+    /// Make a new instance of `Self` for a normal element with the given tag
+    /// name and whose given attribute's value is the first word of the
+    /// operands.
+    #[inline]
+    pub fn with_operand_attr<T, A>(tag: T, attr: A) -> Self
+        where T: Into<String>,
+              A: Into<String>,
+    {
+        Self { operand_attr: Some(attr.into()), ..Self::new(tag) }
+    }
+}
+
+
+/// What to do with forms whose operators are not mapped to elements.
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+pub enum UnknownOperator {
+    /// Render only the operands, as if the form were not there.
+    Strip,
+    /// Render the whole form as text, as it would be printed as Kul.
+    Keep,
+    /// Fail with `RenderError::UnknownOperator`.
+    Error,
+}
+
+/// The errors that might occur when rendering.
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub enum RenderError {
+    /// Writing the output failed.
+    Fmt(fmt::Error),
+    /// An operator is not mapped to an element, and the policy is
+    /// `UnknownOperator::Error`.  The operator is given as Kul text.
+    UnknownOperator(String),
+    /// The operands of a form for an element with an `operand_attr` don't start
+    /// with a text with a word for its value.  The attribute name is given.
+    MissingAttr(String),
+    /// The value of a URL attribute, from the operands, has a scheme that is
+    /// not in `url_schemes`.
+    UnsafeUrl {
+        /// The attribute name.
+        attr: String,
+        /// The value.
+        url: String,
+    },
+}
+
+impl From<fmt::Error> for RenderError {
+    #[inline]
+    fn from(e: fmt::Error) -> Self {
+        RenderError::Fmt(e)
+    }
+}
+
+
+/// Renders ASTs to HTML, by mapping the texts of operators to [`Element`]s.
+///
+/// `Datum::Text`s are rendered with their characters escaped.  A
+/// `Datum::Combination` whose operator is a `Datum::Text` mapped by `elements`
+/// is rendered as that element, with its operands rendered as the content, and
+/// other `Datum::Combination`s are rendered according to the `unknown` policy.
+/// `Datum::List`s are rendered as their elements in order.  `Datum::EmptyNest`s
+/// and `Datum::EmptyList`s are rendered as nothing.  `Datum::Extra`s are
+/// rendered by the function given to [`render_with`], or as nothing.
+///
+/// The values of URL attributes, e.g. `href`, `src`, `action`, `formaction`,
+/// `poster`, `srcset`, and `xlink:href`, that are given by operands must be
+/// relative URLs or have a scheme in `url_schemes`, so that, e.g.,
+/// `{link javascript:...}` can't inject scripts.
+///
+/// Rendering is iterative, and so very deep or long ASTs don't overflow the
+/// stack, and the output is streamed to the given `fmt::Write`.
+///
+/// [`Element`]: struct.Element.html
+/// [`render_with`]: #method.render_with
+#[derive(Clone, Debug)]
+pub struct HtmlRenderer {
+    /// The mapping of operator texts to elements.  You may change this as you
+    /// want.
+    pub elements: HashMap<String, Element>,
+    /// The policy for operators not in `elements`.
+    pub unknown: UnknownOperator,
+    /// The schemes, in lowercase, that are allowed in URL attribute values
+    /// given by operands.  URLs without a scheme are always allowed.
+    pub url_schemes: Vec<String>,
+}
+
+/// The attributes whose values are, or may contain, URLs that a browser loads
+/// or navigates to, including those of SVG.  These are compared ignoring ASCII
+/// case.
+const URL_ATTRS: [&str; 21] = [
+    "action", "archive", "background", "cite", "classid", "codebase", "data", "dynsrc",
+    "formaction", "href", "icon", "longdesc", "lowsrc", "manifest", "ping", "poster",
+    "profile", "src", "srcset", "usemap", "xlink:href",
+];
+
+/// The default of `HtmlRenderer::url_schemes`.
+fn default_url_schemes() -> Vec<String> {
+    ["http", "https", "mailto"].iter().map(|s| String::from(*s)).collect()
+}
+
+impl Default for HtmlRenderer {
+    /// Make a new instance of `Self` with the mappings: `bold` to `strong`,
+    /// `italic` to `em`, `underline` to `u`, `code` to `code`, `pre` to `pre`,
+    /// `p` to `p`, `quote` to `blockquote`, `h1` to `h1`, `h2` to `h2`, `h3` to
+    /// `h3`, `list` to `ul`, `item` to `li`, `link` to `a` with an `href`
+    /// operand, `image` to void `img` with a `src` operand, and `br` to void
+    /// `br`; with the `Strip` policy for unknown operators; and with the
+    /// `http`, `https`, and `mailto` URL schemes.
+    fn default() -> Self {
+        let mut elements = HashMap::new();
+        for (op, tag) in &[("bold", "strong"), ("italic", "em"), ("underline", "u"),
+                           ("code", "code"), ("pre", "pre"), ("p", "p"),
+                           ("quote", "blockquote"), ("h1", "h1"), ("h2", "h2"),
+                           ("h3", "h3"), ("list", "ul"), ("item", "li")]
+        {
+            let _ = elements.insert(String::from(*op), Element::new(*tag));
+        }
+        let _ = elements.insert(String::from("link"), Element::with_operand_attr("a", "href"));
+        let _ = elements.insert(String::from("image"),
+                                Element { void: true, ..Element::with_operand_attr("img", "src") });
+        let _ = elements.insert(String::from("br"), Element::void("br"));
+        Self { elements, unknown: UnknownOperator::Strip, url_schemes: default_url_schemes() }
+    }
+}
+
+/// The pending steps of rendering.
+enum Work<'d, TT, ET, DR>
+    where DR: DerefTryMut<Target = Datum<TT, ET, DR>>,
+{
+    Form(&'d Datum<TT, ET, DR>),
+    Escaped(String),
+    Raw(String),
+}
+
+impl HtmlRenderer {
+    /// Make a new instance of `Self` with the default mappings and policy.
+    #[inline]
+    #[must_use]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Make a new instance of `Self` without any mappings, with the given
+    /// policy for unknown operators, and with the default URL schemes.
+    #[inline]
+    #[must_use]
+    pub fn empty(unknown: UnknownOperator) -> Self {
+        Self { elements: HashMap::new(), unknown, url_schemes: default_url_schemes() }
+    }
+
+    /// Map the given operator text to the given element, replacing any
+    /// previous mapping of it, and return `self` for chaining.
+    #[inline]
+    #[must_use]
+    pub fn map<O>(mut self, operator: O, element: Element) -> Self
+        where O: Into<String>,
+    {
+        let _ = self.elements.insert(operator.into(), element);
+        self
+    }
+
+    /// Render the given forms, in order, to the given output, rendering
+    /// `Datum::Extra`s as nothing.
+    ///
+    /// # Errors
+    ///
+    /// If writing fails, or if an unknown operator is an error, or if an
+    /// operand attribute is missing or is a URL that is not allowed, the error
+    /// is returned, and the output has what was written until then.
+    #[inline]
+    pub fn render<TT, ET, DR, W>(&self, forms: &[Datum<TT, ET, DR>], out: &mut W)
+                                 -> Result<(), RenderError>
+        where TT: Text,
+              DR: DerefTryMut<Target = Datum<TT, ET, DR>>,
+              W: Write,
+    {
+        self.render_with(forms, out, |_, _| Ok(()))
+    }
+
+    /// Render the given forms, in order, to the given output, rendering
+    /// `Datum::Extra`s by the given function.
+    ///
+    /// # Errors
+    ///
+    /// If writing fails, or if an unknown operator is an error, or if an
+    /// operand attribute is missing or is a URL that is not allowed, or if the
+    /// given function fails, the error is returned, and the output has what was
+    /// written until then.
+    pub fn render_with<TT, ET, DR, W, F>(&self, forms: &[Datum<TT, ET, DR>], out: &mut W,
+                                         mut render_extra: F)
+                                         -> Result<(), RenderError>
+        where TT: Text,
+              DR: DerefTryMut<Target = Datum<TT, ET, DR>>,
+              W: Write,
+              F: FnMut(&ET, &mut W) -> Result<(), RenderError>,
+    {
+        let mut stack: Vec<Work<'_, TT, ET, DR>> = forms.iter().rev().map(Work::Form).collect();
+        while let Some(work) = stack.pop() {
+            match work {
+                Work::Form(Datum::Text(text)) => escape_chars(text.chars(), out)?,
+                Work::Form(Datum::Combination { operator, operands }) => {
+                    let element = word_of(operator).and_then(|w| self.elements.get(&w));
+                    match (element, self.unknown) {
+                        (Some(element), _) =>
+                            self.start_element(element, operands, out, &mut stack)?,
+                        (None, UnknownOperator::Strip) => stack.push(Work::Form(operands)),
+                        (None, UnknownOperator::Keep) => {
+                            let form = work_form(&work);
+                            let kul = to_kul(slice::from_ref(form), &DefaultCharClassifier);
+                            escape_chars(kul.chars(), out)?;
+                        }
+                        (None, UnknownOperator::Error) => return Err(
+                            RenderError::UnknownOperator(
+                                to_kul(slice::from_ref(&**operator), &DefaultCharClassifier))),
+                    }
+                }
+                Work::Form(Datum::List { elem, next }) => {
+                    stack.push(Work::Form(next));
+                    stack.push(Work::Form(elem));
+                }
+                Work::Form(Datum::Extra(extra)) => render_extra(extra, out)?,
+                Work::Form(Datum::EmptyNest | Datum::EmptyList) => (),
+                Work::Escaped(s) => escape_chars(s.chars(), out)?,
+                Work::Raw(s) => out.write_str(&s)?,
+            }
+        }
+        Ok(())
+    }
+
+    /// Write the start tag, and push the content and the end tag.
+    fn start_element<'d, TT, ET, DR, W>(&self, element: &Element,
+                                        operands: &'d Datum<TT, ET, DR>,
+                                        out: &mut W, stack: &mut Vec<Work<'d, TT, ET, DR>>)
+                                        -> Result<(), RenderError>
+        where TT: Text,
+              DR: DerefTryMut<Target = Datum<TT, ET, DR>>,
+              W: Write,
+    {
+        let mut content = operands;
+        let mut rest = None;
+        let mut operand_attr = None;
+        if let Some(name) = &element.operand_attr {
+            // The first word of the first operand text is the value, and the
+            // remainder of that text, after one whitespace, starts the content.
+            let mut value = String::new();
+            if let Datum::List { elem, next } = operands {
+                if let Datum::Text(t) = &**elem {
+                    let mut chars = t.chars().skip_while(|c| c.is_whitespace());
+                    value.extend(chars.by_ref().take_while(|c| !c.is_whitespace()));
+                    rest = Some(chars.collect::<String>());
+                    content = next;
+                }
+            }
+            if value.is_empty() {
+                return Err(RenderError::MissingAttr(name.clone()));
+            }
+            let is_url_attr = URL_ATTRS.iter().any(|attr| attr.eq_ignore_ascii_case(name));
+            if is_url_attr && !self.is_allowed_url(&value) {
+                return Err(RenderError::UnsafeUrl { attr: name.clone(), url: value });
+            }
+            operand_attr = Some((name, value));
+        }
+        write!(out, "<{}", element.tag)?;
+        for (name, value) in &element.attrs {
+            write_attr(name, value.chars(), out)?;
+        }
+        if let Some((name, value)) = operand_attr {
+            write_attr(name, value.chars(), out)?;
+        }
+        out.write_char('>')?;
+        if !element.void {
+            stack.push(Work::Raw(format!("</{}>", element.tag)));
+            stack.push(Work::Form(content));
+            if let Some(rest) = rest {
+                stack.push(Work::Escaped(rest));
+            }
+        }
+        Ok(())
+    }
+
+    /// Is the given URL relative or of an allowed scheme?  Like browsers do,
+    /// leading control characters and spaces, and all tabs and newlines, are
+    /// ignored, and so, e.g., `java\tscript:` is taken as a scheme.  Anything
+    /// before a `:` that comes before any `/`, `?`, or `#` is taken as a scheme.
+    fn is_allowed_url(&self, url: &str) -> bool {
+        let url = url.trim_start_matches(|c: char| c <= ' ')
+                     .chars().filter(|c| !matches!(c, '\t' | '\n' | '\r'));
+        let mut scheme = String::new();
+        for c in url {
+            match c {
+                ':' => return self.url_schemes.iter()
+                                  .any(|allowed| allowed.eq_ignore_ascii_case(&scheme)),
+                '/' | '?' | '#' => return true,
+                c => scheme.push(c),
+            }
+        }
+        true
+    }
+}
+
+fn work_form<'d, TT, ET, DR>(work: &Work<'d, TT, ET, DR>) -> &'d Datum<TT, ET, DR>
+    where DR: DerefTryMut<Target = Datum<TT, ET, DR>>,
+{
+    match work {
+        Work::Form(form) => form,
+        _ => unreachable!(),
+    }
+}
+
+fn write_attr<I, W>(name: &str, value: I, out: &mut W) -> fmt::Result
+    where I: Iterator<Item = char>,
+          W: Write,
+{
+    write!(out, " {name}=\"")?;
+    escape_chars(value, out)?;
+    out.write_char('"')
+}
+
+fn escape_chars<I, W>(chars: I, out: &mut W) -> fmt::Result
+    where I: Iterator<Item = char>,
+          W: Write,
+{
+    for c in chars {
+        match c {
+            '&' => out.write_str("&amp;")?,
+            '<' => out.write_str("&lt;")?,
+            '>' => out.write_str("&gt;")?,
+            '"' => out.write_str("&quot;")?,
+            '\'' => out.write_str("&#39;")?,
+            c => out.write_char(c)?,
+        }
+    }
+    Ok(())
+}
+
+/// Write the given text to the given output with the characters that are
+/// special in HTML text and attribute values escaped.
+///
+/// # Errors
+///
+/// If writing fails.
+#[inline]
+pub fn escape<W>(text: &str, out: &mut W) -> fmt::Result
+    where W: Write,
+{
+    escape_chars(text.chars(), out)
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{common::inmem::parse_str, datum::DatumBox};
+
+    fn render(renderer: &HtmlRenderer, input: &str) -> Result<String, RenderError> {
+        let forms = parse_str(input).into_iter().collect::<Result<Vec<_>, _>>().unwrap();
+        let mut out = String::new();
+        renderer.render(&forms, &mut out).map(|()| out)
+    }
+
+    #[test]
+    fn elements() {
+        let r = HtmlRenderer::new();
+        assert_eq!(render(&r, "{italic {bold styling} the <text>}"),
+                   Ok(String::from("<em><strong>styling</strong> the &lt;text&gt;</em>")));
+        assert_eq!(render(&r, "{link http://x.y/?a=1&b=\"2\" the {bold site}}{br}{image  a.png}"),
+                   Ok(String::from("<a href=\"http://x.y/?a=1&amp;b=&quot;2&quot;\">the \
+                                    <strong>site</strong></a><br><img src=\"a.png\">")));
+        let r = HtmlRenderer::empty(UnknownOperator::Error)
+            .map("note", Element { attrs: vec![("class".into(), "a'b".into())],
+                                   ..Element::new("aside") });
+        assert_eq!(render(&r, "{note x}{}"),
+                   Ok(String::from("<aside class=\"a&#39;b\">x</aside>")));
+    }
+
+    #[test]
+    fn operand_attrs() {
+        let mut r = HtmlRenderer::new();
+        for url in &["https://x.y", "MailTo:a@x.y", "a/b:c", "../a.html", "?q=a:b", "#top"] {
+            assert_eq!(render(&r, &format!("{{link {url} x}}")),
+                       Ok(format!("<a href=\"{url}\">x</a>")));
+        }
+        for url in &["javascript:alert(1)", "JavaScript:x", "\u{1}javascript:x", "data:x",
+                     "vbscript:x"] {
+            assert_eq!(render(&r, &format!("{{link {url} x}}")),
+                       Err(RenderError::UnsafeUrl { attr: String::from("href"),
+                                                    url: String::from(*url) }));
+        }
+        assert!(matches!(render(&r, "{image data:image/png;base64,AA}"),
+                         Err(RenderError::UnsafeUrl { .. })));
+        r.url_schemes.push(String::from("data"));
+        assert_eq!(render(&r, "{image data:image/png;base64,AA}"),
+                   Ok(String::from("<img src=\"data:image/png;base64,AA\">")));
+
+        let r = r.map("button", Element::with_operand_attr("button", "formaction"))
+                 .map("video", Element::with_operand_attr("video", "POSTER"))
+                 .map("use", Element { void: true,
+                                       ..Element::with_operand_attr("use", "xlink:href") });
+        for input in &["{button javascript:alert(1) Go}", "{video javascript:x}",
+                       "{use javascript:x}"] {
+            assert!(matches!(render(&r, input), Err(RenderError::UnsafeUrl { .. })));
+        }
+        assert_eq!(render(&r, "{button /submit Go}"),
+                   Ok(String::from("<button formaction=\"/submit\">Go</button>")));
+
+        for input in &["{link {bold x}}", "{link}", "{link  }", "{image}"] {
+            assert!(matches!(render(&r, input), Err(RenderError::MissingAttr(_))));
+        }
+    }
+
+    #[test]
+    fn unknown() {
+        let input = r"a{x {y b\}} & c}";
+        let mut r = HtmlRenderer::new();
+        assert_eq!(render(&r, input), Ok(String::from("ab} &amp; c")));
+        r.unknown = UnknownOperator::Keep;
+        assert_eq!(render(&r, input), Ok(String::from("a{x {y b\\}} &amp; c}")));
+        r.unknown = UnknownOperator::Error;
+        assert_eq!(render(&r, input), Err(RenderError::UnknownOperator(String::from("x"))));
+        assert_eq!(render(&r, "{{bold} z}"),
+                   Err(RenderError::UnknownOperator(String::from("{bold}"))));
+    }
+
+    #[test]
+    fn extra_and_deep() {
+        let r = HtmlRenderer::new();
+        let forms: Vec<crate::common::document::Datum<u8>> = vec![Datum::Extra(7)];
+        let mut out = String::new();
+        r.render_with(&forms, &mut out, |e, out| Ok(write!(out, "<i>{e}</i>")?)).unwrap();
+        assert_eq!(out, "<i>7</i>");
+
+        // Nested deeper than the parser could.
+        let depth = 100_000;
+        let text = |s| -> crate::common::document::Datum {
+            Datum::Text(crate::common::document::Text::from_str(s))
+        };
+        let mut form = text("x");
+        for _ in 0 .. depth {
+            form = Datum::Combination {
+                operator: DatumBox::new(text("bold")),
+                operands: DatumBox::new(Datum::List { elem: DatumBox::new(form),
+                                                      next: DatumBox::new(Datum::EmptyList) }),
+            };
+        }
+        let mut out = String::new();
+        r.render(slice::from_ref(&form), &mut out).unwrap();
+        assert_eq!(out.len(), depth * "<strong></strong>".len() + 1);
+    }
+}
@@ -85,13 +85,36 @@ pub use kul_core::*;
 
 pub mod drop;
 
+pub mod datum_util;
+
+pub mod source_map;
+
+pub mod html;
+
+pub mod plain_text;
+
+pub mod schema;
+
+pub mod stdlib;
+
+pub mod template;
+
+pub mod value;
+
+pub mod edit;
+
+pub mod diff;
+
 /// `Parser`s and related types and functions, provided for convenience, that
 /// use recommended types for instantiating many of the generic parameters of
 /// this crate, for common basic parsing applications.
 pub mod common {
     mod helper;
     pub mod inmem;
+    pub mod inmem_chars;
     pub mod stream;
+    pub mod document;
+    pub mod include;
 }
 
 /// `SourceStream` types that use the `std` library, including heap allocation.
@@ -123,8 +146,20 @@ pub mod parser {
     mod arc_alloc;
     pub use arc_alloc::ArcDatumAllocator;
 
+    mod arena_alloc;
+    pub use arena_alloc::{ArenaDatumAllocator, DatumArena};
+
+    mod hashcons_alloc;
+    pub use hashcons_alloc::HashConsDatumAllocator;
+
     mod hashmap_bindings;
     pub use hashmap_bindings::HashMapOperatorBindings;
+
+    mod trie_bindings;
+    pub use trie_bindings::TrieOperatorBindings;
+
+    mod pattern_bindings;
+    pub use pattern_bindings::{PatternOperatorBindings, Pattern, Capture, PatternMatch};
 }
 
 /// `Text` types that use the `std` library, including heap allocation.  Also
@@ -145,4 +180,7 @@ pub mod text {
 
     mod vec;
     pub use vec::TextVec;
+
+    mod builder;
+    pub use builder::TextBuilder;
 }
@@ -0,0 +1,175 @@
+use std::mem;
+
+use crate::{
+    datum::premade::{MutRefDatum, DatumMutRef},
+    parser::{DatumAllocator, AllocError},
+    Datum, Text,
+};
+
+
+/// The default number of `Datum` slots in the first chunk of a [`DatumArena`].
+///
+/// [`DatumArena`]: struct.DatumArena.html
+const DEFAULT_FIRST_CHUNK_LEN: usize = 64;
+
+/// Mutable slice of generic `MutRefDatum` elements which reference each other
+/// with mutable borrows of the same lifetime.
+type Slice<'a, TT, ET> = &'a mut [MutRefDatum<'a, TT, ET>];
+
+/// Link to the next chunk of a `DatumArena`.
+type Link<'a, TT, ET> = Option<Box<Chunk<'a, TT, ET>>>;
+
+/// Growable storage of `Datum` slots, for allocating from with an
+/// [`ArenaDatumAllocator`].
+///
+/// The slots are stored in a chain of heap-allocated chunks, where each next
+/// chunk has twice the length of the prior, so that growing never moves
+/// already-allocated elements and the number of chunks stays logarithmic.
+/// Chunks are only created, by an allocator, as they are needed.
+///
+/// Dropping an arena frees its chunks one by one, without traversing the
+/// `Datum` trees that were allocated from it and without any of the tree
+/// restructuring done when dropping the heap-allocated `Datum` reference types
+/// (e.g. [`DatumBox`]).  When the `Text` and "extra" types have no drop glue
+/// (e.g. a [`TextDatumList`] of [`PosStr`] chunks, which is allocated from the
+/// arena itself), the slots are not visited at all and each chunk is freed
+/// with a single deallocation, so the cost of teardown is only logarithmic in
+/// the number of allocated `Datum`s.  Otherwise, the `Text` and "extra" values
+/// contained in the slots must be dropped individually.  Like the elements
+/// used with a [`SliceDatumAllocator`], allocated slots are not reused if
+/// dropped.
+///
+/// [`ArenaDatumAllocator`]: struct.ArenaDatumAllocator.html
+/// [`DatumBox`]: ../datum/struct.DatumBox.html
+/// [`TextDatumList`]: ../../kul_core/text/premade/struct.TextDatumList.html
+/// [`PosStr`]: ../../kul_core/text/chunk/premade/struct.PosStr.html
+/// [`SliceDatumAllocator`]: ../../kul_core/parser/premade/struct.SliceDatumAllocator.html
+#[derive(Debug)]
+pub struct DatumArena<'a, TT, ET> {
+    first_chunk_len: usize,
+    chunks: Link<'a, TT, ET>,
+}
+
+#[derive(Debug)]
+struct Chunk<'a, TT, ET> {
+    slots: Box<[MutRefDatum<'a, TT, ET>]>,
+    next: Link<'a, TT, ET>,
+}
+
+impl<TT, ET> DatumArena<'_, TT, ET> {
+    /// Make a new empty arena.  No chunks are allocated until an allocator
+    /// needs them.
+    #[inline]
+    #[must_use]
+    pub fn new() -> Self {
+        Self::with_first_chunk_len(DEFAULT_FIRST_CHUNK_LEN)
+    }
+
+    /// Make a new empty arena whose first chunk will have the given number of
+    /// `Datum` slots (at least one).  Useful when the approximate size of the
+    /// parsed input is known in advance.
+    #[inline]
+    #[must_use]
+    pub fn with_first_chunk_len(len: usize) -> Self {
+        Self { first_chunk_len: len.max(1), chunks: None }
+    }
+}
+
+/// Must implement this manually because deriving would place unwanted bounds on
+/// the type parameters.
+impl<TT, ET> Default for DatumArena<'_, TT, ET> {
+    #[inline]
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<TT, ET> Chunk<'_, TT, ET> {
+    fn new(len: usize) -> Self {
+        Self {
+            // The value of unallocated slots does not matter.
+            slots: (0 .. len).map(|_| Datum::EmptyList).collect(),
+            next: None,
+        }
+    }
+}
+
+
+/// A [`DatumAllocator`] for generic `Datum` types which allocates `Datum`
+/// values by bumping through the slots of a growable [`DatumArena`] and
+/// supplying mutable borrows of them.
+///
+/// This is useful for applications that parse, inspect, and then discard whole
+/// inputs, because allocating is a cheap bump (except when a new chunk is
+/// needed) and because everything is freed together when the arena is
+/// dropped.  Unlike [`SliceDatumAllocator`], this never runs out of slots.
+///
+/// The `DR` type is the same [`DatumMutRef`] handle as that of
+/// `SliceDatumAllocator`, so `DerefTryMut::get_mut` always succeeds for it,
+/// as the `Parser` needs.  The arena must outlive the allocator and all the
+/// `Datum`s allocated from it.
+///
+/// [`DatumAllocator`]: ../../kul_core/parser/trait.DatumAllocator.html
+/// [`DatumArena`]: struct.DatumArena.html
+/// [`SliceDatumAllocator`]: ../../kul_core/parser/premade/struct.SliceDatumAllocator.html
+/// [`DatumMutRef`]: ../../kul_core/datum/premade/struct.DatumMutRef.html
+#[derive(Debug)]
+pub struct ArenaDatumAllocator<'a, TT, ET> {
+    free: Slice<'a, TT, ET>,
+    next: Option<&'a mut Link<'a, TT, ET>>,
+    next_chunk_len: usize,
+}
+
+impl<'a, TT, ET> ArenaDatumAllocator<'a, TT, ET>
+    where TT: Text,
+{
+    /// Given a mutable borrow of an arena of `Datum`s of our type, make a new
+    /// allocator from it.
+    #[inline]
+    pub fn new(arena: &'a mut DatumArena<'a, TT, ET>) -> Self {
+        Self {
+            free: &mut [],
+            next_chunk_len: arena.first_chunk_len,
+            next: Some(&mut arena.chunks),
+        }
+    }
+
+    /// Advance to the next chunk of the arena, creating it if needed.
+    fn grow(&mut self) -> Result<(), AllocError> {
+        let link = self.next.take().ok_or(AllocError::AllocExhausted)?;
+        let len = self.next_chunk_len;
+        let chunk = link.get_or_insert_with(|| Box::new(Chunk::new(len)));
+        let Chunk { slots, next } = &mut **chunk;
+        self.free = &mut slots[..];
+        self.next = Some(next);
+        self.next_chunk_len = len.saturating_mul(2);
+        Ok(())
+    }
+}
+
+impl<'a, TT, ET> DatumAllocator for ArenaDatumAllocator<'a, TT, ET>
+    where TT: Text,
+{
+    type TT = TT;
+    type ET = ET;
+    type DR = DatumMutRef<'a, Self::TT, Self::ET>;
+
+    fn new_datum(&mut self, from: MutRefDatum<'a, Self::TT, Self::ET>)
+                 -> Result<Self::DR, AllocError>
+    {
+        if self.free.is_empty() {
+            self.grow()?;
+        }
+        match mem::take(&mut self.free).split_first_mut() {
+            Some((dr, rest)) => {
+                *dr = from;
+                self.free = rest;
+                Ok(DatumMutRef(dr))
+            }
+            None => Err(AllocError::AllocExhausted)
+        }
+    }
+}
+
+
+// Note: Tested by the arena_alloc integration test.
@@ -0,0 +1,351 @@
+use std::{
+    collections::{HashSet, hash_map::RandomState},
+    hash::{Hash, Hasher, BuildHasher},
+    mem,
+    rc::Rc,
+};
+
+use crate::{
+    parser::{DatumAllocator, AllocError},
+    datum::{DatumRc, RcDatum},
+    Datum, Text,
+};
+
+
+/// A [`DatumAllocator`] for generic `Datum` types which allocates `Datum`
+/// values in heap-allocated `Rc`s, like [`RcDatumAllocator`], but which
+/// "hash-conses" them: structurally-equal `Datum`s, including `Text`s and whole
+/// sub-trees, are interned so that only a single shared instance of each
+/// exists.  The results are DAGs where repetitions are represented by multiple
+/// references to the same `DatumRc`, which can greatly reduce the memory used
+/// for inputs with much repetition (e.g. generated tables where the same
+/// operator forms are used again and again).
+///
+/// Interning is done by hashing each node shallowly, i.e. by its own text or
+/// "extra" value and by the identities of its already-interned children, and so
+/// a tree is interned in time linear in its size.
+///
+/// `Text`s are interned by their content alone, as compared by `Text`'s
+/// equality, which ignores source positions, because otherwise no two `Text`s
+/// (and so no two forms containing any) could ever be shared.  This means that
+/// a repeated `Text`, and each form containing one, is the instance of its first
+/// occurrence and carries the source positions of that.  The positions of the
+/// later occurrences are lost, and so this allocator is not suitable for uses
+/// that need to report where repetitions are in the source.
+///
+/// The `Datum`s returned by `new_datum` are shared with the interning table of
+/// this allocator, and so `DerefTryMut::get_mut` fails for them, except for
+/// `EmptyList`s which are not interned when first allocated (because the
+/// `Parser` allocates those as placeholders which it then mutates when
+/// constructing lists).  Such `Datum`s become interned when a `Datum` that
+/// refers to them is allocated.  (Which also fully interns any other
+/// uniquely-owned, not-yet-interned descendants, without recursion.)
+///
+/// All interned `Datum`s are kept alive as long as the allocator is.
+///
+/// [`DatumAllocator`]: ../../kul_core/parser/trait.DatumAllocator.html
+/// [`RcDatumAllocator`]: struct.RcDatumAllocator.html
+#[derive(Debug)]
+pub struct HashConsDatumAllocator<TT, ET, S = RandomState> {
+    table: HashSet<Interned<TT, ET>, S>,
+    /// Placeholder that temporarily takes the place of children that are being
+    /// interned.  Never interned.
+    hole: DatumRc<TT, ET>,
+}
+
+/// A `DatumRc` in the interning table.  Hashing and equality of these are
+/// shallow, with children compared by identity, which is correct because the
+/// children of interned `Datum`s are themselves interned.
+#[derive(Debug)]
+struct Interned<TT, ET>(DatumRc<TT, ET>);
+
+impl<TT, ET> HashConsDatumAllocator<TT, ET, RandomState>
+    where TT: Text,
+          ET: Hash + Eq,
+{
+    /// Make a new allocator with an empty interning table.
+    #[inline]
+    #[must_use]
+    pub fn new() -> Self {
+        Self::with_hasher(RandomState::new())
+    }
+}
+
+impl<TT, ET, S> HashConsDatumAllocator<TT, ET, S>
+    where TT: Text,
+          ET: Hash + Eq,
+          S: BuildHasher,
+{
+    /// Make a new allocator with an empty interning table that uses the given
+    /// hash builder.
+    #[inline]
+    pub fn with_hasher(hash_builder: S) -> Self {
+        Self {
+            table: HashSet::with_hasher(hash_builder),
+            hole: DatumRc::new(Datum::EmptyList),
+        }
+    }
+
+    /// The number of distinct `Datum`s currently interned.
+    #[inline]
+    pub fn len(&self) -> usize {
+        self.table.len()
+    }
+
+    /// Whether no `Datum`s are currently interned.
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.table.is_empty()
+    }
+
+    /// Whether the given `Datum` reference is the interned instance of its
+    /// value.
+    fn is_interned(&self, dr: &DatumRc<TT, ET>) -> bool {
+        if let Some(interned) = self.table.get(&Interned(DatumRc(Rc::clone(&dr.0)))) {
+            Rc::ptr_eq(&interned.0 .0, &dr.0)
+        } else {
+            false
+        }
+    }
+
+    /// Intern a single `Datum` whose children are already interned (or are
+    /// shared such that they cannot be), and return the shared instance.
+    fn intern_shallow(&mut self, datum: RcDatum<TT, ET>) -> DatumRc<TT, ET> {
+        let candidate = Interned(DatumRc::new(datum));
+        if let Some(interned) = self.table.get(&candidate) {
+            DatumRc(Rc::clone(&interned.0 .0))
+        } else {
+            let dr = DatumRc(Rc::clone(&candidate.0 .0));
+            let _ = self.table.insert(candidate);
+            dr
+        }
+    }
+
+    /// Intern a `Datum` and all of its not-yet-interned descendants, and return
+    /// the shared instance.  Descendants are processed depth-first using an
+    /// explicit stack, so that long lists and deep nests do not overflow the
+    /// call stack.
+    fn intern(&mut self, datum: RcDatum<TT, ET>) -> DatumRc<TT, ET> {
+        // Each frame is a node being interned and the index of its next child
+        // to consider.
+        let mut stack = vec![(datum, 0)];
+        loop {
+            let (node, next_child) = stack.last_mut().unwrap();
+            if let Some(child) = child_mut(node, *next_child) {
+                *next_child += 1;
+                if !self.is_interned(child) {
+                    let rc = mem::replace(&mut child.0, Rc::clone(&self.hole.0));
+                    match Rc::try_unwrap(rc) {
+                        // Uniquely owned by its parent, so intern its
+                        // descendants and then itself, and then put it back.
+                        Ok(child_datum) => stack.push((child_datum, 0)),
+                        // Shared with some other owner, so it cannot be
+                        // interned, and so it is left as it is.
+                        Err(rc) => child.0 = rc,
+                    }
+                }
+            } else {
+                let (node, _) = stack.pop().unwrap();
+                let interned = self.intern_shallow(node);
+                if let Some((parent, next_child)) = stack.last_mut() {
+                    let child = child_mut(parent, *next_child - 1).unwrap();
+                    debug_assert!(Rc::ptr_eq(&child.0, &self.hole.0));
+                    *child = interned;
+                } else {
+                    break interned
+                }
+            }
+        }
+    }
+}
+
+/// Must implement this manually because deriving would place unwanted bounds on
+/// the type parameters.
+impl<TT, ET, S> Default for HashConsDatumAllocator<TT, ET, S>
+    where TT: Text,
+          ET: Hash + Eq,
+          S: BuildHasher + Default,
+{
+    #[inline]
+    fn default() -> Self {
+        Self::with_hasher(S::default())
+    }
+}
+
+impl<TT, ET, S> DatumAllocator for HashConsDatumAllocator<TT, ET, S>
+    where TT: Text,
+          ET: Hash + Eq,
+          S: BuildHasher,
+{
+    type TT = TT;
+    type ET = ET;
+    type DR = DatumRc<Self::TT, Self::ET>;
+
+    fn new_datum(&mut self, from: RcDatum<Self::TT, Self::ET>)
+                 -> Result<Self::DR, AllocError>
+    {
+        Ok(if let Datum::EmptyList = from {
+            // Might be a placeholder that the `Parser` will mutate, so it must
+            // be unique.
+            DatumRc::new(from)
+        } else {
+            self.intern(from)
+        })
+    }
+}
+
+/// The child at the given index of the given `Datum`, if it has one there.
+fn child_mut<TT, ET>(datum: &mut RcDatum<TT, ET>, index: usize)
+                     -> Option<&mut DatumRc<TT, ET>>
+{
+    match datum {
+        Datum::Combination{operator: child1, operands: child2}
+        | Datum::List{elem: child1, next: child2}
+            => match index {
+                0 => Some(child1),
+                1 => Some(child2),
+                _ => None
+            },
+        _
+            => None
+    }
+}
+
+impl<TT, ET> Hash for Interned<TT, ET>
+    where TT: Text,
+          ET: Hash,
+{
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        use Datum::{Combination, EmptyNest, List, EmptyList, Extra};
+
+        let datum = &*self.0;
+        mem::discriminant(datum).hash(state);
+        match datum {
+            Datum::Text(text)
+                => Text::hash(text, state),
+            Combination{operator: child1, operands: child2}
+            | List{elem: child1, next: child2}
+                => { Rc::as_ptr(&child1.0).hash(state);
+                     Rc::as_ptr(&child2.0).hash(state); },
+            Extra(et)
+                => et.hash(state),
+            EmptyNest | EmptyList
+                => (),
+        }
+    }
+}
+
+impl<TT, ET> PartialEq for Interned<TT, ET>
+    where TT: Text,
+          ET: Eq,
+{
+    fn eq(&self, other: &Self) -> bool {
+        use Datum::{Combination, EmptyNest, List, EmptyList, Extra};
+
+        match (&*self.0, &*other.0) {
+            (Datum::Text(txt1), Datum::Text(txt2))
+                => Text::eq(txt1, txt2),
+            (Combination{operator: rtr1, operands: rnds1},
+             Combination{operator: rtr2, operands: rnds2})
+                => Rc::ptr_eq(&rtr1.0, &rtr2.0) && Rc::ptr_eq(&rnds1.0, &rnds2.0),
+            (List{elem: e1, next: n1}, List{elem: e2, next: n2})
+                => Rc::ptr_eq(&e1.0, &e2.0) && Rc::ptr_eq(&n1.0, &n2.0),
+            (EmptyNest, EmptyNest) | (EmptyList, EmptyList)
+                => true,
+            (Extra(et1), Extra(et2))
+                => et1 == et2,
+            _
+                => false
+        }
+    }
+}
+
+impl<TT, ET> Eq for Interned<TT, ET>
+    where TT: Text,
+          ET: Eq,
+{}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        Parser, DerefTryMut,
+        parser::{DefaultCharClassifier, EmptyOperatorBindings},
+        text::{TextVec, chunk::PosStr},
+    };
+
+    type TT = TextVec<PosStr<'static>>;
+
+    fn parse(input: &'static str) -> Vec<RcDatum<TT, ()>> {
+        let mut parser = Parser {
+            classifier: DefaultCharClassifier,
+            allocator: HashConsDatumAllocator::<TT, ()>::new(),
+            bindings: EmptyOperatorBindings,
+        };
+        parser.parse(TT::from_str(input).iter())
+              .collect::<Result<_, _>>()
+              .unwrap()
+    }
+
+    fn operands(datum: &RcDatum<TT, ()>) -> Vec<DatumRc<TT, ()>> {
+        let mut elems = vec![];
+        if let Datum::Combination{operands, ..} = datum {
+            let mut cur = operands;
+            while let Datum::List{elem, next} = &**cur {
+                elems.push(elem.clone());
+                cur = next;
+            }
+        }
+        elems
+    }
+
+    #[test]
+    fn shares_repeats() {
+        let forms = parse("{row {c x} {c y} {c x}} {row {c x} {c y} {c x}}");
+        assert_eq!(forms.len(), 3);
+        let (row1, row2) = (operands(&forms[0]), operands(&forms[2]));
+        assert_eq!(row1.len(), 5);
+        // Repeated sub-forms within a form are shared.
+        assert!(Rc::ptr_eq(&row1[0].0, &row1[4].0));
+        assert!(!Rc::ptr_eq(&row1[0].0, &row1[2].0));
+        // Whitespace texts are shared.
+        assert!(Rc::ptr_eq(&row1[1].0, &row1[3].0));
+        // Repeated sub-forms across forms are shared.
+        for (e1, e2) in row1.iter().zip(&row2) {
+            assert!(Rc::ptr_eq(&e1.0, &e2.0));
+        }
+        // Repeated operators are shared.
+        if let (Datum::Combination{operator: o1, ..},
+                Datum::Combination{operator: o2, ..}) = (&forms[0], &forms[2]) {
+            assert!(Rc::ptr_eq(&o1.0, &o2.0));
+        } else {
+            unreachable!()
+        }
+        assert_eq!(forms[0], forms[2]);
+        assert_ne!(forms[0], forms[1]);
+    }
+
+    #[test]
+    fn long_list() {
+        let input: &'static str
+            = Box::leak(format!("{{l{}}}", " {a b}".repeat(100_000)).into_boxed_str());
+        let forms = parse(input);
+        assert_eq!(forms.len(), 1);
+        let elems = operands(&forms[0]);
+        assert_eq!(elems.len(), 200_000 - 1);
+        assert!(elems.iter().step_by(2).all(|e| Rc::ptr_eq(&e.0, &elems[0].0)));
+    }
+
+    #[test]
+    fn placeholder_mutable() {
+        let mut alloc = HashConsDatumAllocator::<TT, ()>::new();
+        let mut empty = alloc.new_datum(Datum::EmptyList).unwrap();
+        assert!(DerefTryMut::get_mut(&mut empty).is_some());
+        let mut text = alloc.new_datum(Datum::Text(TT::from_str("t"))).unwrap();
+        assert!(DerefTryMut::get_mut(&mut text).is_none());
+        let text2 = alloc.new_datum(Datum::Text(TT::from_str("t"))).unwrap();
+        assert!(Rc::ptr_eq(&text.0, &text2.0));
+        assert_eq!(alloc.len(), 1);
+    }
+}
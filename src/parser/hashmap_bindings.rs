@@ -3,14 +3,13 @@
 use std::{
     collections::{HashMap, hash_map::RandomState},
     hash::{Hash, BuildHasher},
-    ops::Deref,
     marker::PhantomData,
 };
 
 use crate::{
     Datum, Combiner,
     parser::{OperatorBindings, DatumAllocator},
-    combiner::{OpFn, ApFn},
+    combiner::{Operate, Apply},
 };
 
 
@@ -86,16 +85,16 @@ impl<DA, OR, AR, CE, S>
 }
 
 
-impl<DA, OR, AR, CE, S>
-    OperatorBindings<DA>
+impl<DA, OR, AR, CE, S, UC>
+    OperatorBindings<DA, UC>
     for HashMapOperatorBindings<DA, OR, AR, CE, S>
     where DA: DatumAllocator,
           DA::TT: Hash + Eq,
           DA::ET: Hash + Eq,
           DA::DR: Hash + Eq,
           S: BuildHasher,
-          OR: Deref<Target = OpFn<DA, CE>>,
-          AR: Deref<Target = ApFn<DA, CE>>,
+          OR: Operate<DA, CE, UC>,
+          AR: Apply<DA, CE, UC>,
 {
     type OR = OR;
     type AR = AR;
@@ -114,6 +113,7 @@ impl<DA, OR, AR, CE, S>
 mod tests {
     use super::*;
     use crate::{
+        combiner::{OpFn, ApFn},
         text::{Text, TextVec, chunk::PosStr},
         parser::BoxDatumAllocator,
         datum::DatumBox,
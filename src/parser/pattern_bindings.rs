@@ -0,0 +1,546 @@
+#![allow(clippy::type_complexity)]
+
+use std::{borrow::Cow, fmt, marker::PhantomData};
+
+use crate::{
+    Datum, Combiner, Text,
+    parser::{OperatorBindings, DatumAllocator},
+    combiner::{Operate, Apply},
+};
+
+
+/// A pattern that an operator sub-form is matched against, by
+/// [`PatternOperatorBindings`].
+///
+/// Patterns that match the text of a `Datum::Text` borrow it as a `&str` when
+/// it is in a single chunk (see [`Text::as_single_str`]), which is the common
+/// case for operators, and only otherwise collect its `char`s into a temporary
+/// `String`.  If the pattern is inside a `Normalized`, the text is normalized
+/// into a new `String`.  Some patterns capture parts of what they match, which are
+/// given in the order that the patterns appear, depth-first.  Captures are only
+/// made by [`captures`](#method.captures), and not by
+/// [`is_match`](#method.is_match), which is what looking-up uses.
+///
+/// [`PatternOperatorBindings`]: struct.PatternOperatorBindings.html
+/// [`Text::as_single_str`]: ../../kul_core/text/trait.Text.html#method.as_single_str
+pub enum Pattern<DA>
+    where DA: DatumAllocator,
+{
+    /// Matches a `Datum::Text` whose text is equal to the string.
+    Text(String),
+    /// Matches a `Datum::Text` whose text starts with the string, and captures
+    /// the rest of the text as a `Capture::Text`.
+    Prefix(String),
+    /// Matches a `Datum::Text` whose text ends with the string, and captures
+    /// the beginning of the text as a `Capture::Text`.
+    Suffix(String),
+    /// Matches a `Datum::Text` whose text the function returns `true` for.
+    TextPredicate(Box<dyn Fn(&str) -> bool>),
+    /// Matches any `Datum` that the function returns `true` for.
+    Predicate(Box<dyn Fn(&Datum<DA::TT, DA::ET, DA::DR>) -> bool>),
+    /// Matches the same as the inner pattern, but with the texts given to the
+    /// inner pattern first transformed by the function, e.g. to lowercase them
+    /// to match case-insensitively.  The strings of the inner pattern are not
+    /// transformed, and so they should already be normalized.
+    Normalized(Box<dyn Fn(&str) -> String>, Box<Pattern<DA>>),
+    /// Matches any `Datum`, and captures it as a `Capture::Datum`.  I.e. a
+    /// wildcard.
+    Any,
+    /// Matches a `Datum::Combination` whose operator matches the first pattern
+    /// and whose operands list has an element that matches each of the other
+    /// patterns, in order, and no more elements.  If the last of the patterns
+    /// is `Rest`, it matches the rest of the list.
+    Combination(Box<Pattern<DA>>, Vec<Pattern<DA>>),
+    /// Matches the rest of an operands list, as the last pattern of a
+    /// `Combination`, and captures the rest, which might be empty, as a
+    /// `Capture::Datum`.  Elsewhere, it matches nothing.
+    Rest,
+}
+
+/// A captured part of an operator sub-form.  See [`Pattern`].
+///
+/// [`Pattern`]: enum.Pattern.html
+pub enum Capture<'d, DA>
+    where DA: DatumAllocator,
+{
+    /// Part of a text, after any normalization.
+    Text(String),
+    /// A sub-form, or the rest of a list.
+    Datum(&'d Datum<DA::TT, DA::ET, DA::DR>),
+}
+
+/// Must implement this manually because deriving would place unwanted bounds on
+/// the type parameters.
+impl<DA> fmt::Debug for Capture<'_, DA>
+    where DA: DatumAllocator,
+          Datum<DA::TT, DA::ET, DA::DR>: fmt::Debug,
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Capture::Text(s) => f.debug_tuple("Text").field(s).finish(),
+            Capture::Datum(d) => f.debug_tuple("Datum").field(d).finish(),
+        }
+    }
+}
+
+impl<DA> PartialEq for Capture<'_, DA>
+    where DA: DatumAllocator,
+          Datum<DA::TT, DA::ET, DA::DR>: PartialEq,
+{
+    fn eq(&self, other: &Self) -> bool {
+        match (self, other) {
+            (Capture::Text(a), Capture::Text(b)) => a == b,
+            (Capture::Datum(a), Capture::Datum(b)) => a == b,
+            _ => false,
+        }
+    }
+}
+
+
+impl<DA> Pattern<DA>
+    where DA: DatumAllocator,
+{
+    /// Match the given `Datum` against `self`, and return the captured parts
+    /// if it matches, or `None` if it doesn't.
+    ///
+    /// This enables a combiner to get the parts of its operator that the
+    /// pattern it is bound with captures.
+    pub fn captures<'d>(&self, datum: &'d Datum<DA::TT, DA::ET, DA::DR>)
+                        -> Option<Vec<Capture<'d, DA>>>
+    {
+        let mut captures = Vec::new();
+        if self.matches(datum, None, Some(&mut captures)) {
+            Some(captures)
+        } else {
+            None
+        }
+    }
+
+    /// Returns `true` if the given `Datum` matches `self`.  Unlike
+    /// [`captures`](#method.captures), this doesn't allocate, unless a text
+    /// must be collected or normalized.
+    #[inline]
+    pub fn is_match(&self, datum: &Datum<DA::TT, DA::ET, DA::DR>) -> bool {
+        self.matches(datum, None, None)
+    }
+
+    /// Match `datum`, and push what is captured to `captures` if given.
+    fn matches<'d>(&self,
+                   datum: &'d Datum<DA::TT, DA::ET, DA::DR>,
+                   normalize: Option<&dyn Fn(&str) -> String>,
+                   mut captures: Option<&mut Vec<Capture<'d, DA>>>)
+                   -> bool
+    {
+        let text = || if let Datum::Text(text) = datum {
+            let s = text.as_single_str().map_or_else(|| Cow::Owned(text.chars().collect()),
+                                                     Cow::Borrowed);
+            Some(if let Some(normalize) = normalize { Cow::Owned(normalize(&s)) } else { s })
+        } else {
+            None
+        };
+        let mut capture_text = |rest: &str| {
+            if let Some(captures) = captures.as_deref_mut() {
+                captures.push(Capture::Text(rest.to_owned()));
+            }
+        };
+        match self {
+            Pattern::Text(t) => match (datum, normalize) {
+                (Datum::Text(text), None) => text.chars().eq(t.chars()),
+                _ => text().is_some_and(|s| s == *t),
+            },
+            Pattern::Prefix(p) => text().is_some_and(|s| {
+                s.strip_prefix(p.as_str()).map(&mut capture_text).is_some()
+            }),
+            Pattern::Suffix(p) => text().is_some_and(|s| {
+                s.strip_suffix(p.as_str()).map(&mut capture_text).is_some()
+            }),
+            Pattern::TextPredicate(pred) => text().is_some_and(|s| pred(&s)),
+            Pattern::Predicate(pred) => pred(datum),
+            Pattern::Normalized(normalize, inner) =>
+                inner.matches(datum, Some(&**normalize), captures),
+            Pattern::Any => {
+                if let Some(captures) = captures {
+                    captures.push(Capture::Datum(datum));
+                }
+                true
+            },
+            Pattern::Combination(operator_pat, operand_pats) => {
+                if let Datum::Combination { operator, operands } = datum {
+                    operator_pat.matches(operator, normalize, captures.as_deref_mut())
+                        && Self::matches_list(operand_pats, operands, normalize, captures)
+                } else {
+                    false
+                }
+            },
+            Pattern::Rest => false,
+        }
+    }
+
+    fn matches_list<'d>(patterns: &[Self],
+                        mut list: &'d Datum<DA::TT, DA::ET, DA::DR>,
+                        normalize: Option<&dyn Fn(&str) -> String>,
+                        mut captures: Option<&mut Vec<Capture<'d, DA>>>)
+                        -> bool
+    {
+        for pattern in patterns {
+            if let Pattern::Rest = pattern {
+                if let Some(captures) = captures {
+                    captures.push(Capture::Datum(list));
+                }
+                return true;
+            }
+            if let Datum::List { elem, next } = list {
+                if !pattern.matches(elem, normalize, captures.as_deref_mut()) {
+                    return false;
+                }
+                list = next;
+            } else {
+                return false;
+            }
+        }
+        matches!(list, Datum::EmptyList)
+    }
+}
+
+/// Must implement this manually because the functions can't be formatted.
+impl<DA> fmt::Debug for Pattern<DA>
+    where DA: DatumAllocator,
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Pattern::Text(s) => f.debug_tuple("Text").field(s).finish(),
+            Pattern::Prefix(s) => f.debug_tuple("Prefix").field(s).finish(),
+            Pattern::Suffix(s) => f.debug_tuple("Suffix").field(s).finish(),
+            Pattern::TextPredicate(_) => f.write_str("TextPredicate(..)"),
+            Pattern::Predicate(_) => f.write_str("Predicate(..)"),
+            Pattern::Normalized(_, inner) =>
+                f.debug_tuple("Normalized").field(&"..").field(inner).finish(),
+            Pattern::Any => f.write_str("Any"),
+            Pattern::Combination(operator, operands) =>
+                f.debug_tuple("Combination").field(operator).field(operands).finish(),
+            Pattern::Rest => f.write_str("Rest"),
+        }
+    }
+}
+
+
+/// An [`OperatorBindings`] that associates [`Pattern`]s with generic
+/// `Combiner`s, using a `Vec` of entries that are tried in order of priority.
+///
+/// Looking-up an operator returns the `Combiner` of the first entry whose
+/// pattern matches it.  Entries with a higher priority are tried first, and
+/// entries with equal priorities are tried in the order they were inserted, and
+/// so the order is deterministic.  [`lookup_match`] also returns what the
+/// matching pattern captured, and a combiner can get that for its operator
+/// via [`Pattern::captures`].
+///
+/// You are responsible for inserting the desired bindings, and they may be
+/// dynamically mutated inbetween parser invocations, if desired.
+///
+/// [`OperatorBindings`]: ../../kul_core/parser/trait.OperatorBindings.html
+/// [`Pattern`]: enum.Pattern.html
+/// [`lookup_match`]: #method.lookup_match
+/// [`Pattern::captures`]: enum.Pattern.html#method.captures
+#[derive(Debug)]
+pub struct PatternOperatorBindings<DA, OR, AR, CE>
+    where DA: DatumAllocator,
+{
+    /// Sorted by descending priority, and by insertion order within a
+    /// priority.
+    entries: Vec<Entry<DA, OR, AR>>,
+    /// We want `Self` to be parameterized over the "combiner error" type
+    /// because this better fits with the `OperatorBindings` trait and the uses
+    /// of it.
+    have_combiner_error_type: PhantomData<*const CE>,
+}
+
+#[derive(Debug)]
+struct Entry<DA, OR, AR>
+    where DA: DatumAllocator,
+{
+    priority: i32,
+    pattern: Pattern<DA>,
+    combiner: Combiner<OR, AR>,
+}
+
+/// The result of [`PatternOperatorBindings::lookup_match`].
+///
+/// [`PatternOperatorBindings::lookup_match`]: struct.PatternOperatorBindings.html#method.lookup_match
+pub struct PatternMatch<'b, 'd, DA, OR, AR>
+    where DA: DatumAllocator,
+{
+    /// The pattern that matched.
+    pub pattern: &'b Pattern<DA>,
+    /// The `Combiner` bound to the pattern.
+    pub combiner: &'b Combiner<OR, AR>,
+    /// What the pattern captured.
+    pub captures: Vec<Capture<'d, DA>>,
+}
+
+
+impl<DA, OR, AR, CE> PatternOperatorBindings<DA, OR, AR, CE>
+    where DA: DatumAllocator,
+{
+    /// Make a new instance of `Self` without any bindings.
+    #[inline]
+    #[must_use]
+    pub fn new() -> Self {
+        Self {
+            entries: Vec::new(),
+            have_combiner_error_type: PhantomData,
+        }
+    }
+
+    /// The number of bindings.
+    #[inline]
+    #[must_use]
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    /// Returns `true` if there are no bindings.
+    #[inline]
+    #[must_use]
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    /// Remove all bindings.
+    #[inline]
+    pub fn clear(&mut self) {
+        self.entries.clear();
+    }
+
+    /// Bind the given pattern, with the given priority, to the given
+    /// `Combiner`.  It is tried after all the bindings that have a higher
+    /// priority and after those already inserted with the same priority.
+    pub fn insert(&mut self, priority: i32, pattern: Pattern<DA>,
+                  combiner: Combiner<OR, AR>)
+    {
+        let idx = self.entries.iter()
+                              .position(|e| e.priority < priority)
+                              .unwrap_or(self.entries.len());
+        self.entries.insert(idx, Entry { priority, pattern, combiner });
+    }
+
+    /// Remove the bindings for which the given function returns `true` given
+    /// their priority and pattern, and return how many were removed.
+    pub fn remove_where<F>(&mut self, mut f: F) -> usize
+        where F: FnMut(i32, &Pattern<DA>) -> bool,
+    {
+        let len = self.entries.len();
+        self.entries.retain(|e| !f(e.priority, &e.pattern));
+        len - self.entries.len()
+    }
+
+    /// Like [`lookup`], but also return the pattern that matched and what it
+    /// captured.  Only the pattern that matched makes captures.
+    ///
+    /// [`lookup`]: ../../kul_core/parser/trait.OperatorBindings.html#tymethod.lookup
+    pub fn lookup_match<'b, 'd>(&'b self, operator: &'d Datum<DA::TT, DA::ET, DA::DR>)
+                                -> Option<PatternMatch<'b, 'd, DA, OR, AR>>
+    {
+        let e = self.entries.iter().find(|e| e.pattern.is_match(operator))?;
+        e.pattern.captures(operator).map(|captures| PatternMatch {
+            pattern: &e.pattern,
+            combiner: &e.combiner,
+            captures,
+        })
+    }
+}
+
+
+/// Must implement this manually because deriving would place unwanted bounds on
+/// the type parameters.
+impl<DA, OR, AR> fmt::Debug for PatternMatch<'_, '_, DA, OR, AR>
+    where DA: DatumAllocator,
+          Datum<DA::TT, DA::ET, DA::DR>: fmt::Debug,
+          OR: fmt::Debug,
+          AR: fmt::Debug,
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("PatternMatch")
+         .field("pattern", self.pattern)
+         .field("combiner", self.combiner)
+         .field("captures", &self.captures)
+         .finish()
+    }
+}
+
+
+/// Must implement this manually because deriving would place unwanted bounds on
+/// the type parameters.
+impl<DA, OR, AR, CE> Default for PatternOperatorBindings<DA, OR, AR, CE>
+    where DA: DatumAllocator,
+{
+    #[inline]
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+
+impl<DA, OR, AR, CE, UC>
+    OperatorBindings<DA, UC>
+    for PatternOperatorBindings<DA, OR, AR, CE>
+    where DA: DatumAllocator,
+          OR: Operate<DA, CE, UC>,
+          AR: Apply<DA, CE, UC>,
+{
+    type OR = OR;
+    type AR = AR;
+    type CE = CE;
+
+    #[inline]
+    fn lookup(&self, operator: &Datum<DA::TT, DA::ET, DA::DR>)
+              -> Option<&Combiner<OR, AR>>
+    {
+        self.entries.iter()
+                    .find(|e| e.pattern.is_match(operator))
+                    .map(|e| &e.combiner)
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        combiner::{OpFn, ApFn},
+        text::{TextVec, chunk::PosStr},
+        parser::{BoxDatumAllocator, DefaultCharClassifier},
+        datum::DatumBox,
+        Parser,
+    };
+
+    type TT = TextVec<PosStr<'static>>;
+    type DA = BoxDatumAllocator<TT, ()>;
+    type Bindings = PatternOperatorBindings<DA, Box<OpFn<DA, ()>>, Box<ApFn<DA, ()>>, ()>;
+    type TestDatum = Datum<TT, (), DatumBox<TT, ()>>;
+
+    fn text(s: &'static str) -> TestDatum {
+        Datum::Text(TT::from_str(s))
+    }
+
+    fn list(elems: Vec<TestDatum>) -> TestDatum {
+        elems.into_iter().rev().fold(Datum::EmptyList, |next, elem|
+            Datum::List { elem: DatumBox::new(elem), next: DatumBox::new(next) })
+    }
+
+    fn comb(operator: TestDatum, operands: Vec<TestDatum>) -> TestDatum {
+        Datum::Combination { operator: DatumBox::new(operator),
+                             operands: DatumBox::new(list(operands)) }
+    }
+
+    fn heading() -> Pattern<DA> {
+        Pattern::Combination(
+            Box::new(Pattern::Prefix(String::from("h"))), vec![Pattern::Rest])
+    }
+
+    fn marker(name: &'static str) -> Combiner<Box<OpFn<DA, ()>>, Box<ApFn<DA, ()>>> {
+        Combiner::Applicative(Box::new(move |_, _, _| Ok(Some(text(name)))))
+    }
+
+    #[test]
+    fn captures() {
+        let h = Pattern::<DA>::Prefix(String::from("h"));
+        assert_eq!(h.captures(&text("h12")), Some(vec![Capture::Text(String::from("12"))]));
+        assert_eq!(h.captures(&text("x")), None);
+        assert_eq!(h.captures(&Datum::EmptyNest), None);
+
+        let ci = Pattern::<DA>::Normalized(Box::new(str::to_lowercase),
+                                           Box::new(Pattern::Suffix(String::from("!"))));
+        assert_eq!(ci.captures(&text("ABC!")), Some(vec![Capture::Text(String::from("abc"))]));
+
+        let tag = comb(text("tag"), vec![text("x")]);
+        let p = Pattern::<DA>::Combination(Box::new(Pattern::Text(String::from("tag"))),
+                                           vec![Pattern::Any]);
+        assert_eq!(p.captures(&tag), Some(vec![Capture::Datum(&text("x"))]));
+        assert!(!p.is_match(&comb(text("tag"), vec![])));
+        assert!(!p.is_match(&comb(text("tag"), vec![text("x"), text("y")])));
+        assert!(!p.is_match(&comb(text("tog"), vec![text("x")])));
+
+        let rest = &comb(text("h2"), vec![]);
+        assert_eq!(heading().captures(rest),
+                   Some(vec![Capture::Text(String::from("2")),
+                             Capture::Datum(&Datum::EmptyList)]));
+        assert!(!Pattern::<DA>::Rest.is_match(&Datum::EmptyList));
+    }
+
+    #[test]
+    fn priority() {
+        let mut pob = Bindings::new();
+        pob.insert(0, Pattern::Any, marker("any"));
+        pob.insert(1, Pattern::Prefix(String::from("h")), marker("h"));
+        pob.insert(1, Pattern::Text(String::from("hr")), marker("hr"));
+        pob.insert(2, Pattern::TextPredicate(Box::new(
+            |s| s.len() == 2 && s.starts_with('h')
+                && s[1..].chars().all(|c| ('1' ..= '6').contains(&c)))),
+                   marker("heading"));
+        assert_eq!(pob.len(), 4);
+
+        let name = |pob: &Bindings, d: &TestDatum| {
+            pob.lookup_match(d).map(|m| {
+                if let Combiner::Applicative(ap) = m.combiner {
+                    ap(Datum::EmptyNest, Datum::EmptyList, &mut DA::default())
+                } else { unreachable!() }
+            })
+        };
+        assert_eq!(name(&pob, &text("h3")), Some(Ok(Some(text("heading")))));
+        assert_eq!(name(&pob, &text("h7")), Some(Ok(Some(text("h")))));
+        assert_eq!(name(&pob, &text("hr")), Some(Ok(Some(text("h")))));
+        assert_eq!(name(&pob, &Datum::EmptyNest), Some(Ok(Some(text("any")))));
+        assert_eq!(pob.lookup_match(&text("h7")).unwrap().captures,
+                   vec![Capture::Text(String::from("7"))]);
+
+        assert_eq!(pob.remove_where(|p, _| p == 1), 2);
+        assert_eq!(name(&pob, &text("hr")), Some(Ok(Some(text("any")))));
+        pob.clear();
+        assert!(pob.is_empty());
+        assert_eq!(pob.lookup(&text("hr")).map(|_| true), None);
+    }
+
+    #[test]
+    fn parse() {
+        // Wraps its operands with the level of the heading, which is the number
+        // that its operator has after the case-insensitive prefix.
+        let h: Box<ApFn<DA, ()>> = Box::new(|operator, operands, _| {
+            let caps = Pattern::<DA>::Normalized(Box::new(str::to_lowercase),
+                                                 Box::new(Pattern::Prefix(String::from("h"))))
+                             .captures(&operator);
+            let level = match caps.as_ref().map(|caps| &caps[0]) {
+                Some(Capture::Text(level)) => level.parse::<usize>().unwrap(),
+                _ => unreachable!(),
+            };
+            Ok(Some(comb(text(["h", "hh", "hhh"][level - 1]), vec![operands])))
+        });
+        let tag: Box<OpFn<DA, ()>> = Box::new(|operator, operands, _| {
+            Ok(Some(comb(operator, vec![Datum::Text(operands)])))
+        });
+        let mut bindings = Bindings::new();
+        bindings.insert(0, Pattern::Normalized(
+            Box::new(str::to_lowercase),
+            Box::new(Pattern::TextPredicate(Box::new(
+                |s| s.len() == 2 && s.starts_with('h')
+                    && s[1..].chars().all(|c| ('1' ..= '3').contains(&c)))))),
+                        Combiner::Applicative(h));
+        bindings.insert(0, Pattern::Combination(Box::new(Pattern::Text(String::from("tag"))),
+                                                vec![Pattern::Any]),
+                        Combiner::Operative(tag));
+        let mut parser = Parser {
+            classifier: DefaultCharClassifier,
+            allocator: DA::default(),
+            bindings,
+        };
+        let input = "{H2 a}{{tag x} {b}}{hr}{{tag} c}";
+        let results: Vec<_> = parser.parse(TT::from_str(input).iter())
+                                    .collect::<Result<_, _>>()
+                                    .unwrap();
+        assert_eq!(results, vec![
+            comb(text("hh"), vec![list(vec![text("a")])]),
+            comb(comb(text("tag"), vec![text("x")]), vec![text("{b}")]),
+            comb(text("hr"), vec![]),
+            comb(comb(text("tag"), vec![]), vec![text("c")]),
+        ]);
+    }
+}
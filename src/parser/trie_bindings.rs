@@ -0,0 +1,318 @@
+#![allow(clippy::type_complexity)]
+
+use std::marker::PhantomData;
+
+use crate::{
+    Datum, Combiner, Text,
+    parser::{OperatorBindings, DatumAllocator},
+    combiner::{Operate, Apply},
+};
+
+
+/// An [`OperatorBindings`] that associates operator names, given as `str`s,
+/// with generic `Combiner`s, using a character trie.
+///
+/// Only operators that are `Datum::Text`s can be bound by this.  Looking-up an
+/// operator walks the trie by streaming the `char`s of its text, without
+/// constructing any temporary strings or `Datum`s, and stops as soon as no
+/// binding can match.  This is unlike [`HashMapOperatorBindings`], which must
+/// hash the entire operator `Datum` and whose keys must be constructed as
+/// `Datum`s.
+///
+/// You are responsible for inserting the desired bindings, and they may be
+/// dynamically mutated inbetween parser invocations, if desired.
+///
+/// [`OperatorBindings`]: ../../kul_core/parser/trait.OperatorBindings.html
+/// [`HashMapOperatorBindings`]: struct.HashMapOperatorBindings.html
+#[derive(Debug)]
+pub struct TrieOperatorBindings<DA, OR, AR, CE> {
+    /// The nodes of the trie, where index 0 is the root, which always exists.
+    nodes: Vec<Node<OR, AR>>,
+    /// The number of bindings.
+    len: usize,
+    /// We want `Self` to be parameterized over these types because this better
+    /// fits with the `OperatorBindings` trait and the uses of it.
+    have_type_params: PhantomData<(*const DA, *const CE)>,
+}
+
+#[derive(Debug)]
+struct Node<OR, AR> {
+    combiner: Option<Combiner<OR, AR>>,
+    /// Edges to child nodes, by index, sorted by `char` for binary searching.
+    children: Vec<(char, usize)>,
+}
+
+impl<OR, AR> Node<OR, AR> {
+    #[inline]
+    fn new() -> Self {
+        Self { combiner: None, children: Vec::new() }
+    }
+
+    #[inline]
+    fn child(&self, c: char) -> Option<usize> {
+        self.children.binary_search_by_key(&c, |&(k, _)| k)
+                     .ok()
+                     .map(|i| self.children[i].1)
+    }
+}
+
+
+impl<DA, OR, AR, CE> TrieOperatorBindings<DA, OR, AR, CE> {
+    /// Make a new instance of `Self` without any bindings.
+    #[inline]
+    #[must_use]
+    pub fn new() -> Self {
+        Self {
+            nodes: vec![Node::new()],
+            len: 0,
+            have_type_params: PhantomData,
+        }
+    }
+
+    /// The number of bindings.
+    #[inline]
+    #[must_use]
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    /// Returns `true` if there are no bindings.
+    #[inline]
+    #[must_use]
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// Remove all bindings.
+    #[inline]
+    pub fn clear(&mut self) {
+        self.nodes.truncate(1);
+        self.nodes[0] = Node::new();
+        self.len = 0;
+    }
+
+    /// Bind the given operator name to the given `Combiner`, returning the
+    /// `Combiner` previously bound to it, if any.
+    pub fn insert<K>(&mut self, name: K, combiner: Combiner<OR, AR>)
+                     -> Option<Combiner<OR, AR>>
+        where K: AsRef<str>,
+    {
+        let mut cur = 0;
+        for c in name.as_ref().chars() {
+            cur = match self.nodes[cur].children.binary_search_by_key(&c, |&(k, _)| k) {
+                Ok(i) => self.nodes[cur].children[i].1,
+                Err(i) => {
+                    let new = self.nodes.len();
+                    self.nodes.push(Node::new());
+                    self.nodes[cur].children.insert(i, (c, new));
+                    new
+                }
+            };
+        }
+        let prev = self.nodes[cur].combiner.replace(combiner);
+        if prev.is_none() {
+            self.len += 1;
+        }
+        prev
+    }
+
+    /// Unbind the given operator name, returning the `Combiner` that was bound
+    /// to it, if any.
+    ///
+    /// The nodes of the trie are not reclaimed until `clear` is called, so that
+    /// removing is cheap and so that re-inserting the same name is too.
+    pub fn remove<K>(&mut self, name: K) -> Option<Combiner<OR, AR>>
+        where K: AsRef<str>,
+    {
+        let idx = self.find(name.as_ref().chars())?;
+        let prev = self.nodes[idx].combiner.take();
+        if prev.is_some() {
+            self.len -= 1;
+        }
+        prev
+    }
+
+    /// Get the `Combiner` bound to the given operator name, if any.
+    #[inline]
+    pub fn get<K>(&self, name: K) -> Option<&Combiner<OR, AR>>
+        where K: AsRef<str>,
+    {
+        self.lookup_chars(name.as_ref().chars())
+    }
+
+    fn find<I>(&self, chars: I) -> Option<usize>
+        where I: Iterator<Item = char>,
+    {
+        let mut cur = 0;
+        for c in chars {
+            cur = self.nodes[cur].child(c)?;
+        }
+        Some(cur)
+    }
+
+    #[inline]
+    fn lookup_chars<I>(&self, chars: I) -> Option<&Combiner<OR, AR>>
+        where I: Iterator<Item = char>,
+    {
+        self.find(chars).and_then(|idx| self.nodes[idx].combiner.as_ref())
+    }
+}
+
+
+/// Must implement this manually because deriving would place unwanted bounds on
+/// the type parameters.
+impl<DA, OR, AR, CE> Default for TrieOperatorBindings<DA, OR, AR, CE> {
+    #[inline]
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+
+impl<DA, OR, AR, CE, K> Extend<(K, Combiner<OR, AR>)>
+    for TrieOperatorBindings<DA, OR, AR, CE>
+    where K: AsRef<str>,
+{
+    fn extend<I>(&mut self, iter: I)
+        where I: IntoIterator<Item = (K, Combiner<OR, AR>)>,
+    {
+        for (name, combiner) in iter {
+            let _ = self.insert(name, combiner);
+        }
+    }
+}
+
+
+impl<DA, OR, AR, CE, K> std::iter::FromIterator<(K, Combiner<OR, AR>)>
+    for TrieOperatorBindings<DA, OR, AR, CE>
+    where K: AsRef<str>,
+{
+    fn from_iter<I>(iter: I) -> Self
+        where I: IntoIterator<Item = (K, Combiner<OR, AR>)>,
+    {
+        let mut trie = Self::new();
+        trie.extend(iter);
+        trie
+    }
+}
+
+
+impl<DA, OR, AR, CE, UC>
+    OperatorBindings<DA, UC>
+    for TrieOperatorBindings<DA, OR, AR, CE>
+    where DA: DatumAllocator,
+          OR: Operate<DA, CE, UC>,
+          AR: Apply<DA, CE, UC>,
+{
+    type OR = OR;
+    type AR = AR;
+    type CE = CE;
+
+    #[inline]
+    fn lookup(&self, operator: &Datum<DA::TT, DA::ET, DA::DR>)
+              -> Option<&Combiner<OR, AR>>
+    {
+        if let Datum::Text(text) = operator {
+            self.lookup_chars(text.chars())
+        } else {
+            None
+        }
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        combiner::{OpFn, ApFn},
+        text::{TextVec, chunk::PosStr},
+        parser::{BoxDatumAllocator, DefaultCharClassifier},
+        datum::DatumBox,
+        Parser,
+    };
+
+    type TT = TextVec<PosStr<'static>>;
+    type DA = BoxDatumAllocator<TT, ()>;
+    type Bindings = TrieOperatorBindings<DA, Box<OpFn<DA, ()>>, Box<ApFn<DA, ()>>, ()>;
+
+    fn text(s: &'static str) -> Datum<TT, (), DatumBox<TT, ()>> {
+        Datum::Text(TT::from_str(s))
+    }
+
+    fn is_operative<OR, AR>(c: &Combiner<OR, AR>) -> bool {
+        matches!(c, Combiner::Operative(_))
+    }
+
+    #[test]
+    fn basic() {
+        let mut tob = Bindings::new();
+        assert!(tob.is_empty());
+        assert_eq!(tob.lookup(&text("")).map(|_| true), None);
+
+        assert!(tob.insert("foo", Combiner::Operative(Box::new(|_, _, _| unreachable!())))
+                   .is_none());
+        assert!(tob.insert(String::from("fo"),
+                           Combiner::Applicative(Box::new(|_, _, _| unreachable!())))
+                   .is_none());
+        assert!(tob.insert("λ", Combiner::Applicative(Box::new(|_, _, _| unreachable!())))
+                   .is_none());
+        assert_eq!(tob.len(), 3);
+
+        assert_eq!(tob.lookup(&text("foo")).map(is_operative), Some(true));
+        assert_eq!(tob.lookup(&text("fo")).map(is_operative), Some(false));
+        assert_eq!(tob.lookup(&text("λ")).map(is_operative), Some(false));
+        assert_eq!(tob.lookup(&text("f")).map(|_| true), None);
+        assert_eq!(tob.lookup(&text("fooo")).map(|_| true), None);
+        assert_eq!(tob.lookup(&text("")).map(|_| true), None);
+        assert_eq!(tob.lookup(&Datum::EmptyNest).map(|_| true), None);
+        assert_eq!(tob.get("foo").map(is_operative), Some(true));
+
+        assert!(tob.insert("foo", Combiner::Applicative(Box::new(|_, _, _| unreachable!())))
+                   .map(|c| is_operative(&c))
+                   .unwrap());
+        assert_eq!(tob.len(), 3);
+        assert_eq!(tob.lookup(&text("foo")).map(is_operative), Some(false));
+
+        assert!(tob.remove("fo").is_some());
+        assert!(tob.remove("fo").is_none());
+        assert!(tob.remove("nope").is_none());
+        assert_eq!(tob.len(), 2);
+        assert_eq!(tob.lookup(&text("fo")).map(|_| true), None);
+        assert_eq!(tob.lookup(&text("foo")).map(|_| true), Some(true));
+
+        tob.clear();
+        assert!(tob.is_empty());
+        assert_eq!(tob.lookup(&text("foo")).map(|_| true), None);
+    }
+
+    #[test]
+    fn parse() {
+        let e: Box<OpFn<DA, ()>> = Box::new(|_, _, _| Ok(None));
+        let n: Box<ApFn<DA, ()>> = Box::new(|_, operands, _| {
+            let mut count = 0;
+            let mut cur = &operands;
+            while let Datum::List { next, .. } = cur {
+                count += 1;
+                cur = next;
+            }
+            Ok(Some(text(["0", "1", "2", "3"][count])))
+        });
+        let bindings: Bindings = vec![("e", Combiner::Operative(e)),
+                                 ("n", Combiner::Applicative(n))]
+                            .into_iter().collect();
+
+        let mut parser = Parser {
+            classifier: DefaultCharClassifier,
+            allocator: DA::default(),
+            bindings,
+        };
+        let input = "{e x}{n a{b}c}{ee}";
+        let results: Vec<_> = parser.parse(TT::from_str(input).iter())
+                                    .collect::<Result<_, _>>()
+                                    .unwrap();
+        assert_eq!(results.len(), 2);
+        assert_eq!(results[0], text("3"));
+        assert!(matches!(&results[1], Datum::Combination { .. }));
+    }
+}
@@ -0,0 +1,296 @@
+//! Extracting the readable plain text of ASTs of Kul used as a markup language,
+//! e.g. for search indexing and previews.
+//!
+//! See [`Extractor`](struct.Extractor.html).
+
+use std::{collections::HashMap, fmt};
+
+use crate::{Datum, Text, DerefTryMut, source_map::CharOffset};
+
+
+/// What to extract for a form.
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub enum Action {
+    /// Extract the text of the operands, and drop the operator.
+    Operands,
+    /// Extract nothing, e.g. for comments.
+    Drop,
+    /// Extract the given text instead, e.g. a newline for `{br}`.
+    Replace(String),
+}
+
+/// The type of custom handlers, which are given a form's operator and operands
+/// and decide what to extract for it.
+pub type Handler<TT, ET, DR> = dyn Fn(&Datum<TT, ET, DR>, &Datum<TT, ET, DR>) -> Action;
+
+/// How the forms with an operator are extracted.
+pub enum Policy<TT, ET, DR>
+    where DR: DerefTryMut<Target = Datum<TT, ET, DR>>,
+{
+    /// Always do the same.
+    Fixed(Action),
+    /// Call the handler to decide.
+    Custom(Box<Handler<TT, ET, DR>>),
+}
+
+/// Must implement this manually because deriving would place unwanted bounds
+/// on the type parameters, and because the handlers are not `Debug`.
+impl<TT, ET, DR> fmt::Debug for Policy<TT, ET, DR>
+    where DR: DerefTryMut<Target = Datum<TT, ET, DR>>,
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Policy::Fixed(action) => f.debug_tuple("Fixed").field(action).finish(),
+            Policy::Custom(_) => f.write_str("Custom(..)"),
+        }
+    }
+}
+
+
+/// A run of extracted text, which is contiguous in its source if it has an
+/// `offset`.
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct Run {
+    /// The text.
+    pub text: String,
+    /// The `char` offset, relative to the source, of the start of the text, or
+    /// `None` if the text is not from the source, e.g. from `Action::Replace`.
+    pub offset: Option<usize>,
+}
+
+
+/// Extracts the plain text of ASTs, as [`Run`]s that track where in the source
+/// the text is from.
+///
+/// `Datum::Text`s are extracted as they are.  A `Datum::Combination` whose
+/// operator is a `Datum::Text` that has a policy in `policies` is extracted as
+/// that decides, and other `Datum::Combination`s are extracted as the `default`
+/// action decides, which is initially `Action::Operands`.  `Datum::List`s are
+/// extracted as their elements in order.  `Datum::EmptyNest`s,
+/// `Datum::EmptyList`s, and `Datum::Extra`s are extracted as nothing.
+///
+/// Extraction is iterative, and so very deep or long ASTs don't overflow the
+/// stack.
+///
+/// [`Run`]: struct.Run.html
+pub struct Extractor<TT, ET, DR>
+    where DR: DerefTryMut<Target = Datum<TT, ET, DR>>,
+{
+    /// The policies for operator texts.  You may change this as you want.
+    pub policies: HashMap<String, Policy<TT, ET, DR>>,
+    /// The action for the forms whose operators don't have policies.
+    pub default: Action,
+}
+
+/// Must implement this manually because deriving would place unwanted bounds
+/// on the type parameters.
+impl<TT, ET, DR> fmt::Debug for Extractor<TT, ET, DR>
+    where DR: DerefTryMut<Target = Datum<TT, ET, DR>>,
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Extractor")
+         .field("policies", &self.policies)
+         .field("default", &self.default)
+         .finish()
+    }
+}
+
+impl<TT, ET, DR> Default for Extractor<TT, ET, DR>
+    where DR: DerefTryMut<Target = Datum<TT, ET, DR>>,
+{
+    #[inline]
+    fn default() -> Self {
+        Self { policies: HashMap::new(), default: Action::Operands }
+    }
+}
+
+/// The pending steps of extracting.
+enum Work<'d, TT, ET, DR>
+    where DR: DerefTryMut<Target = Datum<TT, ET, DR>>,
+{
+    Form(&'d Datum<TT, ET, DR>),
+    Replace(String),
+}
+
+impl<TT, ET, DR> Extractor<TT, ET, DR>
+    where TT: Text,
+          TT::Pos: CharOffset,
+          DR: DerefTryMut<Target = Datum<TT, ET, DR>>,
+{
+    /// Make a new instance of `Self` without any policies.
+    #[inline]
+    #[must_use]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Drop the forms with the given operator, and return `self` for chaining.
+    #[inline]
+    #[must_use]
+    pub fn drop<O>(self, operator: O) -> Self
+        where O: Into<String>,
+    {
+        self.policy(operator, Policy::Fixed(Action::Drop))
+    }
+
+    /// Replace the forms with the given operator by the given text, and return
+    /// `self` for chaining.
+    #[inline]
+    #[must_use]
+    pub fn replace<O, R>(self, operator: O, text: R) -> Self
+        where O: Into<String>,
+              R: Into<String>,
+    {
+        self.policy(operator, Policy::Fixed(Action::Replace(text.into())))
+    }
+
+    /// Decide by the given handler for the forms with the given operator, and
+    /// return `self` for chaining.
+    #[inline]
+    #[must_use]
+    pub fn custom<O, F>(self, operator: O, handler: F) -> Self
+        where O: Into<String>,
+              F: Fn(&Datum<TT, ET, DR>, &Datum<TT, ET, DR>) -> Action + 'static,
+    {
+        self.policy(operator, Policy::Custom(Box::new(handler)))
+    }
+
+    /// Set the policy for the forms with the given operator, replacing any
+    /// previous one, and return `self` for chaining.
+    #[inline]
+    #[must_use]
+    pub fn policy<O>(mut self, operator: O, policy: Policy<TT, ET, DR>) -> Self
+        where O: Into<String>,
+    {
+        let _ = self.policies.insert(operator.into(), policy);
+        self
+    }
+
+    /// Extract the text of the given forms, in order, as runs.  Each run is as
+    /// long as possible, i.e. adjacent runs are not contiguous in the source.
+    pub fn runs(&self, forms: &[Datum<TT, ET, DR>]) -> Vec<Run> {
+        let mut runs = Vec::new();
+        // The `char` length of the last run, if it has an offset.
+        let mut last_len = 0;
+        let mut stack: Vec<Work<'_, TT, ET, DR>> = forms.iter().rev().map(Work::Form).collect();
+        while let Some(work) = stack.pop() {
+            match work {
+                Work::Form(Datum::Text(text)) => {
+                    for item in text.iter() {
+                        let offset = item.pos.char_offset();
+                        match runs.last_mut() {
+                            Some(Run { text, offset: Some(start) })
+                                if *start + last_len == offset =>
+                            {
+                                text.push(item.ch);
+                                last_len += 1;
+                            }
+                            _ => {
+                                runs.push(Run { text: item.ch.to_string(),
+                                                offset: Some(offset) });
+                                last_len = 1;
+                            }
+                        }
+                    }
+                }
+                Work::Form(Datum::Combination { operator, operands }) => {
+                    let policy = match &**operator {
+                        Datum::Text(t) => self.policies.get(&t.chars().collect::<String>()),
+                        _ => None,
+                    };
+                    let action = match policy {
+                        Some(Policy::Fixed(action)) => action.clone(),
+                        Some(Policy::Custom(handler)) => handler(operator, operands),
+                        None => self.default.clone(),
+                    };
+                    match action {
+                        Action::Operands => stack.push(Work::Form(operands)),
+                        Action::Drop => (),
+                        Action::Replace(text) => stack.push(Work::Replace(text)),
+                    }
+                }
+                Work::Form(Datum::List { elem, next }) => {
+                    stack.push(Work::Form(next));
+                    stack.push(Work::Form(elem));
+                }
+                Work::Form(Datum::EmptyNest | Datum::EmptyList | Datum::Extra(_)) => (),
+                Work::Replace(text) => match runs.last_mut() {
+                    Some(Run { text: last, offset: None }) => last.push_str(&text),
+                    _ => runs.push(Run { text, offset: None }),
+                },
+            }
+        }
+        runs
+    }
+
+    /// Extract the text of the given forms, in order, as a single string.
+    #[inline]
+    pub fn text(&self, forms: &[Datum<TT, ET, DR>]) -> String {
+        self.runs(forms).into_iter().map(|run| run.text).collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::common::document::{Document, Datum as DocDatum, Text as DocText};
+    use crate::datum::DatumBox;
+
+    type Ex = Extractor<DocText, (), DatumBox<DocText, ()>>;
+
+    fn forms(input: &str) -> Vec<DocDatum> {
+        Document::<()>::parse(input).unwrap().into_forms()
+    }
+
+    fn run(text: &str, offset: Option<usize>) -> Run {
+        Run { text: String::from(text), offset }
+    }
+
+    #[test]
+    fn default() {
+        let forms = forms(r"a {b c {d e}}f\{g");
+        assert_eq!(Ex::new().runs(&forms),
+                   [run("a ", Some(0)), run("c ", Some(5)), run("e", Some(10)),
+                    run("f", Some(13)), run("{g", Some(15))]);
+        assert_eq!(Ex::new().text(&forms), "a c ef{g");
+    }
+
+    #[test]
+    fn policies() {
+        let ex = Ex::new()
+            .drop("#")
+            .replace("br", "\n")
+            .replace("hr", "--")
+            .custom("abbr", |_, operands| match operands {
+                Datum::List { elem, .. } => match &**elem {
+                    Datum::Text(t) => Action::Replace(t.chars().collect::<String>()
+                                                       .to_uppercase()),
+                    _ => Action::Drop,
+                },
+                _ => Action::Operands,
+            });
+        let forms = forms("x{# hidden {b}}y{br}{hr}{abbr kul} {abbr}{{b} z}");
+        assert_eq!(ex.runs(&forms),
+                   [run("x", Some(0)), run("y", Some(15)), run("\n--KUL", None),
+                    run(" ", Some(34)), run("z", Some(46))]);
+
+        let mut ex = ex;
+        ex.default = Action::Drop;
+        assert_eq!(ex.text(&forms), "xy\n--KUL ");
+    }
+
+    #[test]
+    fn deep() {
+        let depth = 100_000;
+        let text = |s| -> DocDatum { Datum::Text(DocText::from_str(s)) };
+        let mut form = text("x");
+        for _ in 0 .. depth {
+            form = Datum::Combination {
+                operator: DatumBox::new(text("b")),
+                operands: DatumBox::new(Datum::List { elem: DatumBox::new(form),
+                                                      next: DatumBox::new(Datum::EmptyList) }),
+            };
+        }
+        assert_eq!(Ex::new().text(std::slice::from_ref(&form)), "x");
+    }
+}
@@ -0,0 +1,618 @@
+//! Schemas, themselves written in Kul, that describe which operators a Kul
+//! document may use, where each may appear, and how many and what kinds of
+//! operands each takes, and a validator that checks ASTs against them.
+//!
+//! A schema is a sequence of forms like:
+//!
+//! ```text
+//! {operator section {in root} {arity 1 *} {operands {text [A-Z]*} form}}
+//! {operator bold {in section p} {arity 1}}
+//! {operator link {arity 2} {operands {text http*} text}}
+//! {allow-unknown}
+//! ```
+//!
+//! Each `operator` form names an operator, and may have these clauses:
+//!
+//! - `{in NAME ...}` limits where the operator's forms may appear, to directly
+//!   in the operands of the forms of the named operators.  The name `root`
+//!   means the top level of a document.  Without this, they may appear
+//!   anywhere.
+//! - `{arity MIN MAX}` limits the number of operands, not counting texts that
+//!   are only whitespace, e.g. between nested forms.  `MAX` may be `*` for no
+//!   maximum, and may be omitted to mean the same as `MIN`.  Without this, any
+//!   number is allowed.
+//! - `{operands KIND ...}` gives the kind of each operand, in order, and the
+//!   last kind applies to the remaining operands.  Texts that are only
+//!   whitespace are skipped, as for `arity`.  Without this, any kinds are
+//!   allowed.
+//!
+//! The kinds are:
+//!
+//! - `text` for any text, or `{text PATTERN}` for a text that matches the
+//!   pattern, in which `*` matches any sequence of `char`s, `?` matches any
+//!   single `char`, and `[...]` matches any one of the `char`s, or ranges like
+//!   `a-z`, in it.
+//! - `form` for any nested form, or `{form NAME ...}` for a nested form of one
+//!   of the named operators.
+//! - `list` for a `Datum::List`, e.g. as produced by combiners.
+//! - `any` for anything.
+//!
+//! Forms whose operators are not in the schema are violations, unless the
+//! schema has an `{allow-unknown}` form.
+//!
+//! See [`Schema`](struct.Schema.html).
+
+use std::{collections::HashMap, mem};
+
+use crate::{
+    Datum, Text, DerefTryMut,
+    source_map::CharOffset,
+    datum_util::{word_of, operator_of, list_elems, pos_of},
+    common::document::{Document, Datum as DocDatum, Error as DocError},
+};
+
+
+/// The name, in `in` clauses, of the top level of a document.
+pub const ROOT: &str = "root";
+
+/// What a schema requires of the forms of one operator.
+#[derive(Clone, PartialEq, Eq, Default, Debug)]
+pub struct Rule {
+    /// The operators whose operands the forms may directly appear in, where
+    /// [`ROOT`](constant.ROOT.html) means the top level, or `None` for
+    /// anywhere.
+    pub parents: Option<Vec<String>>,
+    /// The minimum number of operands.
+    pub min: usize,
+    /// The maximum number of operands, or `None` for no maximum.
+    pub max: Option<usize>,
+    /// The kind of each operand, in order, where the last applies to the
+    /// remaining operands.  Empty means any kinds.
+    pub operands: Vec<Kind>,
+}
+
+/// A kind of operand.
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub enum Kind {
+    /// Anything.
+    Any,
+    /// A `Datum::Text`, that matches the pattern if there is one.
+    Text(Option<String>),
+    /// A `Datum::Combination` or `Datum::EmptyNest`, whose operator is one of
+    /// the given names if there are some.
+    Form(Vec<String>),
+    /// A `Datum::List` or `Datum::EmptyList`.
+    List,
+}
+
+/// A schema, which is a set of operators with the [`Rule`]s of each.
+///
+/// [`Rule`]: struct.Rule.html
+#[derive(Clone, PartialEq, Eq, Default, Debug)]
+pub struct Schema {
+    /// The rules of each operator.  You may change this as you want.
+    pub operators: HashMap<String, Rule>,
+    /// Whether forms of operators that are not in `operators` are allowed.
+    pub allow_unknown: bool,
+}
+
+
+/// The ways a schema, as Kul text, can be invalid.
+#[derive(Debug)]
+pub enum SchemaError {
+    /// The text failed to parse.
+    Parse(DocError),
+    /// A form of the schema is invalid, as described by the message.  The
+    /// position is the `char` offset of the form, if known.
+    Invalid {
+        /// What is invalid.
+        message: String,
+        /// Where it is.
+        pos: Option<usize>,
+    },
+}
+
+impl From<DocError> for SchemaError {
+    fn from(e: DocError) -> Self {
+        SchemaError::Parse(e)
+    }
+}
+
+
+/// One step of the path to a violating `Datum`, from the top level.
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct Step {
+    /// The index of the `Datum` among the top-level forms, or among the
+    /// operands or list elements that it is in, counting blank texts too, or
+    /// `None` if it is the operator of the form that it is in.
+    pub index: Option<usize>,
+    /// The operator of the `Datum`, if it is a form whose operator is a text.
+    pub operator: Option<String>,
+}
+
+/// A way an AST violates a schema.
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub enum ViolationKind {
+    /// A form's operator is not in the schema.
+    UnknownOperator,
+    /// A form's operator is not a text, and so can't be in the schema.
+    InvalidOperator,
+    /// A form appears where its operator is not allowed.  Has the operator of
+    /// the form it is in, or `None` if at the top level or if that operator is
+    /// not a text.
+    Misplaced(Option<String>),
+    /// A form has too few or too many operands.  Has the number found.
+    Arity(usize),
+    /// An operand is not of the kind expected.
+    WrongKind(Kind),
+}
+
+/// A violation of a schema by a `Datum` of an AST.
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct Violation {
+    /// The path to the violating `Datum`.
+    pub path: Vec<Step>,
+    /// The `char` offset of the start of the violating `Datum`'s first text,
+    /// if it has any.
+    pub pos: Option<usize>,
+    /// How it violates.
+    pub kind: ViolationKind,
+}
+
+
+impl Schema {
+    /// Make a new empty instance of `Self`, which allows nothing but text.
+    #[inline]
+    #[must_use]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Parse a schema from its Kul text, as described in the [module
+    /// documentation](index.html).
+    ///
+    /// # Errors
+    ///
+    /// If the text fails to parse or has invalid forms, returns an error
+    /// describing the first problem.
+    pub fn parse(source: &str) -> Result<Self, SchemaError> {
+        let doc = Document::<()>::parse(source)?;
+        let mut schema = Self::new();
+        for form in doc.forms() {
+            match form {
+                _ if is_blank(form) => (),
+                Datum::Combination { operator, operands } => {
+                    match &*word_of(operator).unwrap_or_default() {
+                        "operator" => {
+                            let (name, rule) = parse_rule(form, operands)?;
+                            let _ = schema.operators.insert(name, rule);
+                        }
+                        "allow-unknown" => schema.allow_unknown = true,
+                        _ => return Err(invalid("unknown schema form", form)),
+                    }
+                }
+                _ => return Err(invalid("expected schema form", form)),
+            }
+        }
+        Ok(schema)
+    }
+
+    /// Check the given top-level forms against `self`, and return every
+    /// violation, in depth-first order.  Validation is iterative, and so very
+    /// deep ASTs don't overflow the stack.
+    pub fn validate<TT, ET, DR>(&self, forms: &[Datum<TT, ET, DR>]) -> Vec<Violation>
+        where TT: Text,
+              TT::Pos: CharOffset,
+              DR: DerefTryMut<Target = Datum<TT, ET, DR>>,
+    {
+        // Steps are kept as a tree of parent indexes, so that each `Datum`
+        // costs one step, and whole paths are only made for violations.
+        let mut steps: Vec<(Option<usize>, Step)> = Vec::new();
+        let mut violations = Vec::new();
+        // Each pending `Datum` has the index of the step of the form whose
+        // operands it is in, if any, and the index of its own step.
+        let mut stack = Vec::new();
+        for (index, form) in forms.iter().enumerate().rev() {
+            steps.push((None, Step { index: Some(index), operator: operator_of(form) }));
+            stack.push((form, None, steps.len() - 1));
+        }
+        while let Some((datum, parent, step)) = stack.pop() {
+            let (rule, inner, elems) = match datum {
+                Datum::Combination { operator, operands } => {
+                    let named = steps[step].1.operator.is_some();
+                    let rule = steps[step].1.operator.as_ref()
+                                                     .and_then(|n| self.operators.get(n));
+                    if rule.is_none() && !self.allow_unknown {
+                        let kind = if named { ViolationKind::UnknownOperator }
+                                   else { ViolationKind::InvalidOperator };
+                        violations.push(violation(&steps, step, datum, kind));
+                    }
+                    if !named {
+                        stack.push((operator, parent, child(&mut steps, step, None, operator)));
+                    }
+                    (rule, Some(step), list_elems(operands))
+                }
+                // The elements of a list are in the same form as the list.
+                Datum::List { .. } => (None, parent, list_elems(datum)),
+                _ => continue,
+            };
+            if let Some(rule) = rule {
+                if let Some(parents) = &rule.parents {
+                    let parent_name = parent.map(|p| steps[p].1.operator.as_deref());
+                    let allowed = match parent_name {
+                        None => parents.iter().any(|p| p == ROOT),
+                        Some(name) => name.is_some_and(|n| parents.iter().any(|p| p == n)),
+                    };
+                    if !allowed {
+                        let parent_name = parent_name.flatten().map(String::from);
+                        let kind = ViolationKind::Misplaced(parent_name);
+                        violations.push(violation(&steps, step, datum, kind));
+                    }
+                }
+                let found = elems.iter().filter(|elem| !is_blank(elem)).count();
+                if found < rule.min || rule.max.is_some_and(|max| found > max) {
+                    violations.push(violation(&steps, step, datum, ViolationKind::Arity(found)));
+                }
+            }
+            let base = stack.len();
+            let mut operand = 0;
+            for (index, elem) in elems.into_iter().enumerate() {
+                let kind = if is_blank(elem) { None } else {
+                    operand += 1;
+                    rule.and_then(|r| r.operands.get(operand - 1).or_else(|| r.operands.last()))
+                };
+                if let Some(kind) = kind {
+                    if !kind.matches(elem) {
+                        let elem_step = child(&mut steps, step, Some(index), elem);
+                        let kind = ViolationKind::WrongKind(kind.clone());
+                        violations.push(violation(&steps, elem_step, elem, kind));
+                        stack.push((elem, inner, elem_step));
+                        continue;
+                    }
+                }
+                stack.push((elem, inner, child(&mut steps, step, Some(index), elem)));
+            }
+            stack[base ..].reverse();
+        }
+        violations
+    }
+}
+
+impl Kind {
+    /// Returns `true` if the given `Datum` is of `self`.
+    pub fn matches<TT, ET, DR>(&self, datum: &Datum<TT, ET, DR>) -> bool
+        where TT: Text,
+              DR: DerefTryMut<Target = Datum<TT, ET, DR>>,
+    {
+        match (self, datum) {
+            (Kind::Any, _)
+            | (Kind::Text(None), Datum::Text(_))
+            | (Kind::List, Datum::List { .. } | Datum::EmptyList) => true,
+            (Kind::Text(Some(pattern)), Datum::Text(text)) => {
+                glob_match(pattern, &text.chars().collect::<Vec<_>>())
+            }
+            (Kind::Form(names), Datum::Combination { operator, .. }) => {
+                names.is_empty()
+                || word_of(operator).is_some_and(|w| names.contains(&w))
+            }
+            (Kind::Form(names), Datum::EmptyNest) => names.is_empty(),
+            _ => false,
+        }
+    }
+}
+
+
+/// Returns `true` if the whole of `text` matches the pattern, where `*` matches
+/// any sequence, `?` any single `char`, and `[...]` any one of its `char`s or
+/// ranges.
+fn glob_match(pattern: &str, text: &[char]) -> bool {
+    let pattern: Vec<char> = pattern.chars().collect();
+    // The positions to resume at if a later mismatch happens after a `*`.
+    let (mut p, mut t) = (0, 0);
+    let mut resume: Option<(usize, usize)> = None;
+    while t < text.len() {
+        let advance = match pattern.get(p) {
+            Some('*') => {
+                resume = Some((p, t));
+                p += 1;
+                continue;
+            }
+            Some('?') => Some(1),
+            Some('[') => class_match(&pattern[p + 1 ..], text[t]),
+            Some(&c) => if c == text[t] { Some(1) } else { None },
+            None => None,
+        };
+        match (advance, resume) {
+            (Some(len), _) => {
+                p += len;
+                t += 1;
+            }
+            (None, Some((rp, rt))) => {
+                resume = Some((rp, rt + 1));
+                p = rp + 1;
+                t = rt + 1;
+            }
+            (None, None) => return false,
+        }
+    }
+    pattern[p ..].iter().all(|&c| c == '*')
+}
+
+/// Match a `char` against a `[...]` class whose contents start the given
+/// pattern slice, and return the pattern length of the class, including its
+/// `[`, if it matches.  An unterminated class matches only a literal `[`.
+fn class_match(class: &[char], c: char) -> Option<usize> {
+    let Some(end) = class.iter().position(|&x| x == ']') else {
+        return if c == '[' { Some(1) } else { None };
+    };
+    let members = &class[.. end];
+    let mut i = 0;
+    let mut found = false;
+    while i < members.len() {
+        if i + 2 < members.len() && members[i + 1] == '-' {
+            found |= (members[i] ..= members[i + 2]).contains(&c);
+            i += 3;
+        } else {
+            found |= members[i] == c;
+            i += 1;
+        }
+    }
+    if found { Some(end + 2) } else { None }
+}
+
+/// Returns `true` if the given `Datum` is a text of only whitespace.
+fn is_blank<TT, ET, DR>(datum: &Datum<TT, ET, DR>) -> bool
+    where TT: Text,
+          DR: DerefTryMut<Target = Datum<TT, ET, DR>>,
+{
+    matches!(datum, Datum::Text(text) if text.chars().all(char::is_whitespace))
+}
+
+/// Add a step for a child `Datum`, and return its index.
+fn child<TT, ET, DR>(steps: &mut Vec<(Option<usize>, Step)>,
+                     parent: usize,
+                     index: Option<usize>,
+                     datum: &Datum<TT, ET, DR>)
+                     -> usize
+    where TT: Text,
+          DR: DerefTryMut<Target = Datum<TT, ET, DR>>,
+{
+    steps.push((Some(parent), Step { index, operator: operator_of(datum) }));
+    steps.len() - 1
+}
+
+fn violation<TT, ET, DR>(steps: &[(Option<usize>, Step)],
+                         step: usize,
+                         datum: &Datum<TT, ET, DR>,
+                         kind: ViolationKind)
+                         -> Violation
+    where TT: Text,
+          TT::Pos: CharOffset,
+          DR: DerefTryMut<Target = Datum<TT, ET, DR>>,
+{
+    Violation { path: path(steps, step), pos: pos_of(datum), kind }
+}
+
+/// The whole path of steps that ends at the given step.
+fn path(steps: &[(Option<usize>, Step)], mut step: usize) -> Vec<Step> {
+    let mut path = vec![steps[step].1.clone()];
+    while let Some(parent) = steps[step].0 {
+        path.push(steps[parent].1.clone());
+        step = parent;
+    }
+    path.reverse();
+    path
+}
+
+fn invalid(message: &str, datum: &DocDatum) -> SchemaError {
+    SchemaError::Invalid { message: String::from(message), pos: pos_of(datum) }
+}
+
+/// A word, or a nested form, of the operands of a schema form.
+enum Item<'d> {
+    Word(String),
+    Form(&'d DocDatum),
+}
+
+/// Split the texts of the given operands into whitespace-separated words.
+fn items(operands: &DocDatum) -> Vec<Item<'_>> {
+    let mut items = Vec::new();
+    for elem in list_elems(operands) {
+        match elem {
+            Datum::Text(text) => {
+                let mut word = String::new();
+                for c in text.chars().chain(Some(' ')) {
+                    if c.is_whitespace() {
+                        if !word.is_empty() {
+                            items.push(Item::Word(mem::take(&mut word)));
+                        }
+                    } else {
+                        word.push(c);
+                    }
+                }
+            }
+            _ => items.push(Item::Form(elem)),
+        }
+    }
+    items
+}
+
+fn parse_rule(form: &DocDatum, operands: &DocDatum) -> Result<(String, Rule), SchemaError> {
+    let mut clauses = items(operands).into_iter();
+    let Some(Item::Word(name)) = clauses.next() else {
+        return Err(invalid("expected operator name", form));
+    };
+    let mut rule = Rule::default();
+    for item in clauses {
+        let Item::Form(clause @ Datum::Combination { operator, operands }) = item else {
+            return Err(invalid("expected operator clause", form));
+        };
+        let words = || items_words(operands).ok_or_else(|| invalid("expected words", clause));
+        match &*word_of(operator).unwrap_or_default() {
+            "in" => rule.parents = Some(words()?),
+            "arity" => {
+                let words = words()?;
+                let number = |w: &String| w.parse::<usize>()
+                                           .map_err(|_| invalid("invalid arity", clause));
+                match words.as_slice() {
+                    [min] => {
+                        rule.min = number(min)?;
+                        rule.max = Some(rule.min);
+                    }
+                    [min, max] => {
+                        rule.min = number(min)?;
+                        rule.max = if max == "*" { None } else { Some(number(max)?) };
+                    }
+                    _ => return Err(invalid("invalid arity", clause)),
+                }
+            }
+            "operands" => {
+                rule.operands = items(operands).into_iter().map(|item| parse_kind(item, clause))
+                                               .collect::<Result<_, _>>()?;
+            }
+            _ => return Err(invalid("unknown operator clause", clause)),
+        }
+    }
+    Ok((name, rule))
+}
+
+fn parse_kind(item: Item<'_>, clause: &DocDatum) -> Result<Kind, SchemaError> {
+    match item {
+        Item::Word(word) => match &*word {
+            "any" => Ok(Kind::Any),
+            "text" => Ok(Kind::Text(None)),
+            "form" => Ok(Kind::Form(Vec::new())),
+            "list" => Ok(Kind::List),
+            _ => Err(invalid("unknown kind", clause)),
+        },
+        Item::Form(form @ Datum::Combination { operator, operands }) => {
+            let words = items_words(operands).ok_or_else(|| invalid("expected words", form))?;
+            match (&*word_of(operator).unwrap_or_default(), words.as_slice()) {
+                ("text", [pattern]) => Ok(Kind::Text(Some(pattern.clone()))),
+                ("form", _) => Ok(Kind::Form(words)),
+                _ => Err(invalid("invalid kind", form)),
+            }
+        }
+        Item::Form(form) => Err(invalid("invalid kind", form)),
+    }
+}
+
+/// The words of the given operands, or `None` if they have nested forms.
+fn items_words(operands: &DocDatum) -> Option<Vec<String>> {
+    items(operands).into_iter().map(|item| match item {
+        Item::Word(word) => Some(word),
+        Item::Form(_) => None,
+    }).collect()
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::datum::DatumBox;
+    use crate::common::document::Text as DocText;
+
+    const SCHEMA: &str = r"
+        {operator section {in root} {arity 1 *} {operands {text [A-Z]*} form}}
+        {operator b {in section p}}
+        {operator p {in section} {operands any}}
+        {operator link {arity 2} {operands {text http*://?*} form}}
+        {operator br {arity 0}}
+    ";
+
+    /// The kind, position, and path indexes of a violation.
+    type Found<'v> = (&'v ViolationKind, Option<usize>, Vec<Option<usize>>);
+
+    fn kinds(v: &[Violation]) -> Vec<Found<'_>> {
+        v.iter().map(|v| (&v.kind, v.pos, v.path.iter().map(|s| s.index).collect())).collect()
+    }
+
+    #[test]
+    fn parse() {
+        let schema = Schema::parse(SCHEMA).unwrap();
+        assert_eq!(schema.operators.len(), 5);
+        assert!(!schema.allow_unknown);
+        assert_eq!(schema.operators["section"],
+                   Rule { parents: Some(vec![String::from("root")]),
+                          min: 1,
+                          max: None,
+                          operands: vec![Kind::Text(Some(String::from("[A-Z]*"))),
+                                         Kind::Form(vec![])] });
+        assert_eq!(schema.operators["br"], Rule { min: 0, max: Some(0), ..Rule::default() });
+        assert!(Schema::parse("{allow-unknown}").unwrap().allow_unknown);
+
+        let invalid = |s| match Schema::parse(s) {
+            Err(SchemaError::Invalid { pos, .. }) => pos,
+            r => panic!("{:?}", r),
+        };
+        assert_eq!(invalid("{op x}"), Some(1));
+        assert_eq!(invalid("  {operator x {arity a}}"), Some(15));
+        assert_eq!(invalid("{operator x {operands thing}}"), Some(13));
+        assert_eq!(invalid("stray"), Some(0));
+        assert!(matches!(Schema::parse("{"), Err(SchemaError::Parse(_))));
+    }
+
+    #[test]
+    fn validate() {
+        let schema = Schema::parse(SCHEMA).unwrap();
+        let doc = Document::<()>::parse(
+            "{section Intro{p x {b y}}} {b z} {section lower{br}{q}}{link ftp://a {b}}{{b}}{br x}"
+        ).unwrap();
+        let violations = schema.validate(doc.forms());
+        assert_eq!(kinds(&violations), [
+            (&ViolationKind::Misplaced(None), Some(28), vec![Some(2)]),
+            (&ViolationKind::WrongKind(Kind::Text(Some(String::from("[A-Z]*")))),
+             Some(42), vec![Some(4), Some(0)]),
+            (&ViolationKind::UnknownOperator, Some(52), vec![Some(4), Some(2)]),
+            (&ViolationKind::WrongKind(Kind::Text(Some(String::from("http*://?*")))),
+             Some(61), vec![Some(5), Some(0)]),
+            (&ViolationKind::Misplaced(Some(String::from("link"))), Some(70), vec![Some(5), Some(1)]),
+            (&ViolationKind::InvalidOperator, Some(75), vec![Some(6)]),
+            (&ViolationKind::Misplaced(None), Some(75), vec![Some(6), None]),
+            (&ViolationKind::Arity(1), Some(79), vec![Some(7)]),
+        ]);
+        assert_eq!(violations[2].path[0].operator.as_deref(), Some("section"));
+        assert_eq!(violations[2].path[1].operator.as_deref(), Some("q"));
+
+        let mut lenient = schema;
+        lenient.allow_unknown = true;
+        assert_eq!(lenient.validate(doc.forms()).len(), 6);
+    }
+
+    #[test]
+    fn blank_operands() {
+        let schema = Schema::parse("{operator row {arity 2} {operands {form cell}}}
+                                    {operator cell {arity 1} {operands text}}").unwrap();
+        let doc = Document::<()>::parse("{row {cell a} {cell b}}{row {cell c}\n x}").unwrap();
+        assert_eq!(kinds(&schema.validate(doc.forms())), [
+            (&ViolationKind::WrongKind(Kind::Form(vec![String::from("cell")])),
+             Some(36), vec![Some(1), Some(1)]),
+        ]);
+    }
+
+    #[test]
+    fn glob() {
+        let m = |p: &str, t: &str| glob_match(p, &t.chars().collect::<Vec<_>>());
+        assert!(m("", "") && m("*", "") && m("a*c", "abbc") && m("*.kul", "x.y.kul"));
+        assert!(m("h?llo", "hello") && m("[a-c]x[!]", "bx!") && m("[", "["));
+        assert!(!m("a*c", "abcd") && !m("?", "") && !m("[a-c]", "d") && !m("ab", "a"));
+    }
+
+    #[test]
+    fn deep() {
+        let schema = Schema::parse("{operator b {operands form text}}").unwrap();
+        let text = |s| -> DocDatum { Datum::Text(DocText::from_str(s)) };
+        let mut form = text("x");
+        for _ in 0 .. 100_000 {
+            form = Datum::Combination {
+                operator: DatumBox::new(text("b")),
+                operands: DatumBox::new(Datum::List { elem: DatumBox::new(form),
+                                                      next: DatumBox::new(Datum::EmptyList) }),
+            };
+        }
+        let violations = schema.validate(std::slice::from_ref(&form));
+        assert_eq!(violations.len(), 1);
+        assert_eq!(violations[0].path.len(), 100_001);
+        assert_eq!(violations[0].kind, ViolationKind::WrongKind(Kind::Form(vec![])));
+    }
+}
@@ -0,0 +1,470 @@
+//! A registry of multiple source files, and positions that know which file they
+//! are in, for reporting unambiguous locations when ASTs are merged from
+//! several inputs.
+//!
+//! The premade position types, [`CharPos`], [`StrPos`], and [`CharsPos`], are
+//! only relative to their own source, and so a [`SourceMap`] assigns a
+//! [`FileId`] to each source and a [`FilePos`] combines that with the `char`
+//! offset that any of the premade chunk types, or their positions, can produce
+//! via the [`CharOffset`] trait.  A `FilePos` can then be looked-up as a [`Location`]
+//! of the file's name, the line and column, and the line's text.
+//!
+//! So that the parser itself produces `FilePos`s, a [`FileChunk`] wraps any of
+//! those chunk types with the `FileId` of its file, and a `Text` of it, e.g. a
+//! `TextVec<FileChunk<_>>`, is given to the parser as its source, as done by
+//! the [`Includer`].
+//!
+//! [`CharPos`]: ../text/chunk/struct.CharPos.html
+//! [`StrPos`]: ../text/chunk/struct.StrPos.html
+//! [`CharsPos`]: ../text/chunk/struct.CharsPos.html
+//! [`SourceMap`]: struct.SourceMap.html
+//! [`FileId`]: struct.FileId.html
+//! [`FilePos`]: struct.FilePos.html
+//! [`CharOffset`]: trait.CharOffset.html
+//! [`Location`]: struct.Location.html
+//! [`FileChunk`]: struct.FileChunk.html
+//! [`Includer`]: ../common/include/struct.Includer.html
+
+use std::{fmt, rc::Rc};
+
+use crate::{
+    SourcePosition, SourceIterItem, TextBase, TextChunk,
+    text::chunk::{CharPos, StrPos, CharsPos, PosStr, PosChars, PosStrish, RefCntStrish,
+                  SourceStream as ChunkSourceStream},
+    common::document::Document,
+};
+
+
+/// Identifies a file registered in a [`SourceMap`](struct.SourceMap.html).
+#[derive(Copy, Clone, PartialEq, Eq, Hash, PartialOrd, Ord, Debug)]
+pub struct FileId(pub usize);
+
+/// A `SourcePosition` type for characters or text chunks as the `char` offset
+/// relative to the start of a file.
+#[derive(Copy, Clone, PartialEq, Eq, Hash, PartialOrd, Ord, Debug)]
+pub struct FilePos {
+    /// The file that the position is in.
+    pub file: FileId,
+    /// The `char` offset relative to the start of the file.
+    pub offset: usize,
+}
+
+impl FilePos {
+    /// Given a file and anything that knows its `char` offset relative to the
+    /// start of that file, make a new position.
+    #[inline]
+    pub fn new<P>(file: FileId, pos: &P) -> Self
+        where P: CharOffset + ?Sized,
+    {
+        Self { file, offset: pos.char_offset() }
+    }
+}
+
+impl SourcePosition for FilePos {
+    #[inline]
+    fn empty() -> Self {
+        Self { file: FileId(0), offset: 0 }
+    }
+}
+
+
+/// Positions, and text chunks, that know the `char` offset of their start
+/// relative to their original source.
+pub trait CharOffset {
+    /// The `char` offset relative to the original source.
+    fn char_offset(&self) -> usize;
+}
+
+impl CharOffset for CharPos {
+    #[inline]
+    fn char_offset(&self) -> usize { self.0 }
+}
+
+impl CharOffset for StrPos<'_> {
+    #[inline]
+    fn char_offset(&self) -> usize { self.char_pos }
+}
+
+impl CharOffset for CharsPos<'_> {
+    #[inline]
+    fn char_offset(&self) -> usize { self.char_pos }
+}
+
+impl CharOffset for FilePos {
+    #[inline]
+    fn char_offset(&self) -> usize { self.offset }
+}
+
+impl CharOffset for PosStr<'_> {
+    #[inline]
+    fn char_offset(&self) -> usize { self.pos.char_offset() }
+}
+
+impl CharOffset for PosChars<'_> {
+    #[inline]
+    fn char_offset(&self) -> usize { self.pos.char_offset() }
+}
+
+impl<S> CharOffset for PosStrish<S>
+    where S: RefCntStrish,
+{
+    #[inline]
+    fn char_offset(&self) -> usize { self.pos.char_offset() }
+}
+
+
+impl<C> CharOffset for FileChunk<C>
+    where C: CharOffset,
+{
+    #[inline]
+    fn char_offset(&self) -> usize { self.chunk.char_offset() }
+}
+
+
+/// A [`TextChunk`] that is a chunk of another type, `C`, with the `FileId` of
+/// the file that it's from, and so whose positions are [`FilePos`]s.
+///
+/// The positions of the `C` chunk must be relative to the start of the file.
+/// E.g. a `Text` of a whole file is a single `FileChunk` of a `PosStrish` of
+/// the file's contents at `CharPos(0)`, and the `Text`s that are parsed from it
+/// have `FileChunk`s of the same file.
+///
+/// [`TextChunk`]: ../text/trait.TextChunk.html
+/// [`FilePos`]: struct.FilePos.html
+#[derive(Clone, Debug)]
+pub struct FileChunk<C> {
+    /// The chunk.
+    pub chunk: C,
+    /// The file that the chunk is from.
+    pub file: FileId,
+}
+
+impl<C> FileChunk<C> {
+    /// Make a new chunk of the given chunk from the given file.
+    #[inline]
+    pub fn new(chunk: C, file: FileId) -> Self {
+        Self { chunk, file }
+    }
+}
+
+/// The file is the same as that of `FilePos::empty`.
+impl<'s, C> From<&'s str> for FileChunk<C>
+    where C: From<&'s str>,
+{
+    #[inline]
+    fn from(val: &'s str) -> Self {
+        Self::new(C::from(val), FilePos::empty().file)
+    }
+}
+
+impl<C> TextBase for FileChunk<C>
+    where C: TextChunk,
+          C::Pos: CharOffset,
+{
+    type Pos = FilePos;
+
+    #[inline]
+    fn empty() -> Self { Self::new(C::empty(), FilePos::empty().file) }
+
+    #[inline]
+    fn is_empty(&self) -> bool { self.chunk.is_empty() }
+}
+
+impl<C> TextChunk for FileChunk<C>
+    where C: TextChunk,
+          C::Pos: CharOffset,
+{
+    type CharsSrcStrm = FileChunkIter<C>;
+
+    #[inline]
+    fn src_strm(&self) -> Self::CharsSrcStrm {
+        FileChunkIter { inner: self.chunk.src_strm(), file: self.file, peeked: None }
+    }
+
+    #[inline]
+    fn as_chunk_str(&self) -> Option<&str> {
+        self.chunk.as_chunk_str()
+    }
+}
+
+/// A [`chunk::SourceStream`] (and `Iterator`) of the characters, and their
+/// positions, of a [`FileChunk`](struct.FileChunk.html).
+///
+/// [`chunk::SourceStream`]: ../text/chunk/trait.SourceStream.html
+pub struct FileChunkIter<C>
+    where C: TextChunk,
+{
+    inner: C::CharsSrcStrm,
+    file: FileId,
+    peeked: Option<SourceIterItem<FilePos>>,
+}
+
+/// Manually implemented because deriving it doesn't work.
+impl<C> fmt::Debug for FileChunkIter<C>
+    where C: TextChunk,
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("FileChunkIter")
+         .field("file", &self.file)
+         .field("peeked", &self.peeked)
+         .finish_non_exhaustive()
+    }
+}
+
+impl<C> FileChunkIter<C>
+    where C: TextChunk,
+          C::Pos: CharOffset,
+{
+    #[inline]
+    fn in_file(&self, SourceIterItem{ch, pos}: &SourceIterItem<C::Pos>)
+               -> SourceIterItem<FilePos>
+    {
+        SourceIterItem{ch: *ch, pos: FilePos::new(self.file, pos)}
+    }
+}
+
+/// Required by `chunk::SourceStream`.
+impl<C> Iterator for FileChunkIter<C>
+    where C: TextChunk,
+          C::Pos: CharOffset,
+{
+    type Item = SourceIterItem<FilePos>;
+
+    #[inline]
+    fn next(&mut self) -> Option<Self::Item> {
+        self.inner.next().map(|it| self.in_file(&it))
+    }
+}
+
+/// Accumulates by the `C` chunk's stream, so that accumulating is as efficient
+/// as it is for that.
+impl<C> ChunkSourceStream<FileChunk<C>> for FileChunkIter<C>
+    where C: TextChunk,
+          C::Pos: CharOffset,
+{
+    fn peek(&mut self) -> Option<&<Self as Iterator>::Item> {
+        let file = self.file;
+        self.peeked = self.inner.peek().map(|SourceIterItem{ch, pos}|
+                                                SourceIterItem{ch: *ch,
+                                                               pos: FilePos::new(file, pos)});
+        self.peeked.as_ref()
+    }
+
+    #[inline]
+    fn next_accum(&mut self) -> Option<<Self as Iterator>::Item> {
+        self.inner.next_accum().map(|it| self.in_file(&it))
+    }
+
+    #[inline]
+    fn accum_done(&mut self) -> FileChunk<C> {
+        FileChunk::new(self.inner.accum_done(), self.file)
+    }
+}
+
+
+/// The result of looking-up a [`FilePos`](struct.FilePos.html) in a
+/// [`SourceMap`](struct.SourceMap.html).
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+pub struct Location<'m> {
+    /// The file that the position is in.
+    pub file: FileId,
+    /// The name of the file.
+    pub name: &'m str,
+    /// The line number, starting at 1.
+    pub line: usize,
+    /// The column, as the number of `char`s into the line, starting at 1.
+    pub column: usize,
+    /// The text of the line, without its line terminator.
+    pub snippet: &'m str,
+}
+
+
+/// A registry of source files, that assigns each a [`FileId`] and keeps its
+/// name and contents, for looking-up positions in them.
+///
+/// The contents are kept as `Rc<str>`s, and so they may be shared with, e.g.,
+/// the [`Document`]s parsed from them.
+///
+/// [`FileId`]: struct.FileId.html
+/// [`Document`]: ../common/document/struct.Document.html
+#[derive(Default, Debug)]
+pub struct SourceMap {
+    files: Vec<File>,
+}
+
+#[derive(Debug)]
+struct File {
+    name: String,
+    contents: Rc<str>,
+    /// The `char` and byte offsets of the start of each line.
+    lines: Vec<(usize, usize)>,
+}
+
+impl File {
+    fn new(name: String, contents: Rc<str>) -> Self {
+        let mut lines = vec![(0, 0)];
+        let mut chars = 0;
+        for (byte, c) in contents.char_indices() {
+            chars += 1;
+            if c == '\n' {
+                lines.push((chars, byte + 1));
+            }
+        }
+        lines.push((chars + 1, contents.len()));
+        Self { name, contents, lines }
+    }
+}
+
+impl SourceMap {
+    /// Make a new empty instance of `Self`.
+    #[inline]
+    #[must_use]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Register a file with the given name and contents, and return its new
+    /// `FileId`.  Registering the same name again makes a distinct file.
+    pub fn add<N, C>(&mut self, name: N, contents: C) -> FileId
+        where N: Into<String>,
+              C: Into<Rc<str>>,
+    {
+        self.files.push(File::new(name.into(), contents.into()));
+        FileId(self.files.len() - 1)
+    }
+
+    /// Register the source of the given document, sharing it, with the given
+    /// name, and return its new `FileId`.
+    #[inline]
+    pub fn add_document<N, Extra>(&mut self, name: N, document: &Document<Extra>) -> FileId
+        where N: Into<String>,
+    {
+        self.add(name, Rc::clone(document.source_rc()))
+    }
+
+    /// The number of files registered.
+    #[inline]
+    #[must_use]
+    pub fn len(&self) -> usize {
+        self.files.len()
+    }
+
+    /// Whether no files are registered.
+    #[inline]
+    #[must_use]
+    pub fn is_empty(&self) -> bool {
+        self.files.is_empty()
+    }
+
+    /// The name of the given file, or `None` if it's not registered.
+    #[inline]
+    #[must_use]
+    pub fn name(&self, file: FileId) -> Option<&str> {
+        self.files.get(file.0).map(|f| &*f.name)
+    }
+
+    /// The contents of the given file, or `None` if it's not registered.
+    #[inline]
+    #[must_use]
+    pub fn contents(&self, file: FileId) -> Option<&Rc<str>> {
+        self.files.get(file.0).map(|f| &f.contents)
+    }
+
+    /// The first file registered with the given name, if any.
+    #[inline]
+    pub fn find(&self, name: &str) -> Option<FileId> {
+        self.files.iter().position(|f| f.name == name).map(FileId)
+    }
+
+    /// Look-up the line, column, and line text of the given position.  The
+    /// position just past the end of the contents is allowed, e.g. for errors
+    /// at the end.
+    ///
+    /// Returns `None` if the file is not registered or if the position is past
+    /// the end of the file.
+    #[must_use]
+    pub fn lookup(&self, pos: FilePos) -> Option<Location<'_>> {
+        let file = self.files.get(pos.file.0)?;
+        // The last entry is a sentinel one past the end.
+        let (&(end, _), lines) = file.lines.split_last()?;
+        if pos.offset >= end {
+            return None;
+        }
+        let index = match lines.binary_search_by_key(&pos.offset, |&(c, _)| c) {
+            Ok(i) => i,
+            Err(i) => i - 1,
+        };
+        let (line_char, line_byte) = lines[index];
+        let line_end = lines.get(index + 1).map_or(file.contents.len(), |&(_, b)| b);
+        let snippet = file.contents[line_byte .. line_end].trim_end_matches(['\n', '\r']);
+        Some(Location {
+            file: pos.file,
+            name: &file.name,
+            line: index + 1,
+            column: pos.offset - line_char + 1,
+            snippet,
+        })
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Datum, Text, text::TextVec};
+
+    #[test]
+    fn lookup() {
+        let mut map = SourceMap::new();
+        assert!(map.is_empty());
+        let a = map.add("a.kul", "one\r\ntwo ü\n\nfour");
+        let b = map.add("b.kul", String::new());
+        assert_eq!((map.len(), map.name(b), map.find("a.kul")), (2, Some("b.kul"), Some(a)));
+        assert_eq!(map.find("c.kul"), None);
+
+        let at = |offset| map.lookup(FilePos { file: a, offset })
+                             .map(|l| (l.line, l.column, l.snippet));
+        assert_eq!(at(0), Some((1, 1, "one")));
+        assert_eq!(at(4), Some((1, 5, "one")));
+        assert_eq!(at(5), Some((2, 1, "two ü")));
+        assert_eq!(at(10), Some((2, 6, "two ü")));
+        assert_eq!(at(11), Some((3, 1, "")));
+        assert_eq!(at(15), Some((4, 4, "four")));
+        assert_eq!(at(16), Some((4, 5, "four")));
+        assert_eq!(at(17), None);
+
+        let loc = map.lookup(FilePos::new(b, &CharPos(0))).unwrap();
+        assert_eq!((loc.name, loc.line, loc.column, loc.snippet), ("b.kul", 1, 1, ""));
+        assert_eq!(map.lookup(FilePos::new(FileId(2), &CharPos(0))), None);
+    }
+
+    #[test]
+    fn chunks() {
+        let mut map = SourceMap::new();
+        let doc: Document = "x\n{y z}".parse().unwrap();
+        let file = map.add_document("doc", &doc);
+        assert!(Rc::ptr_eq(map.contents(file).unwrap(), doc.source_rc()));
+
+        // Text in a combination's operands, from a `PosStrish` chunk.
+        let operands = match &doc.forms()[1] {
+            Datum::Combination { operands, .. } => match &**operands {
+                Datum::List { elem, .. } => match &**elem {
+                    Datum::Text(t) => t.iter_chunks().next().unwrap().clone(),
+                    _ => unreachable!(),
+                },
+                _ => unreachable!(),
+            },
+            _ => unreachable!(),
+        };
+        let loc = map.lookup(FilePos::new(file, &operands)).unwrap();
+        assert_eq!((loc.line, loc.column, loc.snippet), (2, 4, "{y z}"));
+
+        // A `PosStr` chunk of a text parsed in-memory.
+        let input = "ab\ncd";
+        let file = map.add("mem", input);
+        let text = TextVec::<PosStr<'_>>::from_str(input);
+        let chunk = text.iter_chunks().next().unwrap();
+        assert_eq!(map.lookup(FilePos::new(file, chunk)).map(|l| l.line), Some(1));
+        assert_eq!(FilePos::new(file, &chunk.pos), FilePos { file, offset: 0 });
+    }
+}
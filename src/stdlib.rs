@@ -0,0 +1,575 @@
+//! A premade "standard library" of combiners for common forms, so that
+//! applications can share one dialect of them instead of each defining slightly
+//! different ones.
+//!
+//! This is opt-in: nothing is bound unless you insert these combiners into your
+//! bindings, e.g. by [`insert_into`] for a [`HashMapOperatorBindings`], or by
+//! collecting [`combiners`] into a [`TrieOperatorBindings`], or by binding only
+//! some of the functions of this module as you choose.  The `Datum`s they
+//! produce use our [`Extra`] type, and they fail with our [`CombinerError`]
+//! type.
+//!
+//! The forms and their bound names are:
+//!
+//! * `{# ...}` is a comment and is removed.
+//!
+//! * `{int -42}`, `{float 8.7}`, `{bool true}`, and `{str ...}` convert their
+//!   operands text, trimmed except for `str`, into an [`Extra`] scalar.
+//!
+//! * `{list 9, "blah", {map ...}}` makes a `Datum::List` of its
+//!   comma-separated items, or a `Datum::EmptyList` if there are none.
+//!
+//! * `{map foo: 8.7, bar: asdf}` makes a `Datum::Combination` whose operator is
+//!   `Extra::Map` and whose operands are a list of the comma-separated
+//!   entries, each a two-element `Datum::List` of an `Extra::Str` key and its value.
+//!
+//! An item of a `list` or a value of a `map` entry is either a single nested
+//! form, possibly surrounded by whitespace, which is kept as it is, or text
+//! that is converted to a scalar: `"quoted"` text is an `Extra::Str` without
+//! the quotes, `true` and `false` are `Extra::Bool`s, integers are
+//! `Extra::Int`s, numbers that are not integers are `Extra::Float`s, and any
+//! other text is an `Extra::Str` of it trimmed.  Commas and colons inside
+//! quotes don't separate, and quotes end at nested forms.  An escaped comma,
+//! `\,`, doesn't separate either, except right after the operator or a nested
+//! form, where the parser leaves no trace of the escape and so the comma must
+//! be quoted instead.  A trailing comma is allowed.
+//!
+//! A [`CombinerError`] has the position of the invalid operands text or item.
+//!
+//! [`insert_into`]: fn.insert_into.html
+//! [`combiners`]: fn.combiners.html
+//! [`Extra`]: enum.Extra.html
+//! [`CombinerError`]: struct.CombinerError.html
+//! [`HashMapOperatorBindings`]: ../parser/struct.HashMapOperatorBindings.html
+//! [`TrieOperatorBindings`]: ../parser/struct.TrieOperatorBindings.html
+
+#![allow(clippy::type_complexity)]
+
+use std::{
+    hash::{Hash, Hasher, BuildHasher},
+    mem,
+};
+
+use crate::{
+    Datum, Text, TextBase, TextChunk, DerefTryMut, SourcePosition, SourceIterItem,
+    parser::{DatumAllocator, HashMapOperatorBindings},
+    combiner::{self, Combiner, OpFn, ApFn},
+    source_map::CharOffset,
+};
+
+
+/// The "extra" type of the `Datum`s produced by our combiners.
+#[derive(Clone, Debug)]
+pub enum Extra {
+    /// From `{int ...}` or an integer item.
+    Int(i128),
+    /// From `{float ...}` or a non-integer numeric item.
+    Float(f64),
+    /// From `{bool ...}` or a `true` or `false` item.
+    Bool(bool),
+    /// From `{str ...}`, a quoted item, any other textual item, or a `map` key.
+    Str(String),
+    /// The operator of the `Datum::Combination` produced by `{map ...}`.
+    Map,
+}
+
+/// `Float`s are compared by their bits, so that this is a proper `Eq`, as
+/// needed by `HashMapOperatorBindings`.
+impl PartialEq for Extra {
+    fn eq(&self, other: &Self) -> bool {
+        match (self, other) {
+            (Extra::Int(a), Extra::Int(b)) => a == b,
+            (Extra::Float(a), Extra::Float(b)) => a.to_bits() == b.to_bits(),
+            (Extra::Bool(a), Extra::Bool(b)) => a == b,
+            (Extra::Str(a), Extra::Str(b)) => a == b,
+            (Extra::Map, Extra::Map) => true,
+            _ => false,
+        }
+    }
+}
+
+impl Eq for Extra {}
+
+impl Hash for Extra {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        mem::discriminant(self).hash(state);
+        match self {
+            Extra::Int(i) => i.hash(state),
+            Extra::Float(f) => f.to_bits().hash(state),
+            Extra::Bool(b) => b.hash(state),
+            Extra::Str(s) => s.hash(state),
+            Extra::Map => (),
+        }
+    }
+}
+
+
+/// The errors that our combiners might return, as the
+/// [`Error::FailedCombiner`] variant.
+///
+/// [`Error::FailedCombiner`]: ../enum.Error.html#variant.FailedCombiner
+#[derive(Clone, PartialEq, Eq, Hash, Debug)]
+pub struct CombinerError<Pos> {
+    /// The position of the invalid operand.  For the operatives, this is the
+    /// start of their operands text.  For the applicatives, this is the start
+    /// of the invalid item, i.e. the comma before it or, for the first item,
+    /// its first character, else the comma after it.  It is empty if there is
+    /// no text to give one.
+    pub pos: Pos,
+    /// What is invalid.
+    pub kind: CombinerErrorKind,
+}
+
+/// The kinds of [`CombinerError`](struct.CombinerError.html).
+#[derive(Clone, PartialEq, Eq, Hash, Debug)]
+pub enum CombinerErrorKind {
+    /// The operands text of `{int ...}` is not an `i128`.
+    InvalidInt(String),
+    /// The operands text of `{float ...}` is not an `f64`.
+    InvalidFloat(String),
+    /// The operands text of `{bool ...}` is not `true` or `false`.
+    InvalidBool(String),
+    /// A quoted item has no closing quote or has text after it.
+    InvalidString(String),
+    /// An item, other than a trailing one, is empty.
+    EmptyItem,
+    /// An item has more than a single nested form, or has text besides one.
+    MixedItem,
+    /// A `map` entry has no `key:` before its value.
+    MissingKey,
+}
+
+
+type DADatum<DA> = Datum<<DA as DatumAllocator>::TT,
+                         <DA as DatumAllocator>::ET,
+                         <DA as DatumAllocator>::DR>;
+
+type DAPos<DA> = <<DA as DatumAllocator>::TT as TextBase>::Pos;
+
+type Error<DA> = crate::Error<DAPos<DA>, CombinerError<DAPos<DA>>>;
+
+/// The type of `Combiner`s returned by [`combiners`](fn.combiners.html).
+pub type BoxCombiner<DA> = Combiner<
+    Box<OpFn<DA, CombinerError<<<DA as DatumAllocator>::TT as TextBase>::Pos>>>,
+    Box<ApFn<DA, CombinerError<<<DA as DatumAllocator>::TT as TextBase>::Pos>>>>;
+
+
+/// All of our combiners, paired with their names, boxed so that they can be
+/// collected into bindings.
+#[must_use]
+pub fn combiners<DA>() -> Vec<(&'static str, BoxCombiner<DA>)>
+    where DA: DatumAllocator<ET = Extra> + 'static,
+          DAPos<DA>: CharOffset,
+{
+    vec![
+        ("#", Combiner::Operative(Box::new(comment::<DA>))),
+        ("int", Combiner::Operative(Box::new(int::<DA>))),
+        ("float", Combiner::Operative(Box::new(float::<DA>))),
+        ("bool", Combiner::Operative(Box::new(boolean::<DA>))),
+        ("str", Combiner::Operative(Box::new(string::<DA>))),
+        ("list", Combiner::Applicative(Box::new(list::<DA>))),
+        ("map", Combiner::Applicative(Box::new(map::<DA>))),
+    ]
+}
+
+/// Bind all of our [`combiners`](fn.combiners.html) in the given bindings,
+/// keyed by `Datum::Text`s of their names.  Any existing bindings of the same
+/// names are replaced.
+pub fn insert_into<DA, S>(
+    bindings: &mut HashMapOperatorBindings<DA, Box<OpFn<DA, CombinerError<DAPos<DA>>>>,
+                                           Box<ApFn<DA, CombinerError<DAPos<DA>>>>,
+                                           CombinerError<DAPos<DA>>, S>
+)
+    where DA: DatumAllocator<ET = Extra> + 'static,
+          DAPos<DA>: CharOffset,
+          DA::TT: Hash + Eq,
+          DA::DR: Hash + Eq,
+          <DA::TT as Text>::Chunk: From<&'static str>,
+          S: BuildHasher,
+{
+    bindings.hashmap.extend(
+        combiners().into_iter().map(|(name, c)| (Datum::Text(DA::TT::from_str(name)), c)));
+}
+
+
+/// Operative for `{# ...}` comments.  Always removes the form.
+///
+/// # Errors
+///
+/// Never.
+#[inline]
+#[allow(clippy::needless_pass_by_value)]
+pub fn comment<DA>(_operator: DADatum<DA>, _operands: DA::TT, _: &mut DA)
+                   -> combiner::Result<DA, CombinerError<DAPos<DA>>>
+    where DA: DatumAllocator<ET = Extra>,
+{
+    Ok(None)
+}
+
+/// Operative for `{int ...}`.  Parses the trimmed operands text as an `i128`.
+///
+/// # Errors
+///
+/// If the text is not an integer, `CombinerErrorKind::InvalidInt` is returned.
+#[allow(clippy::needless_pass_by_value)]
+pub fn int<DA>(_operator: DADatum<DA>, operands: DA::TT, _: &mut DA)
+               -> combiner::Result<DA, CombinerError<DAPos<DA>>>
+    where DA: DatumAllocator<ET = Extra>,
+{
+    let s = operands.chars().collect::<String>();
+    match s.trim().parse() {
+        Ok(i) => Ok(Some(Datum::Extra(Extra::Int(i)))),
+        Err(_) => Err(failed::<DA>(text_pos(&operands), CombinerErrorKind::InvalidInt(s))),
+    }
+}
+
+/// Operative for `{float ...}`.  Parses the trimmed operands text as an `f64`.
+///
+/// # Errors
+///
+/// If the text is not a number, `CombinerErrorKind::InvalidFloat` is returned.
+#[allow(clippy::needless_pass_by_value)]
+pub fn float<DA>(_operator: DADatum<DA>, operands: DA::TT, _: &mut DA)
+                 -> combiner::Result<DA, CombinerError<DAPos<DA>>>
+    where DA: DatumAllocator<ET = Extra>,
+{
+    let s = operands.chars().collect::<String>();
+    match s.trim().parse() {
+        Ok(f) => Ok(Some(Datum::Extra(Extra::Float(f)))),
+        Err(_) => Err(failed::<DA>(text_pos(&operands), CombinerErrorKind::InvalidFloat(s))),
+    }
+}
+
+/// Operative for `{bool ...}`.  The trimmed operands text must be `true` or
+/// `false`.
+///
+/// # Errors
+///
+/// If the text is neither, `CombinerErrorKind::InvalidBool` is returned.
+#[allow(clippy::needless_pass_by_value)]
+pub fn boolean<DA>(_operator: DADatum<DA>, operands: DA::TT, _: &mut DA)
+                   -> combiner::Result<DA, CombinerError<DAPos<DA>>>
+    where DA: DatumAllocator<ET = Extra>,
+{
+    let s = operands.chars().collect::<String>();
+    match s.trim().parse() {
+        Ok(b) => Ok(Some(Datum::Extra(Extra::Bool(b)))),
+        Err(_) => Err(failed::<DA>(text_pos(&operands), CombinerErrorKind::InvalidBool(s))),
+    }
+}
+
+/// Operative for `{str ...}`.  The operands text, as it is, becomes an
+/// `Extra::Str`.
+///
+/// # Errors
+///
+/// Never.
+#[inline]
+#[allow(clippy::needless_pass_by_value)]
+pub fn string<DA>(_operator: DADatum<DA>, operands: DA::TT, _: &mut DA)
+                  -> combiner::Result<DA, CombinerError<DAPos<DA>>>
+    where DA: DatumAllocator<ET = Extra>,
+{
+    Ok(Some(Datum::Extra(Extra::Str(operands.chars().collect()))))
+}
+
+/// Applicative for `{list ...}`.  See the [module](index.html) documentation.
+///
+/// # Errors
+///
+/// If an item is invalid, the corresponding `CombinerError` is returned.  If
+/// allocating fails, or if the operands can't be taken, the corresponding
+/// `Error` is returned.
+pub fn list<DA>(_operator: DADatum<DA>, operands: DADatum<DA>, dalloc: &mut DA)
+                -> combiner::Result<DA, CombinerError<DAPos<DA>>>
+    where DA: DatumAllocator<ET = Extra>,
+          DAPos<DA>: CharOffset,
+{
+    let values = items::<DA>(operands)?.into_iter()
+                                       .map(value::<DA>)
+                                       .collect::<Result<Vec<_>, _>>()?;
+    Ok(Some(new_list(values, dalloc)?))
+}
+
+/// Applicative for `{map ...}`.  See the [module](index.html) documentation.
+///
+/// # Errors
+///
+/// If an entry is invalid, the corresponding `CombinerError` is returned.  If
+/// allocating fails, or if the operands can't be taken, the corresponding
+/// `Error` is returned.
+pub fn map<DA>(_operator: DADatum<DA>, operands: DADatum<DA>, dalloc: &mut DA)
+               -> combiner::Result<DA, CombinerError<DAPos<DA>>>
+    where DA: DatumAllocator<ET = Extra>,
+          DAPos<DA>: CharOffset,
+{
+    let mut entries = Vec::new();
+    for mut item in items::<DA>(operands)? {
+        let key = item.key().map_err(Error::<DA>::FailedCombiner)?;
+        let entry = vec![Datum::Extra(Extra::Str(key)), value::<DA>(item)?];
+        entries.push(new_list(entry, dalloc)?);
+    }
+    let operands = new_list(entries, dalloc)?;
+    Ok(Some(Datum::Combination { operator: dalloc.new_datum(Datum::Extra(Extra::Map))?,
+                                 operands: dalloc.new_datum(operands)? }))
+}
+
+
+/// A part of an item, which is either its (possibly partial) text or one of its
+/// nested forms, which are of type `F`.
+pub(crate) enum Piece<F> {
+    Text(String),
+    Form(F),
+}
+
+/// An item of the operands of a `list`, `map`, or similar form.
+pub(crate) struct Item<F, P> {
+    /// The pieces, in order, where adjacent text is joined.
+    pieces: Vec<Piece<F>>,
+    /// Where the item starts, if it has any text or is followed by a comma.
+    pos: Option<P>,
+}
+
+impl<F, P> Item<F, P>
+    where P: SourcePosition,
+{
+    fn new(pos: Option<P>) -> Self {
+        Self { pieces: Vec::new(), pos }
+    }
+
+    fn is_blank(&self) -> bool {
+        self.pieces.iter().all(|p| matches!(p, Piece::Text(s) if s.trim().is_empty()))
+    }
+
+    /// Where `self` starts, or an empty position if that is unknown.
+    pub(crate) fn pos(&self) -> P {
+        self.pos.clone().unwrap_or_else(P::empty)
+    }
+
+    /// An error of the given kind at the start of `self`.
+    pub(crate) fn error(&self, kind: CombinerErrorKind) -> CombinerError<P> {
+        CombinerError { pos: self.pos(), kind }
+    }
+
+    /// The joined text of `self` and its single nested form, if it has one.
+    ///
+    /// If there are several nested forms, or if there is a form and text that
+    /// is not blank, `CombinerErrorKind::MixedItem` is returned.
+    pub(crate) fn into_parts(self) -> Result<(String, Option<F>), CombinerError<P>> {
+        let mixed = self.error(CombinerErrorKind::MixedItem);
+        let mut text = String::new();
+        let mut form = None;
+        for piece in self.pieces {
+            match piece {
+                Piece::Text(s) => text.push_str(&s),
+                Piece::Form(f) => if form.replace(f).is_some() {
+                    return Err(mixed);
+                }
+            }
+        }
+        if form.is_some() && !text.trim().is_empty() {
+            return Err(mixed);
+        }
+        Ok((text, form))
+    }
+
+    /// Remove the `key:` at the start of `self`, as for a `map` entry, and
+    /// return the key, unquoted.
+    ///
+    /// If there is none, `CombinerErrorKind::MissingKey` is returned.
+    pub(crate) fn key(&mut self) -> Result<String, CombinerError<P>> {
+        if let Some(Piece::Text(s)) = self.pieces.first_mut() {
+            if let Some(i) = find_unquoted(s, ':') {
+                let key = unquote(s[.. i].trim()).to_owned();
+                if !key.is_empty() {
+                    s.replace_range(..= i, "");
+                    return Ok(key);
+                }
+            }
+        }
+        Err(self.error(CombinerErrorKind::MissingKey))
+    }
+}
+
+/// Splits the elements of an operands list, given in order, into the items
+/// separated by commas, if splitting, else into a single item.
+///
+/// Commas inside quotes don't separate, and quotes don't continue across nested
+/// forms.  An escaped comma, i.e. `\,`, doesn't separate either.  The parser
+/// drops escape characters, and so a comma whose position doesn't directly
+/// follow the previous character of its text is taken as escaped.  A comma
+/// escaped at the start of a text, i.e. right after the operator or a nested
+/// form, can't be told apart, and so it must be quoted instead.
+pub(crate) struct Items<F, P> {
+    list: Vec<Item<F, P>>,
+    quoted: bool,
+    split: bool,
+}
+
+impl<F, P> Items<F, P>
+    where P: SourcePosition,
+{
+    pub(crate) fn new(split: bool) -> Self {
+        Self { list: vec![Item::new(None)], quoted: false, split }
+    }
+
+    /// Add the characters of a text element.
+    pub(crate) fn text<TT>(&mut self, text: &TT)
+        where TT: Text + TextBase<Pos = P>,
+              P: CharOffset,
+    {
+        // The offset of the previous character, so that a character that
+        // doesn't directly follow it is known to follow a skipped escape.
+        let mut prev: Option<usize> = None;
+        for chunk in text.iter_chunks() {
+            for SourceIterItem{ch, pos} in chunk.src_strm() {
+                let offset = pos.char_offset();
+                let escaped = prev.is_some_and(|prev| offset != prev + 1);
+                prev = Some(offset);
+                if self.split && ch == ',' && !self.quoted && !escaped {
+                    let _ = self.list.last_mut().unwrap().pos.get_or_insert_with(|| pos.clone());
+                    self.list.push(Item::new(Some(pos)));
+                    continue;
+                }
+                if ch == '"' {
+                    self.quoted = !self.quoted;
+                }
+                let item = self.list.last_mut().unwrap();
+                let _ = item.pos.get_or_insert(pos);
+                if let Some(Piece::Text(s)) = item.pieces.last_mut() {
+                    s.push(ch);
+                } else {
+                    item.pieces.push(Piece::Text(ch.to_string()));
+                }
+            }
+        }
+    }
+
+    /// Add a nested form element.
+    pub(crate) fn form(&mut self, form: F) {
+        self.quoted = false;
+        self.list.last_mut().unwrap().pieces.push(Piece::Form(form));
+    }
+
+    /// The items, where a trailing blank item, after a trailing comma or of
+    /// empty operands, is dropped.
+    ///
+    /// If any other item is blank, `CombinerErrorKind::EmptyItem` is returned.
+    pub(crate) fn finish(mut self) -> Result<Vec<Item<F, P>>, CombinerError<P>> {
+        if self.list.last().is_some_and(Item::is_blank) {
+            let _ = self.list.pop();
+        }
+        if let Some(blank) = self.list.iter().find(|item| item.is_blank()) {
+            return Err(blank.error(CombinerErrorKind::EmptyItem));
+        }
+        Ok(self.list)
+    }
+
+    /// The single item, when not splitting.
+    pub(crate) fn single(mut self) -> Item<F, P> {
+        debug_assert!(!self.split);
+        self.list.pop().unwrap()
+    }
+}
+
+/// Take the `Datum` out of a reference to it, leaving an empty list.
+fn take<DA>(dr: &mut DA::DR) -> Result<DADatum<DA>, Error<DA>>
+    where DA: DatumAllocator,
+{
+    DerefTryMut::get_mut(dr).map(|d| mem::replace(d, Datum::EmptyList))
+                            .ok_or(Error::<DA>::FailedDerefTryMut)
+}
+
+fn failed<DA>(pos: DAPos<DA>, kind: CombinerErrorKind) -> Error<DA>
+    where DA: DatumAllocator,
+{
+    Error::<DA>::FailedCombiner(CombinerError { pos, kind })
+}
+
+/// The position of the first character of the given text, or an empty one if
+/// it has none.
+pub(crate) fn text_pos<TT>(text: &TT) -> TT::Pos
+    where TT: Text,
+{
+    text.iter().next().map_or_else(TT::Pos::empty, |SourceIterItem{pos, ..}| pos)
+}
+
+/// Split the operands list into the comma-separated items.
+fn items<DA>(operands: DADatum<DA>) -> Result<Vec<Item<DADatum<DA>, DAPos<DA>>>, Error<DA>>
+    where DA: DatumAllocator,
+          DAPos<DA>: CharOffset,
+{
+    let mut items = Items::new(true);
+    let mut rest = operands;
+    while let Datum::List { mut elem, mut next } = rest {
+        match take::<DA>(&mut elem)? {
+            Datum::Text(text) => items.text(&text),
+            form => items.form(form),
+        }
+        rest = take::<DA>(&mut next)?;
+    }
+    items.finish().map_err(Error::<DA>::FailedCombiner)
+}
+
+/// Convert an item to its value, which is either its single nested form or the
+/// scalar of its text.
+fn value<DA>(item: Item<DADatum<DA>, DAPos<DA>>) -> Result<DADatum<DA>, Error<DA>>
+    where DA: DatumAllocator<ET = Extra>,
+{
+    let pos = item.pos();
+    match item.into_parts().map_err(Error::<DA>::FailedCombiner)? {
+        (_, Some(form)) => Ok(form),
+        (text, None) => scalar(text.trim()).map(Datum::Extra)
+                                           .map_err(|kind| failed::<DA>(pos, kind)),
+    }
+}
+
+pub(crate) fn scalar(s: &str) -> Result<Extra, CombinerErrorKind> {
+    if s.starts_with('"') {
+        if s.len() >= 2 && s.ends_with('"') && !s[1 .. s.len() - 1].contains('"') {
+            Ok(Extra::Str(unquote(s).to_owned()))
+        } else {
+            Err(CombinerErrorKind::InvalidString(s.to_owned()))
+        }
+    } else if let Ok(b) = s.parse::<bool>() {
+        Ok(Extra::Bool(b))
+    } else if let Ok(i) = s.parse::<i128>() {
+        Ok(Extra::Int(i))
+    } else if let (true, Ok(f)) = (looks_numeric(s), s.parse()) {
+        Ok(Extra::Float(f))
+    } else {
+        Ok(Extra::Str(s.to_owned()))
+    }
+}
+
+/// Whether the text starts like a number, so that words like `inf` and `NaN`
+/// are not taken as `Float`s.
+fn looks_numeric(s: &str) -> bool {
+    let s = s.strip_prefix(['+', '-']).unwrap_or(s);
+    s.starts_with(|c: char| c.is_ascii_digit() || c == '.')
+}
+
+fn unquote(s: &str) -> &str {
+    s.strip_prefix('"').and_then(|s| s.strip_suffix('"')).unwrap_or(s)
+}
+
+fn find_unquoted(s: &str, target: char) -> Option<usize> {
+    let mut quoted = false;
+    s.char_indices().find(|&(_, c)| {
+        if c == '"' {
+            quoted = !quoted;
+        }
+        c == target && !quoted
+    }).map(|(i, _)| i)
+}
+
+fn new_list<DA>(elems: Vec<DADatum<DA>>, dalloc: &mut DA) -> Result<DADatum<DA>, Error<DA>>
+    where DA: DatumAllocator,
+{
+    let mut list = Datum::EmptyList;
+    for elem in elems.into_iter().rev() {
+        list = Datum::List { elem: dalloc.new_datum(elem)?,
+                             next: dalloc.new_datum(list)? };
+    }
+    Ok(list)
+}
@@ -0,0 +1,495 @@
+//! Templating forms, evaluated at parse time, for generating documents from an
+//! environment of variables.
+//!
+//! The forms are:
+//!
+//! * `{set name value}` defines, or redefines, the variable `name` as the text
+//!   `value`, after any forms in it are evaluated, and is removed.
+//!
+//! * `{$name}` is substituted by the text of the variable `name`.
+//!
+//! * `{if cond ...}` is substituted by its evaluated body if the variable
+//!   `cond` is true, and is removed otherwise.  `{if !cond ...}` negates.  A
+//!   variable is true if it is defined and is a non-empty text other than
+//!   `false` or is a non-empty list.
+//!
+//! * `{each item in list ...}` is substituted by its body evaluated once for
+//!   each element of the list variable `list`, with the variable `item` defined
+//!   as the element.
+//!
+//! The variables come from an [`Env`] given by Rust code, which `{set}` adds
+//! to.  The bodies of `{if}` and `{each}` are only evaluated when they are
+//! used, and their results are substituted as a single form, or as a
+//! `Datum::List` of their forms if there are several.  The bodies are parsed by
+//! a [`SubParser`], and because the escapes in the bodies are already excluded
+//! when the bodies are parsed, a delimiter that is to be text in a body must be
+//! escaped twice, e.g. `\\\{`, which is text `{` in the result.
+//!
+//! Substituted text is kept as it is in the resulting AST, and so when the
+//! result is printed as Kul, e.g. by [`to_kul`], the text must be escaped by
+//! [`escape`] so that any delimiter characters in it are not taken as forms.
+//!
+//! [`Env`]: struct.Env.html
+//! [`SubParser`]: ../struct.SubParser.html
+//! [`to_kul`]: ../datum_util/fn.to_kul.html
+//! [`escape`]: ../datum_util/fn.escape.html
+
+use std::{
+    collections::HashMap, fmt, iter::{once, FromIterator}, mem, rc::Rc,
+};
+
+use crate::{
+    Parser, SubParser, Datum as DatumEnum,
+    parser::{PatternOperatorBindings, Pattern, DatumAllocator as _},
+    combiner::{Combiner, OpCtxFn, ApCtxFn, WithContext, Context, Enclosing},
+    source_stream::StrishIterSourceStream,
+    text::{Text as TextTrait, chunk::CharPos},
+    datum_util::{word_of, list_elems},
+    common::document::{Text, Datum, DatumAllocator, Error, CharClassifier},
+};
+
+
+/// The value of a variable.
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub enum Value {
+    /// A text, which can be substituted.
+    Text(String),
+    /// A list, which can be iterated by `{each}`.
+    List(Vec<Value>),
+}
+
+impl From<&str> for Value {
+    #[inline]
+    fn from(s: &str) -> Self {
+        Value::Text(String::from(s))
+    }
+}
+
+impl From<String> for Value {
+    #[inline]
+    fn from(s: String) -> Self {
+        Value::Text(s)
+    }
+}
+
+impl<T> From<Vec<T>> for Value
+    where T: Into<Value>,
+{
+    #[inline]
+    fn from(v: Vec<T>) -> Self {
+        Value::List(v.into_iter().map(Into::into).collect())
+    }
+}
+
+
+/// The variables available to templates.
+#[derive(Clone, Default, PartialEq, Eq, Debug)]
+pub struct Env {
+    vars: HashMap<String, Value>,
+}
+
+impl Env {
+    /// Make a new instance of `Self` without any variables.
+    #[inline]
+    #[must_use]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Define, or redefine, the given variable, and return its previous value
+    /// if any.
+    #[inline]
+    pub fn set<N, V>(&mut self, name: N, value: V) -> Option<Value>
+        where N: Into<String>,
+              V: Into<Value>,
+    {
+        self.vars.insert(name.into(), value.into())
+    }
+
+    /// The value of the given variable, if it is defined.
+    #[inline]
+    #[must_use]
+    pub fn get(&self, name: &str) -> Option<&Value> {
+        self.vars.get(name)
+    }
+
+    /// Undefine the given variable, and return its value if it was defined.
+    #[inline]
+    pub fn remove(&mut self, name: &str) -> Option<Value> {
+        self.vars.remove(name)
+    }
+}
+
+impl<N, V> FromIterator<(N, V)> for Env
+    where N: Into<String>,
+          V: Into<Value>,
+{
+    fn from_iter<I>(iter: I) -> Self
+        where I: IntoIterator<Item = (N, V)>,
+    {
+        Self { vars: iter.into_iter().map(|(n, v)| (n.into(), v.into())).collect() }
+    }
+}
+
+
+/// The errors that our combiners might return, as the
+/// [`Error::FailedCombiner`] variant, with the position of the start of the
+/// form that failed.
+///
+/// [`Error::FailedCombiner`]: ../enum.Error.html#variant.FailedCombiner
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub enum TemplateError {
+    /// The variable is not defined.
+    Undefined {
+        /// The variable.
+        name: String,
+        /// The position of the form.
+        pos: CharPos,
+    },
+    /// The variable is a list where a text is needed.
+    NotText {
+        /// The variable.
+        name: String,
+        /// The position of the form.
+        pos: CharPos,
+    },
+    /// The variable is a text where a list is needed.
+    NotList {
+        /// The variable.
+        name: String,
+        /// The position of the form.
+        pos: CharPos,
+    },
+    /// The operands of the form are not as its operator requires.
+    InvalidForm {
+        /// The operator of the form.
+        operator: &'static str,
+        /// The position of the form.
+        pos: CharPos,
+    },
+}
+
+
+/// The type of the operative combiners of our bindings.
+pub type Op = WithContext<Box<OpCtxFn<DatumAllocator, TemplateError, State>>>;
+
+/// The type of the applicative combiners of our bindings.
+pub type Ap = WithContext<Box<ApCtxFn<DatumAllocator, TemplateError, State>>>;
+
+/// The type of the bindings of templates.  The premade ones are made by
+/// [`bindings`](fn.bindings.html), and you may add your own combiners to them.
+pub type Bindings = PatternOperatorBindings<DatumAllocator, Op, Ap, TemplateError>;
+
+/// The user context of parsing templates, given to the combiners.
+pub struct State {
+    /// The variables.
+    pub env: Env,
+    /// The bindings, for evaluating the bodies of forms.
+    bindings: Rc<Bindings>,
+}
+
+/// Must implement this manually because the bindings are not `Debug`.
+impl fmt::Debug for State {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("State").field("env", &self.env).finish_non_exhaustive()
+    }
+}
+
+
+/// Evaluates templates with our bindings.
+pub struct Template {
+    bindings: Rc<Bindings>,
+}
+
+/// Must implement this manually because the bindings are not `Debug`.
+impl fmt::Debug for Template {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Template").finish_non_exhaustive()
+    }
+}
+
+impl Default for Template {
+    #[inline]
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Template {
+    /// Make a new instance of `Self` that uses the premade
+    /// [`bindings`](fn.bindings.html).
+    #[inline]
+    #[must_use]
+    pub fn new() -> Self {
+        Self::with_bindings(bindings())
+    }
+
+    /// Make a new instance of `Self` that uses the given bindings, e.g. the
+    /// premade ones with some of your own added.
+    #[inline]
+    #[must_use]
+    pub fn with_bindings(bindings: Bindings) -> Self {
+        Self { bindings: Rc::new(bindings) }
+    }
+
+    /// Parse the given source, evaluating the forms, with the given variables,
+    /// and return the resulting top-level forms.  The variables defined by
+    /// `{set}` forms are kept in `env` afterward, even if an error occurs.
+    ///
+    /// # Errors
+    ///
+    /// If parsing fails, or if a form fails with a `TemplateError`, the first
+    /// `Error` is returned.
+    pub fn expand<S>(&self, source: S, env: &mut Env)
+                     -> Result<Vec<Datum>>
+        where S: Into<Rc<str>>,
+    {
+        let mut parser = Parser {
+            classifier: CharClassifier,
+            allocator: DatumAllocator::default(),
+            bindings: &*self.bindings,
+        };
+        let state = State { env: mem::take(env), bindings: Rc::clone(&self.bindings) };
+        let mut iter = parser.parse_with_context(
+            StrishIterSourceStream::new(once(source.into())), state);
+        let result = iter.by_ref().collect();
+        *env = iter.into_user_context().env;
+        result
+    }
+}
+
+
+/// Make the premade bindings of `set`, `$`-prefixed names, `if`, and `each`.
+pub fn bindings() -> Bindings {
+    let set: Box<ApCtxFn<DatumAllocator, TemplateError, State>> = Box::new(set);
+    let subst: Box<ApCtxFn<DatumAllocator, TemplateError, State>> = Box::new(subst);
+    let cond: Box<OpCtxFn<DatumAllocator, TemplateError, State>> = Box::new(cond);
+    let each: Box<OpCtxFn<DatumAllocator, TemplateError, State>> = Box::new(each);
+    let mut bindings = Bindings::new();
+    bindings.insert(0, Pattern::Text("set".into()), Combiner::Applicative(WithContext(set)));
+    bindings.insert(0, Pattern::Prefix("$".into()), Combiner::Applicative(WithContext(subst)));
+    bindings.insert(0, Pattern::Text("if".into()), Combiner::Operative(WithContext(cond)));
+    bindings.insert(0, Pattern::Text("each".into()), Combiner::Operative(WithContext(each)));
+    bindings
+}
+
+
+type Result<T> = std::result::Result<T, Error<TemplateError>>;
+
+type Ctx<'c> = Context<'c, DatumAllocator, TemplateError, State>;
+
+fn invalid(operator: &'static str, ctx: &Ctx<'_>)
+           -> Error<TemplateError>
+{
+    Error::FailedCombiner(TemplateError::InvalidForm { operator, pos: ctx.span.start })
+}
+
+#[allow(clippy::needless_pass_by_value)]
+fn set(_operator: Datum, operands: Datum, ctx: &mut Ctx<'_>)
+       -> Result<Option<Datum>>
+{
+    let mut s = String::new();
+    for elem in list_elems(&operands) {
+        s.push_str(&word_of(elem).ok_or_else(|| invalid("set", ctx))?);
+    }
+    let s = s.trim_start();
+    let (name, value) = s.split_at(s.find(char::is_whitespace).unwrap_or(s.len()));
+    if name.is_empty() {
+        return Err(invalid("set", ctx));
+    }
+    let _ = ctx.user.env.set(name, value.trim_start());
+    Ok(None)
+}
+
+#[allow(clippy::needless_pass_by_value)]
+fn subst(operator: Datum, _operands: Datum, ctx: &mut Ctx<'_>)
+         -> Result<Option<Datum>>
+{
+    let name = word_of(&operator).unwrap().chars().skip(1).collect::<String>();
+    match ctx.user.env.get(&name) {
+        Some(Value::Text(s)) => Ok(Some(DatumEnum::Text(Text::from_str(s)))),
+        Some(Value::List(_)) =>
+            Err(Error::FailedCombiner(TemplateError::NotText { name, pos: ctx.span.start })),
+        None =>
+            Err(Error::FailedCombiner(TemplateError::Undefined { name, pos: ctx.span.start })),
+    }
+}
+
+#[allow(clippy::needless_pass_by_value)]
+fn cond(operator: Datum, operands: Text, ctx: &mut Ctx<'_>)
+        -> Result<Option<Datum>>
+{
+    let mut body = operands.iter();
+    let word = words(&mut body, 1).pop().unwrap();
+    let (negate, name) = match word.strip_prefix('!') {
+        Some(name) => (true, name),
+        None => (false, &*word),
+    };
+    if name.is_empty() {
+        return Err(invalid("if", ctx));
+    }
+    let truth = match ctx.user.env.get(name) {
+        Some(Value::Text(s)) => !s.is_empty() && s != "false",
+        Some(Value::List(l)) => !l.is_empty(),
+        None => false,
+    };
+    if truth == negate {
+        return Ok(None);
+    }
+    let mut forms = Vec::new();
+    evaluate(body, &operator, ctx, &mut forms)?;
+    combine(forms, ctx.allocator)
+}
+
+#[allow(clippy::needless_pass_by_value)]
+fn each(operator: Datum, operands: Text, ctx: &mut Ctx<'_>)
+        -> Result<Option<Datum>>
+{
+    let header = words(&mut operands.iter(), 3);
+    let (var, list) = match &header[..] {
+        [var, in_, list] if !var.is_empty() && in_ == "in" && !list.is_empty() => (var, list),
+        _ => return Err(invalid("each", ctx)),
+    };
+    let items = match ctx.user.env.get(list) {
+        Some(Value::List(items)) => items.clone(),
+        Some(Value::Text(_)) => return Err(Error::FailedCombiner(
+            TemplateError::NotList { name: list.clone(), pos: ctx.span.start })),
+        None => return Err(Error::FailedCombiner(
+            TemplateError::Undefined { name: list.clone(), pos: ctx.span.start })),
+    };
+    let outer = ctx.user.env.remove(var);
+    let mut forms = Vec::new();
+    let mut result = Ok(());
+    for item in items {
+        let _ = ctx.user.env.set(var.as_str(), item);
+        let mut body = operands.iter();
+        let _ = words(&mut body, 3);
+        result = evaluate(body, &operator, ctx, &mut forms);
+        if result.is_err() {
+            break;
+        }
+    }
+    match outer {
+        Some(value) => { let _ = ctx.user.env.set(var.as_str(), value); }
+        None => { let _ = ctx.user.env.remove(var); }
+    }
+    result?;
+    combine(forms, ctx.allocator)
+}
+
+/// Consume the given number of whitespace-separated words from the start of
+/// operands text, and the single whitespace character after the last one.
+fn words<I>(source: &mut I, count: usize) -> Vec<String>
+    where I: Iterator<Item = crate::SourceIterItem<CharPos>>,
+{
+    let mut words = vec![String::new()];
+    for item in source {
+        if item.ch.is_whitespace() {
+            if words.last().is_some_and(|w| !w.is_empty()) {
+                if words.len() == count {
+                    break;
+                }
+                words.push(String::new());
+            }
+        } else {
+            words.last_mut().unwrap().push(item.ch);
+        }
+    }
+    words.resize(count, String::new());
+    words
+}
+
+/// Parse the rest of a body, as inside the form of the given operator, with our
+/// bindings, and append its forms.
+fn evaluate(body: crate::text::iter::Iter<'_, Text>,
+            operator: &Datum,
+            ctx: &mut Ctx<'_>,
+            forms: &mut Vec<Datum>)
+            -> Result<()>
+{
+    let bindings = Rc::clone(&ctx.user.bindings);
+    let here = Enclosing { operator, parent: ctx.enclosing };
+    let mut sub = SubParser::new(&CharClassifier, ctx.allocator, &*bindings)
+                      .within(ctx.depth, Some(&here));
+    let mut list = sub.parse_list_with_context(body, ctx.user)?;
+    while let DatumEnum::List { mut elem, mut next } = list {
+        forms.push(mem::replace(&mut *elem, DatumEnum::EmptyList));
+        list = mem::replace(&mut *next, DatumEnum::EmptyList);
+    }
+    Ok(())
+}
+
+/// Substitute a single form as it is, or several as a list.
+fn combine(mut forms: Vec<Datum>, dalloc: &mut DatumAllocator) -> Result<Option<Datum>> {
+    if forms.len() <= 1 {
+        return Ok(forms.pop());
+    }
+    let mut list = DatumEnum::EmptyList;
+    for elem in forms.into_iter().rev() {
+        list = DatumEnum::List { elem: dalloc.new_datum(elem)?, next: dalloc.new_datum(list)? };
+    }
+    Ok(Some(list))
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::datum_util::to_kul;
+
+    fn expand(source: &str, env: &mut Env) -> Result<String> {
+        Template::new().expand(source, env).map(|forms| to_kul(&forms, &CharClassifier))
+    }
+
+    #[test]
+    fn substitution() {
+        let mut env: Env = vec![("name", "Ann {x}")].into_iter().collect();
+        assert_eq!(expand("Hi {$name}!{set greeting Hello, {$name}.} {$greeting}", &mut env),
+                   Ok(String::from(r"Hi Ann \{x\}! Hello, Ann \{x\}.")));
+        assert_eq!(env.get("greeting"), Some(&Value::from("Hello, Ann {x}.")));
+
+        assert_eq!(expand("a {$nope}", &mut env),
+                   Err(Error::FailedCombiner(TemplateError::Undefined {
+                       name: String::from("nope"), pos: CharPos(2) })));
+        let _ = env.set("l", vec!["x"]);
+        assert!(matches!(expand("{$l}", &mut env),
+                         Err(Error::FailedCombiner(TemplateError::NotText { .. }))));
+        assert!(matches!(expand("{set}", &mut env),
+                         Err(Error::FailedCombiner(TemplateError::InvalidForm { operator: "set", .. }))));
+    }
+
+    #[test]
+    fn conditionals_and_loops() {
+        let mut env: Env = vec![("yes", Value::from("y")),
+                                ("no", Value::from("false")),
+                                ("items", Value::from(vec!["a", "b", "c"])),
+                                ("none", Value::List(vec![]))]
+                           .into_iter().collect();
+        assert_eq!(expand("{if yes Y}{if no N}{if !no !N}{if undefined U}{if !none E}",
+                          &mut env),
+                   Ok(String::from("Y!NE")));
+        assert_eq!(expand("{each i in items <{$i}>}{each i in none x}", &mut env),
+                   Ok(String::from("<a><b><c>")));
+        // The body is only evaluated when used, and the item variable is
+        // restored.
+        let _ = env.set("i", "outer");
+        assert_eq!(expand("{if no {$undefined}}{each i in items {set last {$i}}}{$i} {$last}",
+                          &mut env),
+                   Ok(String::from("outer c")));
+        // Escapes in bodies are processed before the bodies are parsed again.
+        assert_eq!(expand(r"{if yes \\\{{$yes}\\\}}", &mut env),
+                   Ok(String::from(r"\{y\}")));
+
+        assert!(matches!(expand("{each i of items x}", &mut env),
+                         Err(Error::FailedCombiner(TemplateError::InvalidForm { operator: "each", .. }))));
+        assert!(matches!(expand("{each i in yes x}", &mut env),
+                         Err(Error::FailedCombiner(TemplateError::NotList { .. }))));
+        assert_eq!(env.get("i"), Some(&Value::from("outer")));
+    }
+
+    #[test]
+    fn printing() {
+        let forms = Template::new().expand(r"a\{{b  c {d}}{}{{e} f}", &mut Env::new()).unwrap();
+        assert_eq!(to_kul(&forms, &CharClassifier), r"a\{{b  c {d}}{}{{e} f}");
+    }
+}
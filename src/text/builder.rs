@@ -0,0 +1,209 @@
+//! Building `Text` values from Rust code, e.g. for generated ASTs or the
+//! outputs of combiners, instead of from parsed sources.
+
+use std::{fmt, iter::FromIterator, marker::PhantomData};
+
+use crate::{Text, SourcePosition};
+use crate::text::chunk::{PosStrish, CharPos, RefCntStrish};
+
+
+/// Builds a `Text` by pushing `&str`s, `String`s, and `char`s, and then makes a
+/// `Text` of a single [`PosStrish`] chunk of them all.
+///
+/// Because the built text isn't from a source, its characters' positions are
+/// synthetic: they start at the position given to [`starting_at`], which is
+/// initially `CharPos(0)`, and increase by one per `char`.
+///
+/// The `S` type parameter is the reference-counted string-ish type of the
+/// chunk, e.g. `Rc<str>`.
+///
+/// [`PosStrish`]: chunk/struct.PosStrish.html
+/// [`starting_at`]: #method.starting_at
+pub struct TextBuilder<S> {
+    buf: String,
+    start: CharPos,
+    strish: PhantomData<S>,
+}
+
+/// Must implement this manually because deriving would place unwanted bounds
+/// on the type parameters.
+impl<S> fmt::Debug for TextBuilder<S> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("TextBuilder")
+         .field("buf", &self.buf)
+         .field("start", &self.start)
+         .finish()
+    }
+}
+
+impl<S> Default for TextBuilder<S> {
+    #[inline]
+    fn default() -> Self {
+        Self { buf: String::new(), start: CharPos::empty(), strish: PhantomData }
+    }
+}
+
+impl<S> TextBuilder<S>
+    where S: RefCntStrish,
+{
+    /// Make a new empty instance of `Self`.
+    #[inline]
+    #[must_use]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Make the synthetic positions start at `start`, and return `self` for
+    /// chaining.
+    #[inline]
+    #[must_use]
+    pub fn starting_at(mut self, start: CharPos) -> Self {
+        self.start = start;
+        self
+    }
+
+    /// Push a `char`.
+    #[inline]
+    pub fn push(&mut self, ch: char) -> &mut Self {
+        self.buf.push(ch);
+        self
+    }
+
+    /// Push the characters of a `&str`.
+    #[inline]
+    pub fn push_str(&mut self, s: &str) -> &mut Self {
+        self.buf.push_str(s);
+        self
+    }
+
+    /// Push the characters of a `String`, which is reused if nothing has been
+    /// pushed yet.
+    pub fn push_string(&mut self, s: String) -> &mut Self {
+        if self.buf.is_empty() {
+            self.buf = s;
+        } else {
+            self.buf.push_str(&s);
+        }
+        self
+    }
+
+    /// Push the logical character sequence of any `Text`.
+    pub fn push_text<TT>(&mut self, text: &TT) -> &mut Self
+        where TT: Text,
+    {
+        match text.as_single_str() {
+            Some(s) => self.buf.push_str(s),
+            None => self.buf.extend(text.chars()),
+        }
+        self
+    }
+
+    /// Return the characters pushed so far.
+    #[inline]
+    #[must_use]
+    pub fn as_str(&self) -> &str {
+        &self.buf
+    }
+
+    /// Make the chunk of everything pushed.
+    #[inline]
+    #[must_use]
+    pub fn build_chunk(&self) -> PosStrish<S> {
+        PosStrish::new(S::from_str(&self.buf), self.start)
+    }
+
+    /// Make a `Text`, of any type whose chunks are `PosStrish`, e.g.
+    /// [`TextVec`](struct.TextVec.html), of everything pushed.
+    #[inline]
+    #[must_use]
+    pub fn build<TT>(&self) -> TT
+        where TT: Text<Chunk = PosStrish<S>>,
+    {
+        TT::from(self.build_chunk())
+    }
+}
+
+impl<S> fmt::Write for TextBuilder<S>
+    where S: RefCntStrish,
+{
+    #[inline]
+    fn write_str(&mut self, s: &str) -> fmt::Result {
+        let _ = self.push_str(s);
+        Ok(())
+    }
+}
+
+impl<S> Extend<char> for TextBuilder<S> {
+    #[inline]
+    fn extend<I>(&mut self, iter: I)
+        where I: IntoIterator<Item = char>,
+    {
+        self.buf.extend(iter);
+    }
+}
+
+impl<'s, S> Extend<&'s str> for TextBuilder<S> {
+    #[inline]
+    fn extend<I>(&mut self, iter: I)
+        where I: IntoIterator<Item = &'s str>,
+    {
+        self.buf.extend(iter);
+    }
+}
+
+impl<S> FromIterator<char> for TextBuilder<S> {
+    #[inline]
+    fn from_iter<I>(iter: I) -> Self
+        where I: IntoIterator<Item = char>,
+    {
+        let mut builder = Self::default();
+        builder.extend(iter);
+        builder
+    }
+}
+
+impl<S> From<String> for TextBuilder<S> {
+    #[inline]
+    fn from(buf: String) -> Self {
+        Self { buf, ..Self::default() }
+    }
+}
+
+impl<S> From<&str> for TextBuilder<S> {
+    #[inline]
+    fn from(s: &str) -> Self {
+        Self::from(String::from(s))
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::{rc::Rc, fmt::Write};
+    use crate::{TextBase, TextConcat, text::TextVec};
+
+    type TB = TextBuilder<Rc<str>>;
+    type TV = TextVec<PosStrish<Rc<str>>>;
+
+    #[test]
+    fn build() {
+        let mut b = TB::new().starting_at(CharPos(10));
+        let _ = b.push_str("ab").push('λ').push_string(String::from("c"));
+        write!(b, "{}", 42).unwrap();
+        let parsed = TV::from_str("x").concat(TV::from_str("y"), &mut ()).unwrap();
+        let _ = b.push_text(&parsed);
+        assert_eq!(b.as_str(), "abλc42xy");
+        let text: TV = b.build();
+        assert_eq!(text, TV::from_str("abλc42xy"));
+        assert_eq!(text.as_single_str(), Some("abλc42xy"));
+        let poss: Vec<usize> = text.iter().map(|it| it.pos.0).collect();
+        assert_eq!(poss, [10, 11, 12, 13, 14, 15, 16, 17]);
+
+        let text: TV = TB::from(String::from("s")).build();
+        assert_eq!(text, TV::from_str("s"));
+        let text: TV = "tu".chars().collect::<TB>().build();
+        assert_eq!(text, TV::from_str("tu"));
+        assert!(TB::new().build::<TV>().is_empty());
+    }
+}
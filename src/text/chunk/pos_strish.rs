@@ -250,6 +250,11 @@ impl<S> TextChunk for PosStrish<S>
     fn src_strm(&self) -> Self::CharsSrcStrm {
         PosStrishIter::new(self)
     }
+
+    #[inline]
+    fn as_chunk_str(&self) -> Option<&str> {
+        Some(AsStr::as_str(&self.val))
+    }
 }
 
 
@@ -370,17 +375,17 @@ mod tests {
 
     #[test]
     fn as_str() {
-        assert_eq!("a".as_str(), "a");
-        assert_eq!(String::from("bb").as_str(), "bb");
-        assert_eq!(Box::<str>::from("c cc").as_str(), "c cc");
-        assert_eq!(Rc::new(String::from(" dddd")).as_str(), " dddd");
-        assert_eq!(Arc::new(Box::new(Rc::new(Arc::<str>::from("eee  ee ")))).as_str(),
+        assert_eq!(AsStr::as_str("a"), "a");
+        assert_eq!(AsStr::as_str(&String::from("bb")), "bb");
+        assert_eq!(AsStr::as_str(&Box::<str>::from("c cc")), "c cc");
+        assert_eq!(AsStr::as_str(&Rc::new(String::from(" dddd"))), " dddd");
+        assert_eq!(AsStr::as_str(&Arc::new(Box::new(Rc::new(Arc::<str>::from("eee  ee "))))),
                    "eee  ee ");
-        assert_eq!(Rc::<str>::from(" f 6 ").as_str(), " f 6 ");
-        assert_eq!(Rc::new(Box::<str>::from("GggGggg")).as_str(), "GggGggg");
-        assert_eq!(Arc::<str>::from(" h 8 ").as_str(), " h 8 ");
-        assert_eq!(Arc::new(Box::<str>::from("IiiiIiiii")).as_str(), "IiiiIiiii");
-        assert_eq!(Arc::new(String::from(" jjjjj jjjjj")).as_str(), " jjjjj jjjjj");
+        assert_eq!(AsStr::as_str(&Rc::<str>::from(" f 6 ")), " f 6 ");
+        assert_eq!(AsStr::as_str(&Rc::new(Box::<str>::from("GggGggg"))), "GggGggg");
+        assert_eq!(AsStr::as_str(&Arc::<str>::from(" h 8 ")), " h 8 ");
+        assert_eq!(AsStr::as_str(&Arc::new(Box::<str>::from("IiiiIiiii"))), "IiiiIiiii");
+        assert_eq!(AsStr::as_str(&Arc::new(String::from(" jjjjj jjjjj"))), " jjjjj jjjjj");
     }
 
     #[test]
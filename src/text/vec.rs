@@ -5,6 +5,7 @@ use std::{slice, cmp::Ordering, hash::{Hash, Hasher}};
 
 use crate::{Text, TextChunk, TextBase, TextConcat};
 use crate::parser::AllocError;
+use crate::text::chunk::{PosStrish, RefCntStrish};
 
 use Repr::{Single, Multi};
 
@@ -113,6 +114,38 @@ impl<C> Text for TextVec<C>
 }
 
 
+impl<S> TextVec<PosStrish<S>>
+    where S: RefCntStrish,
+{
+    /// Merge our chunks into a single contiguous chunk, e.g. after parsing an
+    /// input with many escapes which produces many tiny chunks, so that
+    /// further uses are faster and so that [`as_single_str`] works.
+    ///
+    /// If there are multiple non-empty chunks, their contents are copied into
+    /// a new chunk whose position is that of the first, and so the positions
+    /// of the characters after the first chunk become synthetic.  Otherwise,
+    /// nothing is copied.
+    ///
+    /// [`as_single_str`]: ../trait.Text.html#method.as_single_str
+    pub fn compact(&mut self) {
+        if let Single(_) = self.0 {
+            return
+        }
+        let mut chunks = self.iter_chunks().filter(|chunk| !chunk.is_empty());
+        let compacted = match (chunks.next(), chunks.next()) {
+            (None, _) => Self::empty(),
+            (Some(chunk), None) => Self(Single(chunk.clone())),
+            (Some(first), Some(_)) => {
+                let buf: String = self.iter_chunks().map(|chunk| chunk.val.as_ref())
+                                                    .collect();
+                Self(Single(PosStrish::new(S::from_str(&buf), first.pos)))
+            }
+        };
+        *self = compacted;
+    }
+}
+
+
 /// Enables `TextVec` to be used with `Parser`s as the produced `Datum`s' text
 /// type.
 impl<C, DA> TextConcat<DA> for TextVec<C>
@@ -194,4 +227,43 @@ mod tests {
                                                     TV::from_str("p"))),
                    TV::from_str("qrs"));
     }
+    #[test]
+    fn slicing() {
+        let text = concat(TV::from_str(" key: "), TV::from_str("val "));
+        let colon = text.find(|c| c == ':').unwrap();
+        let (key, val) = text.split_at(colon, &mut ()).unwrap();
+        assert_eq!(key.trim(&mut ()).unwrap(), TV::from_str("key"));
+        let val = val.strip_prefix(":", &mut ()).unwrap().unwrap().trim(&mut ()).unwrap();
+        assert_eq!(val, TV::from_str("val"));
+        assert_eq!(val.as_single_str(), Some("val"));
+        let words: Vec<TV> = text.split(char::is_whitespace, &mut ())
+                                 .collect::<Result<_, _>>().unwrap();
+        assert_eq!(words, [TV::empty(), TV::from_str("key:"), TV::from_str("val"),
+                           TV::empty()]);
+    }
+    #[test]
+    fn compact() {
+        use std::rc::Rc;
+        use crate::text::chunk::CharPos;
+
+        type TS = TextVec<PosStrish<Rc<str>>>;
+        let chunk = |s, pos| TS::from(PosStrish::new(Rc::from(s), CharPos(pos)));
+
+        let mut text = chunk("ab", 3).concat(chunk("", 0), &mut ()).unwrap()
+                                     .concat(chunk("λ", 7), &mut ()).unwrap();
+        assert_eq!(text.iter_chunks().count(), 2);
+        assert_eq!(text.as_single_str(), None);
+        text.compact();
+        assert_eq!(text.iter_chunks().count(), 1);
+        assert_eq!(text.as_single_str(), Some("abλ"));
+        assert_eq!(text.iter().map(|it| it.pos.0).collect::<Vec<_>>(), [3, 4, 5]);
+
+        let mut text = TS::empty().concat(chunk("c", 9), &mut ()).unwrap();
+        text.compact();
+        assert_eq!((text.as_single_str(), text.iter().next().map(|it| it.pos)),
+                   (Some("c"), Some(CharPos(9))));
+        let mut text = TS::empty();
+        text.compact();
+        assert!(text.is_empty());
+    }
 }
@@ -0,0 +1,696 @@
+//! A generic data model, [`Value`], for using Kul as a data format in place of
+//! JSON, YAML, or TOML.
+//!
+//! The data profile is the forms of the [`stdlib`] module, plus a few more,
+//! where each form denotes a `Value`:
+//!
+//! * `{null}` is `Value::Null`.
+//!
+//! * `{bool true}`, `{int -42}`, `{float 8.7}`, and `{str ...}` are the scalars
+//!   of their operands text, trimmed except for `str`.
+//!
+//! * `{list 9, "blah", {map ...}}` is a `Value::List` of its comma-separated
+//!   items.
+//!
+//! * `{map foo: 8.7, bar: asdf}` is a `Value::Map` of its comma-separated
+//!   `key: value` entries.
+//!
+//! * `{tag name: item}` is a `Value::Tagged` of the name and the item.  A form
+//!   of any other operator, e.g. `{point 1}`, is the same as
+//!   `{tag point: 1}`, and so an application's own forms are kept.
+//!
+//! * `{# ...}` is a comment and is ignored.
+//!
+//! The items of lists, the values of map entries, and the items of tags are as
+//! described for [`stdlib`]: either a single nested form, or text that is
+//! converted to a scalar, where `"quoted"` text is a string, `true` and `false`
+//! are booleans, and numbers are integers or floats, and any other text is a
+//! string.  A blank item of a tag is `Value::Null`.  A text at the top level is
+//! converted the same way, and `{}` is `Value::Null`.
+//!
+//! A `Value` can be produced either while parsing, by binding our
+//! [`combiners`], which produce `Datum::Extra`s of `Value`s, or after parsing,
+//! by [`Value::from_datum`] on an AST parsed without bindings.  The two can be
+//! mixed: `Value::from_datum` also converts the `Datum::Extra`s produced by the
+//! combiners.  [`Value::to_kul`] writes a `Value` back as canonical Kul, which
+//! converts to an equal `Value`.
+//!
+//! [`Value`]: enum.Value.html
+//! [`stdlib`]: ../stdlib/index.html
+//! [`combiners`]: fn.combiners.html
+//! [`Value::from_datum`]: enum.Value.html#method.from_datum
+//! [`Value::to_kul`]: enum.Value.html#method.to_kul
+
+#![allow(clippy::type_complexity)]
+
+use std::{
+    collections::BTreeMap, fmt, mem,
+    hash::{Hash, Hasher, BuildHasher},
+};
+
+use crate::{
+    Datum, Text, TextBase, DerefTryMut,
+    parser::{DatumAllocator, HashMapOperatorBindings, DefaultCharClassifier},
+    combiner::{Combiner, OpFn, ApFn},
+    stdlib::{self, CombinerError, CombinerErrorKind, Extra, Items, text_pos},
+    datum_util::escape,
+    source_map::CharOffset,
+};
+
+
+/// A value of the data profile.  See the [module](index.html) documentation.
+#[derive(Clone, Debug)]
+pub enum Value {
+    /// From `{null}` or `{}`.
+    Null,
+    /// From `{bool ...}` or a `true` or `false` item.
+    Bool(bool),
+    /// From `{int ...}` or an integer item.
+    Int(i128),
+    /// From `{float ...}` or a non-integer numeric item.
+    Float(f64),
+    /// From `{str ...}`, a quoted item, or any other textual item.
+    String(String),
+    /// From `{list ...}`.
+    List(Vec<Value>),
+    /// From `{map ...}`.  Later entries replace earlier ones with the same key.
+    Map(BTreeMap<String, Value>),
+    /// From `{tag ...}` or a form of an operator not in the profile.
+    Tagged(String, Box<Value>),
+}
+
+/// `Float`s are compared by their bits, so that this is a proper `Eq`, as
+/// needed by `HashMapOperatorBindings`.
+impl PartialEq for Value {
+    fn eq(&self, other: &Self) -> bool {
+        match (self, other) {
+            (Value::Null, Value::Null) => true,
+            (Value::Bool(a), Value::Bool(b)) => a == b,
+            (Value::Int(a), Value::Int(b)) => a == b,
+            (Value::Float(a), Value::Float(b)) => a.to_bits() == b.to_bits(),
+            (Value::String(a), Value::String(b)) => a == b,
+            (Value::List(a), Value::List(b)) => a == b,
+            (Value::Map(a), Value::Map(b)) => a == b,
+            (Value::Tagged(an, av), Value::Tagged(bn, bv)) => an == bn && av == bv,
+            _ => false,
+        }
+    }
+}
+
+impl Eq for Value {}
+
+impl Hash for Value {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        mem::discriminant(self).hash(state);
+        match self {
+            Value::Null => (),
+            Value::Bool(b) => b.hash(state),
+            Value::Int(i) => i.hash(state),
+            Value::Float(f) => f.to_bits().hash(state),
+            Value::String(s) => s.hash(state),
+            Value::List(l) => l.hash(state),
+            Value::Map(m) => m.hash(state),
+            Value::Tagged(n, v) => {
+                n.hash(state);
+                v.hash(state);
+            }
+        }
+    }
+}
+
+impl From<Extra> for Value {
+    /// `Extra::Map`, which is only an operator, becomes `Value::Null`.
+    fn from(extra: Extra) -> Self {
+        match extra {
+            Extra::Int(i) => Value::Int(i),
+            Extra::Float(f) => Value::Float(f),
+            Extra::Bool(b) => Value::Bool(b),
+            Extra::Str(s) => Value::String(s),
+            Extra::Map => Value::Null,
+        }
+    }
+}
+
+
+/// The "extra" types of `Datum`s that [`Value::from_datum`] can convert.
+///
+/// [`Value::from_datum`]: enum.Value.html#method.from_datum
+pub trait ExtraValue {
+    /// The `Value` of `self`, or `None` if it has none.
+    fn to_value(&self) -> Option<Value>;
+}
+
+impl ExtraValue for () {
+    /// Has no value.
+    #[inline]
+    fn to_value(&self) -> Option<Value> {
+        None
+    }
+}
+
+impl ExtraValue for Value {
+    #[inline]
+    fn to_value(&self) -> Option<Value> {
+        Some(self.clone())
+    }
+}
+
+
+/// The errors of converting to `Value`s, which are also the errors that our
+/// combiners might return, as the [`Error::FailedCombiner`] variant.  The
+/// `Pos` type parameter is the position type of the converted `Text`s.
+///
+/// [`Error::FailedCombiner`]: ../enum.Error.html#variant.FailedCombiner
+#[derive(Clone, PartialEq, Eq, Hash, Debug)]
+pub enum ValueError<Pos> {
+    /// A scalar or an item is invalid, as for the [`stdlib`] combiners, and
+    /// with a position as they give.
+    ///
+    /// [`stdlib`]: ../stdlib/index.html
+    Invalid(CombinerError<Pos>),
+    /// A form's operator is not a text, and so the form has no meaning.
+    NonTextOperator,
+    /// A `Datum::Extra` has no `Value`.
+    OpaqueExtra,
+}
+
+impl<Pos> From<CombinerError<Pos>> for ValueError<Pos> {
+    #[inline]
+    fn from(e: CombinerError<Pos>) -> Self {
+        ValueError::Invalid(e)
+    }
+}
+
+/// The errors of writing `Value`s as Kul.
+#[derive(Debug)]
+pub enum WriteError {
+    /// The output failed.
+    Fmt(fmt::Error),
+    /// A map key or tag name can't be written so that it reads back the same,
+    /// because it is empty or needs quoting but has a `"` in it.
+    Unrepresentable(String),
+}
+
+impl From<fmt::Error> for WriteError {
+    #[inline]
+    fn from(e: fmt::Error) -> Self {
+        WriteError::Fmt(e)
+    }
+}
+
+
+type DAPos<DA> = <<DA as DatumAllocator>::TT as TextBase>::Pos;
+
+type Error<DA> = crate::Error<DAPos<DA>, ValueError<DAPos<DA>>>;
+
+/// The type of `Combiner`s returned by [`combiners`](fn.combiners.html).
+pub type BoxCombiner<DA> = Combiner<
+    Box<OpFn<DA, ValueError<<<DA as DatumAllocator>::TT as TextBase>::Pos>>>,
+    Box<ApFn<DA, ValueError<<<DA as DatumAllocator>::TT as TextBase>::Pos>>>>;
+
+
+/// All of our combiners, paired with their names, boxed so that they can be
+/// collected into bindings.
+#[must_use]
+pub fn combiners<DA>() -> Vec<(&'static str, BoxCombiner<DA>)>
+    where DA: DatumAllocator<ET = Value> + 'static,
+          DAPos<DA>: CharOffset,
+{
+    let scalar_op = |name: &'static str| -> BoxCombiner<DA> {
+        Combiner::Operative(Box::new(move |_, operands, _| {
+            let text = operands.chars().collect::<String>();
+            typed(name, text).map(|v| Some(Datum::Extra(v)))
+                             .map_err(|kind| Error::<DA>::FailedCombiner(
+                                 CombinerError { pos: text_pos(&operands), kind }.into()))
+        }))
+    };
+    let compound_op = |name: &'static str| -> BoxCombiner<DA> {
+        Combiner::Applicative(Box::new(move |_, operands, _| {
+            compound(name, &operands).map(|v| Some(Datum::Extra(v)))
+                                     .map_err(Error::<DA>::FailedCombiner)
+        }))
+    };
+    vec![
+        ("#", Combiner::Operative(Box::new(|_, _, _| Ok(None)))),
+        ("null", scalar_op("null")),
+        ("bool", scalar_op("bool")),
+        ("int", scalar_op("int")),
+        ("float", scalar_op("float")),
+        ("str", scalar_op("str")),
+        ("list", compound_op("list")),
+        ("map", compound_op("map")),
+        ("tag", compound_op("tag")),
+    ]
+}
+
+/// Bind all of our [`combiners`](fn.combiners.html) in the given bindings,
+/// keyed by `Datum::Text`s of their names.  Any existing bindings of the same
+/// names are replaced.
+pub fn insert_into<DA, S>(
+    bindings: &mut HashMapOperatorBindings<DA, Box<OpFn<DA, ValueError<DAPos<DA>>>>,
+                                           Box<ApFn<DA, ValueError<DAPos<DA>>>>,
+                                           ValueError<DAPos<DA>>, S>
+)
+    where DA: DatumAllocator<ET = Value> + 'static,
+          DAPos<DA>: CharOffset,
+          DA::TT: Hash + Eq,
+          DA::DR: Hash + Eq,
+          <DA::TT as Text>::Chunk: From<&'static str>,
+          S: BuildHasher,
+{
+    bindings.hashmap.extend(
+        combiners().into_iter().map(|(name, c)| (Datum::Text(DA::TT::from_str(name)), c)));
+}
+
+
+impl Value {
+    /// Convert the given `Datum` to a `Value`, as described in the
+    /// [module](index.html) documentation.
+    ///
+    /// This recurses as deeply as the `Datum` is nested.
+    ///
+    /// # Errors
+    ///
+    /// If the `Datum` or anything in it is invalid, returns the corresponding
+    /// `ValueError`.
+    pub fn from_datum<TT, ET, DR>(datum: &Datum<TT, ET, DR>)
+                                  -> Result<Self, ValueError<TT::Pos>>
+        where TT: Text,
+              TT::Pos: CharOffset,
+              ET: ExtraValue,
+              DR: DerefTryMut<Target = Datum<TT, ET, DR>>,
+    {
+        match datum {
+            Datum::Text(text) => stdlib::scalar(text.chars().collect::<String>().trim())
+                                     .map(Value::from)
+                                     .map_err(|kind| invalid(text_pos(text), kind)),
+            Datum::Extra(extra) => extra.to_value().ok_or(ValueError::OpaqueExtra),
+            Datum::EmptyNest => Ok(Value::Null),
+            Datum::EmptyList | Datum::List { .. } => {
+                let mut values = Vec::new();
+                let mut list = datum;
+                while let Datum::List { elem, next } = list {
+                    values.push(Self::from_datum(elem)?);
+                    list = next;
+                }
+                Ok(Value::List(values))
+            }
+            Datum::Combination { operator, operands } => match &**operator {
+                Datum::Text(name) => {
+                    let name = name.chars().collect::<String>();
+                    match &*name {
+                        "#" => Ok(Value::Null),
+                        "null" | "bool" | "int" | "float" | "str" => {
+                            let item = single(operands);
+                            let pos = item.pos();
+                            match item.into_parts()? {
+                                (text, None) => typed(&name, text)
+                                                    .map_err(|kind| invalid(pos, kind)),
+                                (_, Some(_)) => Err(invalid(pos, CombinerErrorKind::MixedItem)),
+                            }
+                        }
+                        "list" | "map" | "tag" => compound(&name, operands),
+                        _ => Ok(Value::Tagged(name, Box::new(item_value(single(operands))?))),
+                    }
+                }
+                _ => Err(ValueError::NonTextOperator),
+            },
+        }
+    }
+
+    /// Convert the given top-level forms to `Value`s, ignoring blank texts and
+    /// comments.
+    ///
+    /// # Errors
+    ///
+    /// If a form is invalid, returns the corresponding `ValueError`.
+    pub fn from_forms<TT, ET, DR>(forms: &[Datum<TT, ET, DR>])
+                                  -> Result<Vec<Self>, ValueError<TT::Pos>>
+        where TT: Text,
+              TT::Pos: CharOffset,
+              ET: ExtraValue,
+              DR: DerefTryMut<Target = Datum<TT, ET, DR>>,
+    {
+        forms.iter()
+             .filter(|d| !match d {
+                 Datum::Text(text) => text.chars().all(char::is_whitespace),
+                 _ => is_comment(d),
+             })
+             .map(Self::from_datum)
+             .collect()
+    }
+
+    /// Write `self` as canonical Kul, as a single form, such that converting it
+    /// back gives an equal `Value`.
+    ///
+    /// Strings are bare words when they would read back as themselves, and
+    /// are `{str ...}` forms otherwise.  Floats always have a `.` or an
+    /// exponent.  Map entries are in the order of their keys.  Writing is
+    /// iterative, and so very deep values don't overflow the stack.
+    ///
+    /// # Errors
+    ///
+    /// If the output fails, or if a map key or tag name can't be represented,
+    /// returns the corresponding `WriteError`.
+    pub fn write<W>(&self, out: &mut W) -> Result<(), WriteError>
+        where W: fmt::Write,
+    {
+        enum Work<'v> {
+            Value(&'v Value),
+            /// A map key, and whether it's the first.
+            Key(&'v str, bool),
+            Raw(&'static str),
+        }
+
+        let mut stack = vec![Work::Value(self)];
+        while let Some(work) = stack.pop() {
+            match work {
+                Work::Raw(s) => out.write_str(s)?,
+                Work::Key(k, first) => {
+                    write!(out, "{}{}: ", if first { " " } else { ", " }, word(k)?)?;
+                }
+                Work::Value(value) => match value {
+                    Value::Null => out.write_str("{null}")?,
+                    Value::Bool(b) => write!(out, "{b}")?,
+                    Value::Int(i) => write!(out, "{i}")?,
+                    Value::Float(f) if f.is_finite() => write!(out, "{f:?}")?,
+                    Value::Float(f) => write!(out, "{{float {f:?}}}")?,
+                    Value::String(s) => if is_bare(s) {
+                        out.write_str(s)?;
+                    } else {
+                        write!(out, "{{str {}}}", escape(s, &DefaultCharClassifier))?;
+                    },
+                    Value::List(values) => {
+                        out.write_str("{list")?;
+                        stack.push(Work::Raw("}"));
+                        for (i, v) in values.iter().enumerate().rev() {
+                            stack.push(Work::Value(v));
+                            stack.push(Work::Raw(if i == 0 { " " } else { ", " }));
+                        }
+                    }
+                    Value::Map(entries) => {
+                        out.write_str("{map")?;
+                        stack.push(Work::Raw("}"));
+                        for (i, (k, v)) in entries.iter().enumerate().rev() {
+                            stack.push(Work::Value(v));
+                            stack.push(Work::Key(k, i == 0));
+                        }
+                    }
+                    Value::Tagged(name, v) => {
+                        write!(out, "{{tag {}: ", word(name)?)?;
+                        stack.push(Work::Raw("}"));
+                        stack.push(Work::Value(v));
+                    }
+                },
+            }
+        }
+        Ok(())
+    }
+
+    /// Write `self` as canonical Kul to a new `String`.  See
+    /// [`write`](#method.write).
+    ///
+    /// # Errors
+    ///
+    /// If a map key or tag name can't be represented,
+    /// `WriteError::Unrepresentable` is returned.
+    pub fn to_kul(&self) -> Result<String, WriteError> {
+        let mut s = String::new();
+        self.write(&mut s)?;
+        Ok(s)
+    }
+}
+
+
+/// Convert the operands text of a scalar form of the given name.
+fn typed(name: &str, text: String) -> Result<Value, CombinerErrorKind> {
+    let trimmed = text.trim();
+    let invalid = match name {
+        "null" => return Ok(Value::Null),
+        "str" => return Ok(Value::String(text)),
+        "bool" => match trimmed.parse() {
+            Ok(b) => return Ok(Value::Bool(b)),
+            Err(_) => CombinerErrorKind::InvalidBool,
+        },
+        "int" => match trimmed.parse() {
+            Ok(i) => return Ok(Value::Int(i)),
+            Err(_) => CombinerErrorKind::InvalidInt,
+        },
+        _ => match trimmed.parse() {
+            Ok(f) => return Ok(Value::Float(f)),
+            Err(_) => CombinerErrorKind::InvalidFloat,
+        },
+    };
+    Err(invalid(text))
+}
+
+#[inline]
+fn invalid<Pos>(pos: Pos, kind: CombinerErrorKind) -> ValueError<Pos> {
+    ValueError::Invalid(CombinerError { pos, kind })
+}
+
+/// Convert the operands of a `list`, `map`, or `tag` form.
+fn compound<TT, ET, DR>(name: &str, operands: &Datum<TT, ET, DR>)
+                        -> Result<Value, ValueError<TT::Pos>>
+    where TT: Text,
+          TT::Pos: CharOffset,
+          ET: ExtraValue,
+          DR: DerefTryMut<Target = Datum<TT, ET, DR>>,
+{
+    match name {
+        "list" => items(operands)?.into_iter().map(item_value).collect::<Result<_, _>>()
+                                              .map(Value::List),
+        "map" => {
+            let mut map = BTreeMap::new();
+            for mut item in items(operands)? {
+                let key = item.key()?;
+                let _ = map.insert(key, item_value(item)?);
+            }
+            Ok(Value::Map(map))
+        }
+        _ => {
+            let mut item = single(operands);
+            let name = item.key()?;
+            Ok(Value::Tagged(name, Box::new(item_value(item)?)))
+        }
+    }
+}
+
+
+/// An item of the operands of a form, whose nested forms are borrowed.
+type Item<'d, TT, ET, DR> = stdlib::Item<&'d Datum<TT, ET, DR>, <TT as TextBase>::Pos>;
+
+fn is_comment<TT, ET, DR>(datum: &Datum<TT, ET, DR>) -> bool
+    where TT: Text,
+          DR: DerefTryMut<Target = Datum<TT, ET, DR>>,
+{
+    matches!(datum, Datum::Combination { operator, .. }
+                    if matches!(&**operator, Datum::Text(t) if t.chars().eq(Some('#'))))
+}
+
+/// Give the elements of the operands list, without comments, to the splitter.
+fn split<'d, TT, ET, DR>(operands: &'d Datum<TT, ET, DR>,
+                         items: &mut Items<&'d Datum<TT, ET, DR>, TT::Pos>)
+    where TT: Text,
+          TT::Pos: CharOffset,
+          DR: DerefTryMut<Target = Datum<TT, ET, DR>>,
+{
+    let mut list = operands;
+    while let Datum::List { elem, next } = list {
+        match &**elem {
+            Datum::Text(text) => items.text(text),
+            form if is_comment(form) => (),
+            form => items.form(form),
+        }
+        list = next;
+    }
+}
+
+/// The comma-separated items of the operands, where a trailing comma is
+/// allowed.
+fn items<TT, ET, DR>(operands: &Datum<TT, ET, DR>)
+                     -> Result<Vec<Item<'_, TT, ET, DR>>, CombinerError<TT::Pos>>
+    where TT: Text,
+          TT::Pos: CharOffset,
+          DR: DerefTryMut<Target = Datum<TT, ET, DR>>,
+{
+    let mut items = Items::new(true);
+    split(operands, &mut items);
+    items.finish()
+}
+
+/// The whole operands as a single item.
+fn single<TT, ET, DR>(operands: &Datum<TT, ET, DR>) -> Item<'_, TT, ET, DR>
+    where TT: Text,
+          TT::Pos: CharOffset,
+          DR: DerefTryMut<Target = Datum<TT, ET, DR>>,
+{
+    let mut items = Items::new(false);
+    split(operands, &mut items);
+    items.single()
+}
+
+/// Convert an item to its value, which is either that of its single nested
+/// form or the scalar of its text, or `Value::Null` if it's blank.
+fn item_value<TT, ET, DR>(item: Item<'_, TT, ET, DR>) -> Result<Value, ValueError<TT::Pos>>
+    where TT: Text,
+          TT::Pos: CharOffset,
+          ET: ExtraValue,
+          DR: DerefTryMut<Target = Datum<TT, ET, DR>>,
+{
+    let pos = item.pos();
+    match item.into_parts()? {
+        (_, Some(form)) => Value::from_datum(form),
+        (text, None) if text.trim().is_empty() => Ok(Value::Null),
+        (text, None) => stdlib::scalar(text.trim()).map(Value::from)
+                                                   .map_err(|kind| invalid(pos, kind)),
+    }
+}
+
+
+/// Whether a string can be written as a bare item that reads back as itself.
+fn is_bare(s: &str) -> bool {
+    !s.is_empty()
+    && s.trim() == s
+    && !s.contains(['"', ',', ':', '{', '}', '\\'])
+    && matches!(stdlib::scalar(s), Ok(Extra::Str(_)))
+}
+
+/// A map key or tag name as it is written, quoted and escaped if needed.  There
+/// is no escape for `"` inside quotes, and so keys with it can't be written.
+fn word(s: &str) -> Result<String, WriteError> {
+    if s.is_empty() || s.contains('"') {
+        Err(WriteError::Unrepresentable(s.to_owned()))
+    } else if !s.contains(['"', ',', ':', '{', '}', '\\']) && s.trim() == s {
+        Ok(s.to_owned())
+    } else {
+        Ok(format!("\"{}\"", escape(s, &DefaultCharClassifier)))
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        common::{
+            document::{Document, OperatorBindings, Error as DocError},
+            stream,
+        },
+        text::chunk::CharPos,
+    };
+
+    fn map(entries: Vec<(&str, Value)>) -> Value {
+        Value::Map(entries.into_iter().map(|(k, v)| (k.to_owned(), v)).collect())
+    }
+
+    fn s(s: &str) -> Value {
+        Value::String(s.to_owned())
+    }
+
+    fn parsed(input: &str) -> Result<Vec<Value>, DocError<ValueError<CharPos>>> {
+        let mut bindings = OperatorBindings::default();
+        insert_into(&mut bindings);
+        let doc = Document::<Value>::parse_with(input, bindings)?;
+        Ok(Value::from_forms(doc.forms()).unwrap())
+    }
+
+    fn converted(input: &str) -> Result<Vec<Value>, ValueError<CharPos>> {
+        Value::from_forms(Document::<()>::parse(input).unwrap().forms())
+    }
+
+    const CONFIG: &str = r#"
+        {# A config.}
+        {config
+            {things {list 9, "blah", {map foo: 8.7, bar: asdf}, {null}, {}}}
+            {logging {map err: yes, warn: {bool false}, "a:b": {str  x, y}}}
+        }
+        plain
+    "#;
+
+    #[test]
+    fn profile() {
+        // The operands of `config` are two forms, i.e. a mixed item.
+        assert_eq!(converted(CONFIG),
+                   Err(invalid(CharPos(39), CombinerErrorKind::MixedItem)));
+
+        let config = CONFIG.replace("}}\n            {logging", "}},\n            {logging");
+        let config = config.replace("{config", "{config {list").replace("\n        }", "}}");
+        let value = vec![
+            Value::Tagged(String::from("config"), Box::new(Value::List(vec![
+                Value::Tagged(String::from("things"), Box::new(Value::List(vec![
+                    Value::Int(9), s("blah"),
+                    map(vec![("foo", Value::Float(8.7)), ("bar", s("asdf"))]),
+                    Value::Null, Value::Null,
+                ]))),
+                Value::Tagged(String::from("logging"), Box::new(map(vec![
+                    ("err", s("yes")), ("warn", Value::Bool(false)), ("a:b", s(" x, y")),
+                ]))),
+            ]))),
+            s("plain"),
+        ];
+        assert_eq!(converted(&config), Ok(value.clone()));
+        assert_eq!(parsed(&config), Ok(value));
+
+        assert_eq!(converted("{tag t: 1}{t}{int 1.5}"),
+                   Err(invalid(CharPos(18), CombinerErrorKind::InvalidInt(String::from("1.5")))));
+        assert_eq!(converted("{tag t: 1}{t}"),
+                   Ok(vec![Value::Tagged(String::from("t"), Box::new(Value::Int(1))),
+                           Value::Tagged(String::from("t"), Box::new(Value::Null))]));
+        assert_eq!(converted("{{x} y}"), Err(ValueError::NonTextOperator));
+        assert_eq!(parsed("{map a: 1, b}"),
+                   Err(DocError::FailedCombiner(invalid(CharPos(9),
+                                                        CombinerErrorKind::MissingKey))));
+    }
+
+    #[test]
+    fn canonical() {
+        let value = Value::Tagged(String::from("conf ig"), Box::new(Value::List(vec![
+            Value::Null, Value::Bool(true), Value::Int(-3), Value::Float(1.0),
+            Value::Float(f64::NEG_INFINITY), Value::Float(1e300), s("word"), s("two words"),
+            s("true"), s("12"), s(" pad"), s("a, {b}"), s(""), s("\"q\""),
+            Value::List(vec![]),
+            map(vec![("z", Value::Int(1)), ("a:b", map(vec![])), ("{}", s("c"))]),
+        ])));
+        let kul = value.to_kul().unwrap();
+        assert_eq!(kul, "{tag conf ig: {list {null}, true, -3, 1.0, {float -inf}, 1e300, word, \
+                         two words, {str true}, {str 12}, {str  pad}, {str a, \\{b\\}}, {str }, \
+                         {str \"q\"}, {list}, {map \"a:b\": {map}, z: 1, \"\\{\\}\": c}}}");
+        assert_eq!(converted(&kul), Ok(vec![value.clone()]));
+        assert_eq!(parsed(&kul), Ok(vec![value]));
+
+        assert!(matches!(map(vec![("\"", Value::Null)]).to_kul(),
+                         Err(WriteError::Unrepresentable(_))));
+        assert!(matches!(map(vec![("", Value::Null)]).to_kul(),
+                         Err(WriteError::Unrepresentable(_))));
+    }
+
+    #[test]
+    fn chunked_input() {
+        // The parser also breaks text chunks where the input strings end, and
+        // those breaks must not be taken as escapes.
+        let input = vec!["{list a", ",b}{tag t", r"\,u: c", r",d\", ",e}"];
+        let forms = stream::parse_stream(input.into_iter().map(String::from))
+                        .into_iter()
+                        .collect::<Result<Vec<_>, _>>()
+                        .unwrap();
+        assert_eq!(Value::from_forms(&forms),
+                   Ok(vec![Value::List(vec![s("a"), s("b")]),
+                           Value::Tagged(String::from("t,u"), Box::new(s("c,d,e")))]));
+    }
+
+    #[test]
+    fn deep_write() {
+        let mut value = Value::Int(0);
+        for _ in 0 .. 100_000 {
+            value = Value::List(vec![value]);
+        }
+        let kul = value.to_kul().unwrap();
+        assert_eq!(kul.len(), 100_000 * "{list }".len() + 1);
+        // Dropping is recursive, so take the value apart one level at a time.
+        while let Value::List(mut values) = value {
+            value = values.pop().unwrap();
+        }
+        assert_eq!(value, Value::Int(0));
+    }
+}
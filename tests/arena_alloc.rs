@@ -0,0 +1,71 @@
+use std::mem;
+
+use kul::{
+    Parser, Datum, Text,
+    datum::premade::MutRefDatum,
+    parser::{ArenaDatumAllocator, DatumArena, DefaultCharClassifier,
+             EmptyOperatorBindings},
+    text::{TextVec, TextDatumList, chunk::PosStr},
+};
+
+use kul_shared_tests::{
+    suites::test_suite0,
+    bindings::BasicTestOperatorBindings,
+};
+
+
+type TT = TextVec<PosStr<'static>>;
+
+type DA<'a> = ArenaDatumAllocator<'a, TT, ()>;
+
+fn parser<'a>(arena: &'a mut DatumArena<'a, TT, ()>)
+              -> Parser<DefaultCharClassifier,
+                        DA<'a>,
+                        BasicTestOperatorBindings<DA<'a>>>
+{
+    Parser {
+        classifier: DefaultCharClassifier,
+        allocator: ArenaDatumAllocator::new(arena),
+        bindings: BasicTestOperatorBindings::default(),
+    }
+}
+
+#[test]
+fn suite0() {
+    // Start small to exercise growing through many chunks.
+    let mut arena = DatumArena::with_first_chunk_len(1);
+    test_suite0(parser(&mut arena));
+}
+
+#[test]
+fn long_list() {
+    const LEN: usize = 100_000;
+
+    type TT<'a, 's> = TextDatumList<'a, PosStr<'s>, ()>;
+
+    // With this `Text` type, dropping the arena does not visit the slots.
+    assert!(!mem::needs_drop::<MutRefDatum<'_, TT<'_, '_>, ()>>());
+
+    let input = format!("{{{}}}", "{} ".repeat(LEN));
+    let text = TT::from_str(&input);
+    let mut arena = DatumArena::<TT<'_, '_>, ()>::default();
+    let mut parser = Parser {
+        classifier: DefaultCharClassifier,
+        allocator: ArenaDatumAllocator::new(&mut arena),
+        bindings: EmptyOperatorBindings,
+    };
+    let mut iter = parser.parse(text.iter());
+    let datum = iter.next().unwrap().unwrap();
+    assert!(iter.next().is_none());
+    let mut count = 0;
+    if let Datum::Combination { operands, .. } = datum {
+        let mut cur = &*operands;
+        while let Datum::List { elem, next } = cur {
+            if let Datum::EmptyNest = **elem {
+                count += 1;
+            }
+            cur = next;
+        }
+    }
+    assert_eq!(count, LEN - 1);
+}
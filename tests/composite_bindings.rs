@@ -0,0 +1,99 @@
+#![allow(clippy::type_complexity)]
+
+use std::{cell::RefCell, rc::Rc};
+
+use kul::{
+    Parser, Datum, Text,
+    parser::{BoxDatumAllocator, DefaultCharClassifier, DatumAllocator,
+             OperatorBindings, HashMapOperatorBindings,
+             LayeredOperatorBindings, FallbackOperatorBindings,
+             DisabledOperatorBindings},
+    combiner::{Combiner, OpFn, ApFn},
+    text::{TextVec, chunk::PosStr},
+    datum::DatumBox,
+};
+
+
+type TT = TextVec<PosStr<'static>>;
+type DA = BoxDatumAllocator<TT, ()>;
+type Comb = Combiner<Box<OpFn<DA, ()>>, Box<ApFn<DA, ()>>>;
+type Bindings = HashMapOperatorBindings<DA, Box<OpFn<DA, ()>>, Box<ApFn<DA, ()>>, ()>;
+type TestDatum = Datum<TT, (), DatumBox<TT, ()>>;
+
+fn text(s: &'static str) -> TestDatum {
+    Datum::Text(TT::from_str(s))
+}
+
+fn comb(operator: &'static str, operands: TestDatum) -> TestDatum {
+    Datum::Combination { operator: DatumBox::new(text(operator)),
+                         operands: DatumBox::new(operands) }
+}
+
+fn marker(name: &'static str) -> Comb {
+    Combiner::Applicative(Box::new(move |_, _, _: &mut DA| Ok(Some(text(name)))))
+}
+
+fn bindings(pairs: Vec<(&'static str, Comb)>) -> Bindings {
+    Bindings::new(pairs.into_iter().map(|(n, c)| (text(n), c)).collect())
+}
+
+fn parse<OB>(bindings: OB, input: &'static str) -> Vec<TestDatum>
+    where OB: OperatorBindings<DA, CE = ()>,
+{
+    let mut parser = Parser {
+        classifier: DefaultCharClassifier,
+        allocator: DA::default(),
+        bindings,
+    };
+    parser.parse(TT::from_str(input).iter())
+          .collect::<Result<_, _>>()
+          .unwrap()
+}
+
+#[test]
+fn layered() {
+    let shared = bindings(vec![("a", marker("A")), ("b", marker("B"))]);
+    let overrides = bindings(vec![("b", marker("B2")), ("c", marker("C2"))]);
+    assert_eq!(parse(LayeredOperatorBindings::new(&overrides, &shared),
+                     "{a}{b}{c}{d}"),
+               vec![text("A"), text("B2"), text("C2"), comb("d", Datum::EmptyList)]);
+    assert_eq!(parse(LayeredOperatorBindings::new(&shared, &overrides), "{b}{c}"),
+               vec![text("B"), text("C2")]);
+}
+
+#[test]
+fn fallback() {
+    // Logs the unknown operators and then keeps the forms as if unbound.
+    let log = Rc::new(RefCell::new(Vec::<String>::new()));
+    let logger = Rc::clone(&log);
+    let unknown: Box<ApFn<DA, ()>> = Box::new(move |operator, operands, dalloc: &mut DA| {
+        if let Datum::Text(t) = &operator {
+            logger.borrow_mut().push(t.chars().collect());
+        }
+        Ok(Some(Datum::Combination { operator: dalloc.new_datum(operator)?,
+                                     operands: dalloc.new_datum(operands)? }))
+    });
+    let shared = bindings(vec![("a", marker("A"))]);
+    let fob = FallbackOperatorBindings::new(&shared, Combiner::Applicative(unknown));
+    assert_eq!(parse(&fob, "{a}{x {a}}{y}{}"),
+               vec![text("A"),
+                    comb("x", Datum::List { elem: DatumBox::new(text("A")),
+                                            next: DatumBox::new(Datum::EmptyList) }),
+                    comb("y", Datum::EmptyList),
+                    Datum::EmptyNest]);
+    assert_eq!(*log.borrow(), ["x", "y"]);
+}
+
+#[test]
+fn disabled() {
+    let shared = bindings(vec![("a", marker("A")), ("b", marker("B"))]);
+    let overrides = bindings(vec![("c", marker("C"))]);
+    let layered = LayeredOperatorBindings::new(overrides, &shared);
+    let dob = DisabledOperatorBindings::new(&layered, vec![String::from("b"),
+                                                           String::from("c")]);
+    assert_eq!(parse(&dob, "{a}{b}{c}"),
+               vec![text("A"), comb("b", Datum::EmptyList), comb("c", Datum::EmptyList)]);
+    // A fallback over the masked bindings is called for the masked names.
+    let fob = FallbackOperatorBindings::new(dob, marker("?"));
+    assert_eq!(parse(fob, "{a}{b}{c}"), vec![text("A"), text("?"), text("?")]);
+}
@@ -0,0 +1,123 @@
+#![allow(clippy::type_complexity)]
+
+use kul::{
+    Parser, Datum, Text, TextBase, Error, SubParser,
+    parser::{BoxDatumAllocator, DefaultCharClassifier, TrieOperatorBindings},
+    combiner::{Combiner, OpCtxFn, ApCtxFn, WithContext, Span, Enclosing},
+    text::{TextVec, chunk::{PosStr, StrPos}},
+    datum::DatumBox,
+};
+
+
+type TT = TextVec<PosStr<'static>>;
+type DA = BoxDatumAllocator<TT, ()>;
+type CE = (&'static str, usize, usize);
+type Bindings = TrieOperatorBindings<DA,
+                                     WithContext<Box<OpCtxFn<DA, CE>>>,
+                                     WithContext<Box<ApCtxFn<DA, CE>>>,
+                                     CE>;
+type TestDatum = Datum<TT, (), DatumBox<TT, ()>>;
+
+fn text(s: &'static str) -> TestDatum {
+    Datum::Text(TT::from_str(s))
+}
+
+fn is_text(datum: &TestDatum, s: &str) -> bool {
+    if let Datum::Text(t) = datum { t.chars().eq(s.chars()) } else { false }
+}
+
+fn char_span(span: &Span<StrPos<'_>>) -> (usize, usize) {
+    (span.start.char_pos, span.end.char_pos)
+}
+
+/// Reports its depth and how many forms enclose it.
+fn depth_combiner() -> Box<ApCtxFn<DA, CE>> {
+    Box::new(|_, _, ctx| {
+        Err(Error::FailedCombiner(("depth", ctx.depth,
+                                   ctx.enclosing_operators().count())))
+    })
+}
+
+fn parse(input: &'static str) -> Vec<Result<TestDatum, Error<StrPos<'static>, CE>>> {
+    let mut bindings = Bindings::new();
+    // Reports its span when its operands are empty.
+    let date: Box<OpCtxFn<DA, CE>> = Box::new(|_, operands, ctx| {
+        let (start, end) = char_span(&ctx.span);
+        if operands.is_empty() {
+            Err(Error::FailedCombiner(("empty date", start, end)))
+        } else {
+            Ok(Some(Datum::Extra(())))
+        }
+    });
+    // Must be used directly inside a `{row}`.
+    let cell: Box<ApCtxFn<DA, CE>> = Box::new(|_, operands, ctx| {
+        let (start, end) = char_span(&ctx.span);
+        match ctx.enclosing_operators().next() {
+            Some(op) if is_text(op, "row") => Ok(Some(operands)),
+            _ => Err(Error::FailedCombiner(("cell outside row", start, end))),
+        }
+    });
+    // Sub-parses its operands, with only `{depth}` bound, as inside itself.
+    let sub: Box<OpCtxFn<DA, CE>> = Box::new(|operator, operands, ctx| {
+        let mut bindings = Bindings::new();
+        let _ = bindings.insert("depth", Combiner::Applicative(WithContext(depth_combiner())));
+        let here = Enclosing { operator: &operator, parent: ctx.enclosing };
+        SubParser::new(&DefaultCharClassifier, ctx.allocator, &bindings)
+            .within(ctx.depth, Some(&here))
+            .parse_list(operands.iter())
+            .map(Some)
+    });
+    let _ = bindings.insert("date", Combiner::Operative(WithContext(date)));
+    let _ = bindings.insert("cell", Combiner::Applicative(WithContext(cell)));
+    let _ = bindings.insert("depth", Combiner::Applicative(WithContext(depth_combiner())));
+    let _ = bindings.insert("sub", Combiner::Operative(WithContext(sub)));
+
+    let mut parser = Parser {
+        classifier: DefaultCharClassifier,
+        allocator: DA::default(),
+        bindings,
+    };
+    parser.parse(TT::from_str(input).iter()).collect()
+}
+
+#[test]
+fn span() {
+    assert_eq!(parse("ab\n{date}"),
+               vec![Ok(text("ab\n")),
+                    Err(Error::FailedCombiner(("empty date", 3, 8)))]);
+    assert_eq!(parse("{date 2019-01-01}"), vec![Ok(Datum::Extra(()))]);
+}
+
+#[test]
+fn enclosing() {
+    assert_eq!(parse("{row {cell a}}")[0],
+               Ok(Datum::Combination {
+                   operator: DatumBox::new(text("row")),
+                   operands: DatumBox::new(Datum::List {
+                       elem: DatumBox::new(Datum::List {
+                           elem: DatumBox::new(text("a")),
+                           next: DatumBox::new(Datum::EmptyList) }),
+                       next: DatumBox::new(Datum::EmptyList) }) }));
+    assert_eq!(parse("x {cell}"),
+               vec![Ok(text("x ")),
+                    Err(Error::FailedCombiner(("cell outside row", 2, 7)))]);
+    assert_eq!(parse("{row {table {cell}}}")[0],
+               Err(Error::FailedCombiner(("cell outside row", 12, 17))));
+}
+
+#[test]
+fn depth() {
+    assert_eq!(parse("{depth}"), vec![Err(Error::FailedCombiner(("depth", 1, 0)))]);
+    assert_eq!(parse("{a {b {} {c {depth}}}}")[0],
+               Err(Error::FailedCombiner(("depth", 4, 3))));
+    // Empty nests don't enclose, and neither do operands of operatives.
+    assert_eq!(parse("{{depth}}")[0], Err(Error::FailedCombiner(("depth", 2, 0))));
+    assert_eq!(parse("{date {depth}}"), vec![Ok(Datum::Extra(()))]);
+    // Sub-parses continue from the form whose operands they parse.
+    assert_eq!(parse("{sub {depth}}")[0], Err(Error::FailedCombiner(("depth", 2, 1))));
+    assert_eq!(parse("{a {sub {b {depth}}}}")[0],
+               Err(Error::FailedCombiner(("depth", 4, 3))));
+    // But their nest end characters are checked relative to their start.
+    assert!(matches!(parse("{sub x\\}}")[0],
+                     Err(Error::UnbalancedEndChar(StrPos { char_pos: 7, .. }))));
+}
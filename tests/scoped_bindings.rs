@@ -0,0 +1,120 @@
+#![allow(clippy::type_complexity)]
+
+use kul::{
+    Parser, Datum, Text, SubParser,
+    parser::{BoxDatumAllocator, DefaultCharClassifier, DatumAllocator,
+             OperatorBindings, TrieOperatorBindings, ScopedOperatorBindings},
+    combiner::{Combiner, OpFn, ApFn, OpCtxFn, ApCtxFn, WithContext, Enclosing},
+    text::{TextVec, chunk::PosStr},
+    datum::DatumBox,
+};
+
+
+type TT = TextVec<PosStr<'static>>;
+type DA = BoxDatumAllocator<TT, ()>;
+type Bindings = TrieOperatorBindings<DA, Box<OpFn<DA, ()>>, Box<ApFn<DA, ()>>, ()>;
+type CtxBindings = TrieOperatorBindings<DA,
+                                        WithContext<Box<OpCtxFn<DA, ()>>>,
+                                        WithContext<Box<ApCtxFn<DA, ()>>>,
+                                        ()>;
+type TestDatum = Datum<TT, (), DatumBox<TT, ()>>;
+
+fn text(s: &'static str) -> TestDatum {
+    Datum::Text(TT::from_str(s))
+}
+
+fn list(elems: Vec<TestDatum>) -> TestDatum {
+    elems.into_iter().rev().fold(Datum::EmptyList, |next, elem|
+        Datum::List { elem: DatumBox::new(elem), next: DatumBox::new(next) })
+}
+
+fn comb(operator: &'static str, operands: Vec<TestDatum>) -> TestDatum {
+    Datum::Combination { operator: DatumBox::new(text(operator)),
+                         operands: DatumBox::new(list(operands)) }
+}
+
+fn parse<OB>(bindings: OB, input: &'static str) -> Vec<TestDatum>
+    where OB: OperatorBindings<DA, CE = ()>,
+{
+    let mut parser = Parser {
+        classifier: DefaultCharClassifier,
+        allocator: DA::default(),
+        bindings,
+    };
+    parser.parse(TT::from_str(input).iter())
+          .collect::<Result<_, _>>()
+          .unwrap()
+}
+
+fn marker(name: &'static str) -> Combiner<Box<OpFn<DA, ()>>, Box<ApFn<DA, ()>>> {
+    Combiner::Applicative(Box::new(move |_, _, _: &mut DA| Ok(Some(text(name)))))
+}
+
+#[test]
+fn nested_scopes() {
+    // Returns its arguments list as is.
+    let row: Box<ApFn<DA, ()>> = Box::new(|_, operands, _| Ok(Some(operands)));
+    let row_scope: Bindings = vec![("cell", marker("CELL"))].into_iter().collect();
+    let table_scope = ScopedOperatorBindings::new(
+        vec![("row", Combiner::Applicative(row)),
+             ("cell", marker("OUTER-CELL"))].into_iter().collect::<Bindings>(),
+        [("row", row_scope)]);
+    let root = ScopedOperatorBindings::new(
+        vec![("top", marker("TOP"))].into_iter().collect::<Bindings>(),
+        [("table", table_scope)]);
+
+    assert_eq!(
+        parse(&root, "{table {row {cell a}{top}} {cell b}} {row x}{cell}{top}"),
+        vec![comb("table", vec![list(vec![text("CELL"), text("TOP")]),
+                                text(" "),
+                                text("OUTER-CELL")]),
+             text(" "),
+             comb("row", vec![text("x")]),
+             comb("cell", vec![]),
+             text("TOP")]);
+
+    // The scope applies even to a bound operator, and inner bindings shadow
+    // outer ones.
+    let top: Box<ApFn<DA, ()>> = Box::new(|_, operands, _| Ok(Some(operands)));
+    let inner: Bindings = vec![("top", marker("INNER"))].into_iter().collect();
+    let middle = ScopedOperatorBindings::new(
+        vec![("top", Combiner::Applicative(top))].into_iter().collect::<Bindings>(),
+        [("top", inner)]);
+    let middle: &dyn OperatorBindings<DA, OR = _, AR = _, CE = ()> = &middle;
+    let shadow: Box<ApFn<DA, ()>> = Box::new(|_, operands, dalloc: &mut DA| {
+        Ok(Some(Datum::Combination { operator: dalloc.new_datum(text("S"))?,
+                                     operands: dalloc.new_datum(operands)? }))
+    });
+    let root = ScopedOperatorBindings::new(
+        vec![("shadow", Combiner::Applicative(shadow))].into_iter().collect::<Bindings>(),
+        [("shadow", middle)]);
+    assert_eq!(parse(&root, "{shadow {top {top}}}{top}"),
+               vec![comb("S", vec![list(vec![text("INNER")])]),
+                    comb("top", vec![])]);
+}
+
+#[test]
+fn operative_scopes() {
+    // Parses its operands with `{row}` bound, on the chain of its own form.
+    let table: Box<OpCtxFn<DA, ()>> = Box::new(|operator, operands, ctx| {
+        let rows: Bindings = vec![("row", marker("ROW"))].into_iter().collect();
+        let here = Enclosing { operator: &operator, parent: ctx.enclosing };
+        SubParser::new(&DefaultCharClassifier, ctx.allocator, &rows)
+            .within(ctx.depth, Some(&here))
+            .parse_list_in(operands.iter(), ctx.scope, ctx.user)
+            .map(Some)
+    });
+    let top: Box<ApCtxFn<DA, ()>> = Box::new(|_, _, _| Ok(Some(text("TOP"))));
+    let root = ScopedOperatorBindings::new(
+        vec![("table", Combiner::Operative(WithContext(table))),
+             ("top", Combiner::Applicative(WithContext(top)))]
+            .into_iter().collect::<CtxBindings>(),
+        // The child scope of an operative's operator is also on its chain.
+        [("table", vec![("cell", marker("CELL"))].into_iter().collect::<Bindings>())]);
+
+    assert_eq!(parse(&root, "{table {row}{cell}{top} {table {row}}}{row}{cell}"),
+               vec![list(vec![text("ROW"), text("CELL"), text("TOP"), text(" "),
+                              list(vec![text("ROW")])]),
+                    comb("row", vec![]),
+                    comb("cell", vec![])]);
+}
@@ -0,0 +1,132 @@
+use kul::{
+    Datum, DerefTryMut,
+    datum::DatumBox,
+    common::stream,
+    common::document::{Document, OperatorBindings, Datum as DocDatum, Error as DocError},
+    stdlib::{Extra, CombinerError, CombinerErrorKind, insert_into},
+    text::chunk::CharPos,
+};
+
+
+fn parse(input: &str)
+         -> Result<Vec<DocDatum<Extra>>, DocError<CombinerError<CharPos>>>
+{
+    let mut bindings = OperatorBindings::default();
+    insert_into(&mut bindings);
+    Document::<Extra>::parse_with(input, bindings).map(Document::into_forms)
+}
+
+fn list(elems: Vec<DocDatum<Extra>>) -> DocDatum<Extra> {
+    elems.into_iter().rev().fold(Datum::EmptyList, |next, elem|
+        Datum::List { elem: DatumBox::new(elem), next: DatumBox::new(next) })
+}
+
+fn map(entries: Vec<DocDatum<Extra>>) -> DocDatum<Extra> {
+    Datum::Combination { operator: DatumBox::new(Datum::Extra(Extra::Map)),
+                         operands: DatumBox::new(list(entries)) }
+}
+
+fn failed(pos: usize, kind: CombinerErrorKind)
+          -> Result<Vec<DocDatum<Extra>>, DocError<CombinerError<CharPos>>>
+{
+    Err(DocError::FailedCombiner(CombinerError { pos: CharPos(pos), kind }))
+}
+
+
+#[test]
+fn scalars() {
+    assert_eq!(parse("{int -42}{float 8.7}{bool  true }{str  a, b}{# {nope}}"),
+               Ok(vec![Datum::Extra(Extra::Int(-42)),
+                       Datum::Extra(Extra::Float(8.7)),
+                       Datum::Extra(Extra::Bool(true)),
+                       Datum::Extra(Extra::Str(" a, b".into()))]));
+    assert_eq!(parse("{int 4.2}"), failed(5, CombinerErrorKind::InvalidInt("4.2".into())));
+    assert_eq!(parse("{float x}"), failed(7, CombinerErrorKind::InvalidFloat("x".into())));
+    assert_eq!(parse("{bool yes}"), failed(6, CombinerErrorKind::InvalidBool("yes".into())));
+}
+
+#[test]
+fn lists_and_maps() {
+    assert_eq!(parse(r#"{list 9, "blah, blah", {map foo: 8.7, bar: asdf}, -1.5, inf,}"#),
+               Ok(vec![list(vec![
+                   Datum::Extra(Extra::Int(9)),
+                   Datum::Extra(Extra::Str("blah, blah".into())),
+                   map(vec![
+                       list(vec![Datum::Extra(Extra::Str("foo".into())),
+                                 Datum::Extra(Extra::Float(8.7))]),
+                       list(vec![Datum::Extra(Extra::Str("bar".into())),
+                                 Datum::Extra(Extra::Str("asdf".into()))]),
+                   ]),
+                   Datum::Extra(Extra::Float(-1.5)),
+                   Datum::Extra(Extra::Str("inf".into())),
+               ])]));
+    assert_eq!(parse(r#"{list}{map}{map "a:b": {list false}, c: "x:y"}"#),
+               Ok(vec![Datum::EmptyList,
+                       map(vec![]),
+                       map(vec![
+                           list(vec![Datum::Extra(Extra::Str("a:b".into())),
+                                     list(vec![Datum::Extra(Extra::Bool(false))])]),
+                           list(vec![Datum::Extra(Extra::Str("c".into())),
+                                     Datum::Extra(Extra::Str("x:y".into()))]),
+                       ])]));
+}
+
+#[test]
+fn escaped_commas() {
+    assert_eq!(parse(r"{list a\, b, c\,}"),
+               Ok(vec![list(vec![Datum::Extra(Extra::Str("a, b".into())),
+                                 Datum::Extra(Extra::Str("c,".into()))])]));
+    // Right after the operator, the escape can't be seen.
+    assert_eq!(parse(r"{list \,a}"), failed(7, CombinerErrorKind::EmptyItem));
+}
+
+#[test]
+fn chunked_input() {
+    // The parser also breaks text chunks where the input strings end, and those
+    // breaks must not be taken as escapes.
+    fn items<TT, DR>(mut list: &Datum<TT, Extra, DR>) -> Vec<Extra>
+        where DR: DerefTryMut<Target = Datum<TT, Extra, DR>>,
+    {
+        let mut items = Vec::new();
+        while let Datum::List { elem, next } = list {
+            if let Datum::Extra(extra) = &**elem {
+                items.push(extra.clone());
+            }
+            list = next;
+        }
+        items
+    }
+
+    let mut bindings = stream::OperatorBindings::default();
+    insert_into(&mut bindings);
+    let input = vec!["{list a", ",b}{list c", r"\,d", r",e\", ",f}"];
+    let forms = stream::parse_stream_with(input.into_iter().map(String::from), bindings)
+                    .into_iter()
+                    .collect::<Result<Vec<_>, _>>()
+                    .unwrap();
+    let forms = forms.iter().map(items).collect::<Vec<_>>();
+    assert_eq!(forms, vec![vec![Extra::Str("a".into()), Extra::Str("b".into())],
+                           vec![Extra::Str("c,d".into()), Extra::Str("e,f".into())]]);
+}
+
+#[test]
+fn quotes_end_at_forms() {
+    // Were the quote to continue past the nested form, this would be a single
+    // mixed item.
+    assert_eq!(parse(r#"{list "a {int 1},, b}"#), failed(16, CombinerErrorKind::EmptyItem));
+}
+
+#[test]
+fn invalid_items() {
+    assert_eq!(parse("{list a,, b}"), failed(7, CombinerErrorKind::EmptyItem));
+    assert_eq!(parse("{list a {int 1}}"), failed(6, CombinerErrorKind::MixedItem));
+    assert_eq!(parse("{list {int 1} {int 2}}"), failed(13, CombinerErrorKind::MixedItem));
+    assert_eq!(parse("{list 1, a {int 1}}"), failed(7, CombinerErrorKind::MixedItem));
+    assert_eq!(parse(r#"{list "a}"#),
+               failed(6, CombinerErrorKind::InvalidString("\"a".into())));
+    assert_eq!(parse(r#"{list "a"b}"#),
+               failed(6, CombinerErrorKind::InvalidString("\"a\"b".into())));
+    assert_eq!(parse("{map a}"), failed(5, CombinerErrorKind::MissingKey));
+    assert_eq!(parse("{map : 1}"), failed(5, CombinerErrorKind::MissingKey));
+    assert_eq!(parse("{map {int 1}: 1}"), failed(12, CombinerErrorKind::MissingKey));
+}
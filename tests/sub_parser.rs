@@ -0,0 +1,141 @@
+#![allow(clippy::type_complexity)]
+
+use kul::{
+    Parser, Datum, Text, Error, SubParser,
+    parser::{BoxDatumAllocator, DefaultCharClassifier, CharClassifier,
+             TrieOperatorBindings},
+    combiner::{Combiner, OpFn, ApFn},
+    text::{TextVec, chunk::{PosStr, StrPos}},
+    datum::DatumBox,
+};
+
+
+type TT = TextVec<PosStr<'static>>;
+type DA = BoxDatumAllocator<TT, ()>;
+type Bindings = TrieOperatorBindings<DA, Box<OpFn<DA, ()>>, Box<ApFn<DA, ()>>, ()>;
+type TestDatum = Datum<TT, (), DatumBox<TT, ()>>;
+
+/// Uses square brackets instead of curly braces.
+struct SquareClassifier;
+
+impl CharClassifier for SquareClassifier {
+    fn is_nest_start(&self, c: char) -> bool { c == '[' }
+    fn is_nest_end(&self, c: char) -> bool { c == ']' }
+    fn is_nest_escape(&self, c: char) -> bool { c == '\\' }
+    fn is_whitespace(&self, c: char) -> bool { c.is_whitespace() }
+}
+
+fn text(s: &'static str) -> TestDatum {
+    Datum::Text(TT::from_str(s))
+}
+
+fn list(elems: Vec<TestDatum>) -> TestDatum {
+    elems.into_iter().rev().fold(Datum::EmptyList, |next, elem|
+        Datum::List { elem: DatumBox::new(elem), next: DatumBox::new(next) })
+}
+
+fn comb(operator: &'static str, operands: Vec<TestDatum>) -> TestDatum {
+    Datum::Combination { operator: DatumBox::new(text(operator)),
+                         operands: DatumBox::new(list(operands)) }
+}
+
+fn char_pos(text: &TestDatum) -> Option<usize> {
+    if let Datum::Text(text) = text {
+        text.iter().next().map(|it| it.pos.char_pos)
+    } else {
+        None
+    }
+}
+
+fn parser(bindings: Bindings) -> Parser<DefaultCharClassifier, DA, Bindings> {
+    Parser {
+        classifier: DefaultCharClassifier,
+        allocator: DA::default(),
+        bindings,
+    }
+}
+
+#[test]
+fn operative_sub_parse() {
+    // Re-parses its operands with square brackets as the delimiters and with
+    // its own bindings.
+    let square: Box<OpFn<DA, ()>> = Box::new(|_, operands, dalloc| {
+        let bindings: Bindings = vec![
+            ("b", Combiner::Applicative(Box::new(|_, _, _: &mut DA| Ok(Some(text("B"))))
+                                        as Box<ApFn<DA, ()>>)),
+        ].into_iter().collect();
+        let mut sub = SubParser::new(&SquareClassifier, dalloc, &bindings);
+        let list = sub.parse_list(operands.iter())?;
+        Ok(Some(Datum::Combination { operator: DatumBox::new(text("square")),
+                                     operands: DatumBox::new(list) }))
+    });
+    let mut parser = parser(vec![("sq", Combiner::Operative(square))].into_iter()
+                                                                    .collect());
+
+    let input = TT::from_str("{sq a[b][c d]{b}}{b}");
+    let results: Vec<TestDatum> = parser.parse(input.iter())
+                                        .collect::<Result<_, _>>()
+                                        .unwrap();
+    assert_eq!(results, vec![comb("square", vec![text("a"),
+                                                 text("B"),
+                                                 comb("c", vec![text("d")]),
+                                                 text("{b}")]),
+                             comb("b", vec![])]);
+
+    // Positions are relative to the original source.
+    if let Datum::Combination { operands, .. } = &results[0] {
+        if let Datum::List { next, .. } = &**operands {
+            if let Datum::List { next, .. } = &**next {
+                if let Datum::List { elem, .. } = &**next {
+                    if let Datum::Combination { operator, .. } = &**elem {
+                        assert_eq!(char_pos(operator), Some(9));
+                    } else { panic!() }
+                } else { panic!() }
+            } else { panic!() }
+        } else { panic!() }
+    } else { panic!() }
+
+    // Errors from a sub-parse also have positions relative to the original
+    // source.
+    let input = TT::from_str("{sq x]y}");
+    let result = parser.parse(input.iter()).next().unwrap();
+    assert!(matches!(result, Err(Error::UnbalancedEndChar(StrPos { char_pos: 5, .. }))));
+}
+
+#[test]
+fn escapes_twice() {
+    // The operands text that a sub-parse is given has already had its escapes
+    // processed once, and so a delimiter must be escaped twice to be text.
+    let sub: Box<OpFn<DA, ()>> = Box::new(|_, operands, dalloc| {
+        let bindings = Bindings::new();
+        let mut sub = SubParser::new(&SquareClassifier, dalloc, &bindings);
+        Ok(Some(sub.parse_list(operands.iter())?))
+    });
+    let mut parser = parser(vec![("sq", Combiner::Operative(sub))].into_iter().collect());
+    let input = TT::from_str(r"{sq \[a\] \\\[b\\\]}");
+    let results: Vec<TestDatum> = parser.parse(input.iter())
+                                        .collect::<Result<_, _>>()
+                                        .unwrap();
+    assert_eq!(results, vec![list(vec![comb("a", vec![]), text(" [b]")])]);
+}
+
+#[test]
+fn iterate() {
+    let mut parser = parser(Bindings::new());
+    let mut dalloc = DA::default();
+    let bindings = Bindings::new();
+    let input = TT::from_str("[a] b [c]");
+    {
+        let mut sub = parser.sub_parser();
+        let results: Vec<TestDatum> = sub.parse(input.iter())
+                                         .collect::<Result<_, _>>()
+                                         .unwrap();
+        assert_eq!(results, vec![text("[a] b [c]")]);
+    }
+    let mut sub = SubParser::new(&SquareClassifier, &mut dalloc, &bindings);
+    let results: Vec<TestDatum> = sub.parse(input.iter())
+                                     .collect::<Result<_, _>>()
+                                     .unwrap();
+    assert_eq!(results, vec![comb("a", vec![]), text(" b "), comb("c", vec![])]);
+    assert_eq!(sub.parse_list(TT::from_str("").iter()), Ok(Datum::EmptyList));
+}
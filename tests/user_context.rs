@@ -0,0 +1,99 @@
+#![allow(clippy::type_complexity)]
+
+use kul::{
+    Parser, Datum, Text, SubParser,
+    parser::{BoxDatumAllocator, DefaultCharClassifier, TrieOperatorBindings,
+             StrPairOperatorBindings},
+    combiner::{Combiner, OpCtxFn, ApCtxFn, WithContext},
+    text::{TextVec, chunk::PosStr},
+    datum::DatumBox,
+};
+
+
+type TT = TextVec<PosStr<'static>>;
+type DA = BoxDatumAllocator<TT, usize>;
+type Op = WithContext<Box<OpCtxFn<DA, (), Notes>>>;
+type Ap = WithContext<Box<ApCtxFn<DA, (), Notes>>>;
+type Bindings = TrieOperatorBindings<DA, Op, Ap, ()>;
+type TestDatum = Datum<TT, usize, DatumBox<TT, usize>>;
+
+/// The state shared by the combiners across a parse.
+#[derive(Default, Debug)]
+struct Notes {
+    texts: Vec<String>,
+}
+
+fn text(s: &'static str) -> TestDatum {
+    Datum::Text(TT::from_str(s))
+}
+
+/// Records the text of its first operand as a footnote and is replaced by the
+/// footnote's number.
+fn note() -> Combiner<Op, Ap> {
+    let note: Box<ApCtxFn<DA, (), Notes>> = Box::new(|_, operands, ctx| {
+        let s = match operands {
+            Datum::List { elem, .. } => match &*elem {
+                Datum::Text(t) => t.chars().collect(),
+                _ => String::new(),
+            },
+            _ => String::new(),
+        };
+        ctx.user.texts.push(s);
+        Ok(Some(Datum::Extra(ctx.user.texts.len())))
+    });
+    Combiner::Applicative(WithContext(note))
+}
+
+fn parser<OB>(bindings: OB) -> Parser<DefaultCharClassifier, DA, OB> {
+    Parser {
+        classifier: DefaultCharClassifier,
+        allocator: DA::default(),
+        bindings,
+    }
+}
+
+#[test]
+fn footnotes() {
+    let mut trie = parser(vec![("note", note())].into_iter().collect::<Bindings>());
+    let input = TT::from_str("a{note b} c{note d}{note {note e}}");
+    let mut iter = trie.parse_with_context(input.iter(), Notes::default());
+    let results: Vec<TestDatum> = iter.by_ref().collect::<Result<_, _>>().unwrap();
+    assert_eq!(results, vec![text("a"), Datum::Extra(1), text(" c"),
+                             Datum::Extra(2), Datum::Extra(4)]);
+    assert_eq!(iter.user_context().texts, ["b", "d", "e", ""]);
+    let notes = iter.into_user_context();
+    assert_eq!(notes.texts.len(), 4);
+
+    // The same with bindings of a different type, and with a context that
+    // already has state, which is changed inbetween forms.
+    let mut pairs = parser(StrPairOperatorBindings::<_, _, DA, _, _, ()>::new(
+        [("note", note())]));
+    let input = TT::from_str("{note x}{note y}");
+    let mut iter = pairs.parse_with_context(input.iter(), notes);
+    assert_eq!(iter.next(), Some(Ok(Datum::Extra(5))));
+    iter.user_context_mut().texts.clear();
+    assert_eq!(iter.next(), Some(Ok(Datum::Extra(1))));
+    assert_eq!(iter.next(), None);
+    assert_eq!(iter.into_user_context().texts, ["y"]);
+}
+
+#[test]
+fn sub_parse() {
+    // Re-parses its operands with its own bindings while continuing to share
+    // the state of the enclosing parse.
+    let quote: Box<OpCtxFn<DA, (), Notes>> = Box::new(|_, operands, ctx| {
+        let bindings: Bindings = vec![("n", note())].into_iter().collect();
+        let mut sub = SubParser::new(&DefaultCharClassifier, ctx.allocator, &bindings);
+        sub.parse_list_with_context(operands.iter(), ctx.user).map(Some)
+    });
+    let mut parser = parser(vec![("quote", Combiner::Operative(WithContext(quote))),
+                                 ("note", note())]
+                            .into_iter().collect::<Bindings>());
+    let input = TT::from_str("{note a}{quote {n b}{note c}}{n d}");
+    let mut iter = parser.parse_with_context(input.iter(), Notes::default());
+    let results: Vec<TestDatum> = iter.by_ref().collect::<Result<_, _>>().unwrap();
+    assert_eq!(results[0], TestDatum::Extra(1));
+    assert!(matches!(&results[1], Datum::List { elem, .. } if **elem == TestDatum::Extra(2)));
+    assert!(matches!(&results[2], Datum::Combination { .. }));
+    assert_eq!(iter.into_user_context().texts, ["a", "b"]);
+}